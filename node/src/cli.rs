@@ -8,6 +8,10 @@ pub struct Cli {
 
 	#[structopt(flatten)]
 	pub run: RunCmd,
+
+	/// Index Polkapool and Engine trades into candles served over the `indexer_*` RPC methods.
+	#[structopt(long = "enable-indexer")]
+	pub enable_indexer: bool,
 }
 
 #[derive(Debug, StructOpt)]
@@ -0,0 +1,241 @@
+//! Node-side indexer of Polkapool and Engine trades.
+//!
+//! Follows the finalized chain, decodes the events of each block and aggregates the trades into
+//! OHLCV candles per market, along with the reserves of the pools touched, in a local RocksDB
+//! database served over the `indexer_*` RPC methods. Blocks are read from their state, so a node
+//! indexing from genesis has to keep it with `--pruning archive`.
+
+mod rpc;
+mod store;
+
+#[cfg(test)]
+mod tests;
+
+pub use rpc::{Indexer, IndexerApi};
+pub use store::{Candle, IndexerStore, Market, ReserveSnapshot};
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+
+use codec::{Decode, Encode};
+use futures::StreamExt;
+use log::{error, warn};
+use node_polkadex_runtime::{opaque::Block, AccountId, AssetId, Balance, BlockNumber, Event, Hash};
+use polkapool::types::{ConcentratedPool, WeightedPool};
+use polkapool::{ConcentratedPoolId, WeightedPoolId};
+use polkapool_rpc::PolkapoolRuntimeApi;
+use sc_client_api::{Backend, BlockchainEvents, StorageProvider};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{hashing::{twox_128, twox_64}, storage::StorageKey};
+use sp_runtime::{generic::BlockId, PerThing, Permill};
+
+use store::Trade;
+
+const LOG_TARGET: &str = "indexer";
+
+/// Storage prefix of the Polkapool pallet.
+const POLKAPOOL_PREFIX: &[u8] = b"PolkadexSwapEngine";
+
+type EventRecord = frame_system::EventRecord<Event, Hash>;
+
+/// Reason a block could not be indexed.
+#[derive(Debug)]
+enum IndexError {
+	/// The state of the block was pruned or could not be read.
+	StateUnavailable(String),
+	/// The state of the block could not be decoded, the node is likely outdated.
+	Decode(String),
+	/// Any other failure, such as a runtime API call or a database write.
+	Other(String),
+}
+
+impl fmt::Display for IndexError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			IndexError::StateUnavailable(e) => write!(f, "state unavailable: {}", e),
+			IndexError::Decode(e) => write!(f, "unable to decode the state: {}", e),
+			IndexError::Other(e) => write!(f, "{}", e),
+		}
+	}
+}
+
+fn storage_value_key(module: &[u8], item: &[u8]) -> StorageKey {
+	StorageKey([twox_128(module), twox_128(item)].concat())
+}
+
+/// Key of a `twox_64_concat` map entry.
+fn storage_map_key(module: &[u8], item: &[u8], key: &impl Encode) -> StorageKey {
+	let key = key.encode();
+	StorageKey([&twox_128(module)[..], &twox_128(item)[..], &twox_64(&key)[..], &key[..]].concat())
+}
+
+fn read_storage_value<C, B, V>(client: &C, at: &BlockId<Block>, key: &StorageKey) -> Result<Option<V>, IndexError> where
+	B: Backend<Block>,
+	C: StorageProvider<Block, B>,
+	V: Decode,
+{
+	client.storage(at, key)
+		.map_err(|e| IndexError::StateUnavailable(format!("{:?}", e)))?
+		.map(|data| V::decode(&mut &data.0[..]).map_err(|e| IndexError::Decode(e.what().to_string())))
+		.transpose()
+}
+
+/// Indexes the finalized blocks not indexed yet, then every block as it gets finalized.
+pub async fn run<C, B>(client: Arc<C>, store: Arc<IndexerStore>) where
+	B: Backend<Block>,
+	C: BlockchainEvents<Block> + HeaderBackend<Block> + StorageProvider<Block, B> + ProvideRuntimeApi<Block>,
+	C::Api: PolkapoolRuntimeApi<Block, AccountId, AssetId, Balance>,
+{
+	let mut finality_notifications = client.finality_notification_stream();
+	index_finalized(&*client, &store);
+	while finality_notifications.next().await.is_some() {
+		index_finalized(&*client, &store);
+	}
+}
+
+/// Indexes the blocks between the last indexed one and the last finalized one. Finality
+/// notifications may cover several blocks at once, and none are received while the node
+/// was stopped.
+fn index_finalized<C, B>(client: &C, store: &IndexerStore) where
+	B: Backend<Block>,
+	C: HeaderBackend<Block> + StorageProvider<Block, B> + ProvideRuntimeApi<Block>,
+	C::Api: PolkapoolRuntimeApi<Block, AccountId, AssetId, Balance>,
+{
+	let (mut next, mut timestamp) = match store.last_indexed() {
+		Ok(Some((number, timestamp))) => (number + 1, timestamp),
+		Ok(None) => (0, 0),
+		Err(e) => {
+			warn!(target: LOG_TARGET, "Unable to read the indexer database: {:?}", e);
+			return;
+		}
+	};
+	let finalized = client.info().finalized_number;
+	while next <= finalized {
+		match index_block(client, store, next, timestamp) {
+			Ok(block_timestamp) => timestamp = block_timestamp,
+			Err(e @ IndexError::Decode(_)) => {
+				error!(target: LOG_TARGET, "Indexing stopped at block #{}: {}", next, e);
+				return;
+			}
+			Err(e) => {
+				warn!(target: LOG_TARGET, "Unable to index block #{}: {}", next, e);
+				return;
+			}
+		}
+		next += 1;
+	}
+}
+
+/// Indexes a block and returns its timestamp. Blocks whose state is not available anymore are
+/// recorded without trades, the previous timestamp standing for theirs. Indexing stops at blocks
+/// whose state cannot be decoded rather than skipping their trades.
+fn index_block<C, B>(client: &C, store: &IndexerStore, number: BlockNumber, previous_timestamp: u64) -> Result<u64, IndexError> where
+	B: Backend<Block>,
+	C: HeaderBackend<Block> + StorageProvider<Block, B> + ProvideRuntimeApi<Block>,
+	C::Api: PolkapoolRuntimeApi<Block, AccountId, AssetId, Balance>,
+{
+	let hash = client.hash(number)
+		.map_err(|e| IndexError::Other(format!("{:?}", e)))?
+		.ok_or_else(|| IndexError::Other("unknown block".to_string()))?;
+	let at = BlockId::Hash(hash);
+
+	let block_data = read_storage_value::<_, _, u64>(client, &at, &storage_value_key(b"Timestamp", b"Now"))
+		.and_then(|timestamp| Ok((timestamp, block_trades(client, &at)?)));
+	let (timestamp, trades, reserves) = match block_data {
+		Ok((timestamp, (trades, pools))) => {
+			let mut reserves = Vec::with_capacity(pools.len());
+			for pool in pools {
+				if let Market::Pool(currency_id_0, currency_id_1) = pool {
+					let pool_reserves = client.runtime_api().get_pool_reserves(&at, currency_id_0, currency_id_1)
+						.map_err(|e| IndexError::Other(format!("{:?}", e)))?;
+					reserves.push((pool, pool_reserves));
+				}
+			}
+			(timestamp.unwrap_or(previous_timestamp), trades, reserves)
+		}
+		Err(IndexError::StateUnavailable(e)) => {
+			warn!(target: LOG_TARGET, "Block #{} indexed without trades, its state is unavailable: {}", number, e);
+			(previous_timestamp, Vec::new(), Vec::new())
+		}
+		Err(e) => return Err(e),
+	};
+
+	store.import_block(number, timestamp, &trades, &reserves).map_err(|e| IndexError::Other(e.to_string()))?;
+	Ok(timestamp)
+}
+
+/// Trades of a block and the pools whose reserves changed.
+fn block_trades<C, B>(client: &C, at: &BlockId<Block>) -> Result<(Vec<Trade>, BTreeSet<Market>), IndexError> where
+	B: Backend<Block>,
+	C: StorageProvider<Block, B>,
+{
+	let events: Vec<EventRecord> = read_storage_value(client, at, &storage_value_key(b"System", b"Events"))?
+		.unwrap_or_default();
+	event_trades(events, |pool| pool_fee(client, at, pool))
+}
+
+/// Pool of a swap whose event does not carry the fee.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum FeePool {
+	Weighted(WeightedPoolId),
+	Concentrated(ConcentratedPoolId),
+}
+
+/// Fee rate of a weighted or concentrated liquidity pool, fixed at its creation.
+fn pool_fee<C, B>(client: &C, at: &BlockId<Block>, pool: FeePool) -> Result<Permill, IndexError> where
+	B: Backend<Block>,
+	C: StorageProvider<Block, B>,
+{
+	let fee = match pool {
+		FeePool::Weighted(pool_id) => {
+			read_storage_value::<_, _, WeightedPool<AssetId, Balance>>(client, at, &storage_map_key(POLKAPOOL_PREFIX, b"WeightedPools", &pool_id))?
+				.map(|pool| pool.swap_fee)
+		}
+		FeePool::Concentrated(pool_id) => {
+			read_storage_value::<_, _, ConcentratedPool<AssetId>>(client, at, &storage_map_key(POLKAPOOL_PREFIX, b"ConcentratedPools", &pool_id))?
+				.map(|pool| pool.fee)
+		}
+	};
+	fee.ok_or_else(|| IndexError::Decode(format!("{:?} swapped through but not stored", pool)))
+}
+
+/// Trades of the events of a block and the pools whose reserves changed. Swaps of weighted and
+/// concentrated liquidity pools are charged the fee rate of their pool on the supply amount.
+fn event_trades<F>(events: Vec<EventRecord>, fee_of: F) -> Result<(Vec<Trade>, BTreeSet<Market>), IndexError> where
+	F: Fn(FeePool) -> Result<Permill, IndexError>,
+{
+	let mut trades = Vec::new();
+	let mut pools = BTreeSet::new();
+	for record in events {
+		match record.event {
+			Event::polkapool(polkapool::RawEvent::PoolSwap(supply_currency_id, supply_amount, target_currency_id, target_amount, fee)) => {
+				let trade = Trade::pool_swap(supply_currency_id, supply_amount, target_currency_id, target_amount, fee);
+				pools.insert(trade.market);
+				trades.push(trade);
+			}
+			Event::polkapool(polkapool::RawEvent::WeightedPoolSwap(_, pool_id, supply_currency_id, supply_amount, target_currency_id, target_amount)) => {
+				let fee = fee_of(FeePool::Weighted(pool_id))?.mul_ceil(supply_amount);
+				let market = Market::weighted_pool(pool_id, supply_currency_id, target_currency_id);
+				trades.push(Trade::swap(market, supply_currency_id, supply_amount, target_amount, fee));
+			}
+			Event::polkapool(polkapool::RawEvent::ConcentratedPoolSwap(_, pool_id, supply_currency_id, supply_amount, target_currency_id, target_amount)) => {
+				let fee = fee_of(FeePool::Concentrated(pool_id))?.mul_ceil(supply_amount);
+				let market = Market::concentrated_pool(pool_id, supply_currency_id, target_currency_id);
+				trades.push(Trade::swap(market, supply_currency_id, supply_amount, target_amount, fee));
+			}
+			Event::polkapool(polkapool::RawEvent::AddLiquidity(_, currency_id_0, _, currency_id_1, _, _)) |
+			Event::polkapool(polkapool::RawEvent::RemoveLiquidity(_, currency_id_0, _, currency_id_1, _, _)) |
+			Event::polkapool(polkapool::RawEvent::ProtocolFeeMinted(currency_id_0, currency_id_1, _)) |
+			Event::polkapool(polkapool::RawEvent::FlashSwap(_, currency_id_0, _, _, currency_id_1, _, _)) => {
+				pools.insert(Market::pool(currency_id_0, currency_id_1));
+			}
+			Event::orderbook_engine(orderbook_engine::RawEvent::TradeSettled(_, _, asset_id, price, quantity)) => {
+				trades.push(Trade::orderbook_trade(asset_id, price, quantity));
+			}
+			_ => {}
+		}
+	}
+	Ok((trades, pools))
+}
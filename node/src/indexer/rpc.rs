@@ -0,0 +1,88 @@
+//! RPC interface of the trade indexer.
+
+use std::sync::Arc;
+
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use node_polkadex_runtime::AssetId;
+
+use super::store::{Candle, IndexerStore, Market, ReserveSnapshot, INTERVALS};
+
+/// Maximum number of intervals covered by a single query.
+const MAX_INTERVALS_PER_QUERY: u64 = 1_000;
+
+/// Error code returned when the database cannot be read.
+const DATABASE_ERROR: i64 = 1;
+/// Error code returned when the requested range is invalid.
+const INVALID_RANGE_ERROR: i64 = 2;
+
+/// Indexer RPC methods.
+#[rpc]
+pub trait IndexerApi {
+	/// Returns the candles of a market between `from` and `to`, in milliseconds since the unix epoch.
+	/// `interval` is one of 1m, 5m, 15m, 1h, 4h or 1d in milliseconds.
+	#[rpc(name = "indexer_getCandles")]
+	fn get_candles(&self, market: Market, interval: u64, from: u64, to: u64) -> Result<Vec<Candle>>;
+
+	/// Returns the volume, fees and price range of a market over the last 24 hours.
+	#[rpc(name = "indexer_get24hStats")]
+	fn get_24h_stats(&self, market: Market) -> Result<Candle>;
+
+	/// Returns the reserves of a pool at the end of each interval between `from` and `to`.
+	#[rpc(name = "indexer_getReserveHistory")]
+	fn get_reserve_history(&self, currency_id_a: AssetId, currency_id_b: AssetId, interval: u64, from: u64, to: u64) -> Result<Vec<ReserveSnapshot>>;
+}
+
+/// Implements the [`IndexerApi`] RPC trait by reading the indexer database.
+pub struct Indexer {
+	store: Arc<IndexerStore>,
+}
+
+impl Indexer {
+	/// Creates a new instance of the indexer RPC handler.
+	pub fn new(store: Arc<IndexerStore>) -> Self {
+		Self { store }
+	}
+}
+
+fn database_error(err: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(DATABASE_ERROR),
+		message: "Unable to read the indexer database.".into(),
+		data: Some(format!("{:?}", err).into()),
+	}
+}
+
+fn invalid_range_error(message: &str) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(INVALID_RANGE_ERROR),
+		message: message.into(),
+		data: None,
+	}
+}
+
+fn ensure_valid_range(interval: u64, from: u64, to: u64) -> Result<()> {
+	if !INTERVALS.contains(&interval) {
+		return Err(invalid_range_error("Unsupported interval."));
+	}
+	if from > to || (to - from) / interval >= MAX_INTERVALS_PER_QUERY {
+		return Err(invalid_range_error("Invalid or too large time range."));
+	}
+	Ok(())
+}
+
+impl IndexerApi for Indexer {
+	fn get_candles(&self, market: Market, interval: u64, from: u64, to: u64) -> Result<Vec<Candle>> {
+		ensure_valid_range(interval, from, to)?;
+		self.store.candles(market.normalized(), interval, from, to).map_err(database_error)
+	}
+
+	fn get_24h_stats(&self, market: Market) -> Result<Candle> {
+		self.store.stats_24h(market.normalized()).map_err(database_error)
+	}
+
+	fn get_reserve_history(&self, currency_id_a: AssetId, currency_id_b: AssetId, interval: u64, from: u64, to: u64) -> Result<Vec<ReserveSnapshot>> {
+		ensure_valid_range(interval, from, to)?;
+		self.store.reserve_history(Market::pool(currency_id_a, currency_id_b), interval, from, to).map_err(database_error)
+	}
+}
@@ -0,0 +1,304 @@
+//! RocksDB backed storage of the indexed candles and reserve snapshots.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use codec::{Decode, Encode};
+use kvdb::KeyValueDB;
+use kvdb_rocksdb::{Database, DatabaseConfig};
+use node_polkadex_runtime::{AssetId, Balance, BlockNumber};
+use polkapool::{ConcentratedPoolId, WeightedPoolId};
+use serde::{Deserialize, Serialize};
+use sp_runtime::{FixedPointNumber, FixedU128};
+
+/// Candle intervals kept by the indexer, in milliseconds.
+pub const INTERVALS: [u64; 6] = [60_000, 300_000, 900_000, 3_600_000, 14_400_000, 86_400_000];
+/// Interval of the candles aggregated into the 24h statistics.
+const STATS_INTERVAL: u64 = 300_000;
+/// Length of the window of the 24h statistics.
+const STATS_WINDOW: u64 = 86_400_000;
+
+const COLUMN_CANDLES: u32 = 0;
+const COLUMN_RESERVES: u32 = 1;
+const COLUMN_META: u32 = 2;
+const NUM_COLUMNS: u32 = 3;
+
+/// Key of the number and timestamp of the last indexed block in `COLUMN_META`.
+const LAST_INDEXED_KEY: &[u8] = b"last_indexed";
+
+/// Market whose trades are aggregated into candles.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Market {
+	/// Polkapool pair, ordered as in the pallet. The first asset is the base asset.
+	Pool(AssetId, AssetId),
+	/// Engine orderbook of an asset.
+	Orderbook(AssetId),
+	/// Two assets of a weighted pool, ordered like `Pool`.
+	WeightedPool(WeightedPoolId, AssetId, AssetId),
+	/// Concentrated liquidity pool, its assets ordered like `Pool`.
+	ConcentratedPool(ConcentratedPoolId, AssetId, AssetId),
+}
+
+/// Assets of a pair in the order the pallet stores it.
+fn ordered(currency_id_a: AssetId, currency_id_b: AssetId) -> (AssetId, AssetId) {
+	if currency_id_a > currency_id_b {
+		(currency_id_a, currency_id_b)
+	} else {
+		(currency_id_b, currency_id_a)
+	}
+}
+
+impl Market {
+	/// Polkapool market of two assets, in the order the pallet stores the pair.
+	pub fn pool(currency_id_a: AssetId, currency_id_b: AssetId) -> Self {
+		let (base, quote) = ordered(currency_id_a, currency_id_b);
+		Market::Pool(base, quote)
+	}
+
+	/// Market of two assets of a weighted pool.
+	pub fn weighted_pool(pool_id: WeightedPoolId, currency_id_a: AssetId, currency_id_b: AssetId) -> Self {
+		let (base, quote) = ordered(currency_id_a, currency_id_b);
+		Market::WeightedPool(pool_id, base, quote)
+	}
+
+	/// Market of a concentrated liquidity pool.
+	pub fn concentrated_pool(pool_id: ConcentratedPoolId, currency_id_a: AssetId, currency_id_b: AssetId) -> Self {
+		let (base, quote) = ordered(currency_id_a, currency_id_b);
+		Market::ConcentratedPool(pool_id, base, quote)
+	}
+
+	/// Same market with the assets of a pool in the order the pallet stores the pair.
+	pub fn normalized(self) -> Self {
+		match self {
+			Market::Pool(currency_id_a, currency_id_b) => Market::pool(currency_id_a, currency_id_b),
+			Market::WeightedPool(pool_id, currency_id_a, currency_id_b) => Market::weighted_pool(pool_id, currency_id_a, currency_id_b),
+			Market::ConcentratedPool(pool_id, currency_id_a, currency_id_b) => Market::concentrated_pool(pool_id, currency_id_a, currency_id_b),
+			market => market,
+		}
+	}
+
+	/// Asset whose price the market quotes.
+	fn base(&self) -> AssetId {
+		match *self {
+			Market::Pool(base, _) | Market::WeightedPool(_, base, _) | Market::ConcentratedPool(_, base, _) => base,
+			Market::Orderbook(asset_id) => asset_id,
+		}
+	}
+}
+
+/// A single trade of a block.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Trade {
+	pub market: Market,
+	/// Price of the base asset in the quote asset, as the inner value of a `FixedU128`.
+	pub price: u128,
+	pub base_amount: Balance,
+	pub quote_amount: Balance,
+	pub base_fee: Balance,
+	pub quote_fee: Balance,
+}
+
+impl Trade {
+	/// Trade through a Polkapool pair, the fee being charged in the supplied asset.
+	pub fn pool_swap(supply_currency_id: AssetId, supply_amount: Balance, target_currency_id: AssetId, target_amount: Balance, fee: Balance) -> Self {
+		let market = Market::pool(supply_currency_id, target_currency_id);
+		Self::swap(market, supply_currency_id, supply_amount, target_amount, fee)
+	}
+
+	/// Swap of a pool in `market`, the fee being charged in the supplied asset.
+	pub fn swap(market: Market, supply_currency_id: AssetId, supply_amount: Balance, target_amount: Balance, fee: Balance) -> Self {
+		let (base_amount, quote_amount, base_fee, quote_fee) = if market.base() == supply_currency_id {
+			(supply_amount, target_amount, fee, 0)
+		} else {
+			(target_amount, supply_amount, 0, fee)
+		};
+		let price = FixedU128::checked_from_rational(quote_amount, base_amount).unwrap_or_default().into_inner();
+		Trade { market, price, base_amount, quote_amount, base_fee, quote_fee }
+	}
+
+	/// Trade on an Engine orderbook, `price` being an 18 decimals fixed point number.
+	pub fn orderbook_trade(asset_id: AssetId, price: Balance, quantity: Balance) -> Self {
+		let quote_amount = FixedU128::from_inner(price).saturating_mul_int(quantity);
+		Trade { market: Market::Orderbook(asset_id), price, base_amount: quantity, quote_amount, base_fee: 0, quote_fee: 0 }
+	}
+}
+
+/// OHLCV candle of a market. Prices are inner values of `FixedU128`.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candle {
+	/// Start of the candle, in milliseconds since the unix epoch.
+	pub start: u64,
+	pub open: u128,
+	pub high: u128,
+	pub low: u128,
+	pub close: u128,
+	/// Traded amount of the base asset, fees included.
+	pub base_volume: Balance,
+	/// Traded amount of the quote asset, fees included.
+	pub quote_volume: Balance,
+	/// Fees charged in the base asset.
+	pub base_fee: Balance,
+	/// Fees charged in the quote asset.
+	pub quote_fee: Balance,
+	/// Number of trades.
+	pub trades: u32,
+}
+
+impl Candle {
+	fn add_trade(&mut self, trade: &Trade) {
+		if self.trades == 0 {
+			self.open = trade.price;
+			self.high = trade.price;
+			self.low = trade.price;
+		}
+		self.high = self.high.max(trade.price);
+		self.low = self.low.min(trade.price);
+		self.close = trade.price;
+		self.base_volume = self.base_volume.saturating_add(trade.base_amount);
+		self.quote_volume = self.quote_volume.saturating_add(trade.quote_amount);
+		self.base_fee = self.base_fee.saturating_add(trade.base_fee);
+		self.quote_fee = self.quote_fee.saturating_add(trade.quote_fee);
+		self.trades = self.trades.saturating_add(1);
+	}
+
+	/// Appends a later candle to this one.
+	fn merge(&mut self, later: &Candle) {
+		if later.trades == 0 {
+			return;
+		}
+		if self.trades == 0 {
+			self.open = later.open;
+			self.high = later.high;
+			self.low = later.low;
+		}
+		self.high = self.high.max(later.high);
+		self.low = self.low.min(later.low);
+		self.close = later.close;
+		self.base_volume = self.base_volume.saturating_add(later.base_volume);
+		self.quote_volume = self.quote_volume.saturating_add(later.quote_volume);
+		self.base_fee = self.base_fee.saturating_add(later.base_fee);
+		self.quote_fee = self.quote_fee.saturating_add(later.quote_fee);
+		self.trades = self.trades.saturating_add(later.trades);
+	}
+}
+
+/// Reserves of a Polkapool pair at the end of an interval.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReserveSnapshot {
+	/// Start of the interval, in milliseconds since the unix epoch.
+	pub start: u64,
+	/// Last block of the interval that changed the reserves.
+	pub block: BlockNumber,
+	pub reserve_0: Balance,
+	pub reserve_1: Balance,
+	pub total_shares: Balance,
+}
+
+/// Candles and reserve snapshots kept in a local RocksDB database.
+pub struct IndexerStore {
+	db: Database,
+}
+
+fn bucket_key(prefix: &impl Encode, interval: u64, start: u64) -> Vec<u8> {
+	let mut key = prefix.encode();
+	key.extend_from_slice(&interval.to_be_bytes());
+	key.extend_from_slice(&start.to_be_bytes());
+	key
+}
+
+fn decode<V: Decode>(data: &[u8]) -> io::Result<V> {
+	V::decode(&mut &data[..]).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.what()))
+}
+
+impl IndexerStore {
+	/// Opens or creates the database at `path`.
+	pub fn open(path: &Path) -> io::Result<Self> {
+		let config = DatabaseConfig::with_columns(NUM_COLUMNS);
+		let db = Database::open(&config, &path.to_string_lossy())?;
+		Ok(IndexerStore { db })
+	}
+
+	/// Number and timestamp of the last indexed block.
+	pub fn last_indexed(&self) -> io::Result<Option<(BlockNumber, u64)>> {
+		self.db.get(COLUMN_META, LAST_INDEXED_KEY)?.map(|data| decode(&data)).transpose()
+	}
+
+	/// Stores the trades and the pool reserves of a block, along with the block as last indexed.
+	pub fn import_block(&self, number: BlockNumber, timestamp: u64, trades: &[Trade], reserves: &[(Market, (Balance, Balance, Balance))]) -> io::Result<()> {
+		let mut candles: BTreeMap<Vec<u8>, Candle> = BTreeMap::new();
+		for trade in trades {
+			for interval in INTERVALS.iter() {
+				let start = timestamp - timestamp % interval;
+				let key = bucket_key(&trade.market, *interval, start);
+				if !candles.contains_key(&key) {
+					let candle = match self.db.get(COLUMN_CANDLES, &key)? {
+						Some(data) => decode(&data)?,
+						None => Candle { start, ..Default::default() },
+					};
+					candles.insert(key.clone(), candle);
+				}
+				if let Some(candle) = candles.get_mut(&key) {
+					candle.add_trade(trade);
+				}
+			}
+		}
+
+		let mut transaction = self.db.transaction();
+		for (key, candle) in candles {
+			transaction.put(COLUMN_CANDLES, &key, &candle.encode());
+		}
+		for (market, (reserve_0, reserve_1, total_shares)) in reserves {
+			for interval in INTERVALS.iter() {
+				let start = timestamp - timestamp % interval;
+				let snapshot = ReserveSnapshot { start, block: number, reserve_0: *reserve_0, reserve_1: *reserve_1, total_shares: *total_shares };
+				transaction.put(COLUMN_RESERVES, &bucket_key(market, *interval, start), &snapshot.encode());
+			}
+		}
+		transaction.put(COLUMN_META, LAST_INDEXED_KEY, &(number, timestamp).encode());
+		self.db.write(transaction)
+	}
+
+	/// Candles of `market` starting between `from` and `to`, intervals without trades omitted.
+	pub fn candles(&self, market: Market, interval: u64, from: u64, to: u64) -> io::Result<Vec<Candle>> {
+		let mut candles = Vec::new();
+		let mut start = from - from % interval;
+		while start <= to {
+			if let Some(data) = self.db.get(COLUMN_CANDLES, &bucket_key(&market, interval, start))? {
+				candles.push(decode(&data)?);
+			}
+			start += interval;
+		}
+		Ok(candles)
+	}
+
+	/// Reserve snapshots of a pool for the intervals between `from` and `to` in which its reserves changed.
+	pub fn reserve_history(&self, market: Market, interval: u64, from: u64, to: u64) -> io::Result<Vec<ReserveSnapshot>> {
+		let mut snapshots = Vec::new();
+		let mut start = from - from % interval;
+		while start <= to {
+			if let Some(data) = self.db.get(COLUMN_RESERVES, &bucket_key(&market, interval, start))? {
+				snapshots.push(decode(&data)?);
+			}
+			start += interval;
+		}
+		Ok(snapshots)
+	}
+
+	/// Trades of `market` over the 24 hours up to the last indexed block, aggregated into one candle.
+	pub fn stats_24h(&self, market: Market) -> io::Result<Candle> {
+		let now = match self.last_indexed()? {
+			Some((_, timestamp)) => timestamp,
+			None => return Ok(Candle::default()),
+		};
+		let from = now.saturating_sub(STATS_WINDOW);
+		let mut stats = Candle { start: from, ..Default::default() };
+		for candle in self.candles(market, STATS_INTERVAL, from, now)? {
+			stats.merge(&candle);
+		}
+		Ok(stats)
+	}
+}
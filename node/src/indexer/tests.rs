@@ -0,0 +1,174 @@
+use super::*;
+use frame_system::Phase;
+use sp_core::H160;
+use sp_runtime::{FixedPointNumber, FixedU128};
+
+const BTC: AssetId = AssetId::ChainsafeErc20(H160([1; 20]));
+const PDX: AssetId = AssetId::POLKADEX;
+const MINUTE: u64 = 60_000;
+const DAY: u64 = 86_400_000;
+
+fn alice() -> AccountId {
+	AccountId::from([1; 32])
+}
+
+fn record(event: Event) -> EventRecord {
+	EventRecord { phase: Phase::ApplyExtrinsic(0), event, topics: vec![] }
+}
+
+fn price(quote_amount: Balance, base_amount: Balance) -> u128 {
+	FixedU128::checked_from_rational(quote_amount, base_amount).unwrap().into_inner()
+}
+
+fn open_store() -> (tempfile::TempDir, IndexerStore) {
+	let dir = tempfile::tempdir().unwrap();
+	let store = IndexerStore::open(dir.path()).unwrap();
+	(dir, store)
+}
+
+fn fixed_fee(_: FeePool) -> Result<Permill, IndexError> {
+	Ok(Permill::from_perthousand(3))
+}
+
+#[test]
+fn pool_swaps_are_quoted_in_the_second_asset() {
+	// BTC is the base asset of the pair whichever asset is supplied
+	let sell = Trade::pool_swap(BTC, 1_000, PDX, 2_000, 3);
+	assert_eq!(sell.market, Market::Pool(BTC, PDX));
+	assert_eq!((sell.base_amount, sell.quote_amount, sell.base_fee, sell.quote_fee), (1_000, 2_000, 3, 0));
+	assert_eq!(sell.price, price(2_000, 1_000));
+
+	let buy = Trade::pool_swap(PDX, 2_000, BTC, 1_000, 6);
+	assert_eq!(buy.market, Market::Pool(BTC, PDX));
+	assert_eq!((buy.base_amount, buy.quote_amount, buy.base_fee, buy.quote_fee), (1_000, 2_000, 0, 6));
+	assert_eq!(Market::Pool(PDX, BTC).normalized(), Market::Pool(BTC, PDX));
+}
+
+#[test]
+fn event_trades_index_swaps_of_every_pool_kind() {
+	let events = vec![
+		record(Event::polkapool(polkapool::RawEvent::PoolSwap(BTC, 1_000, PDX, 2_000, 3))),
+		record(Event::polkapool(polkapool::RawEvent::WeightedPoolSwap(alice(), 4, PDX, 2_000, BTC, 990))),
+		record(Event::polkapool(polkapool::RawEvent::ConcentratedPoolSwap(alice(), 7, BTC, 1_000, PDX, 1_990))),
+		record(Event::orderbook_engine(orderbook_engine::RawEvent::TradeSettled(alice(), alice(), BTC, FixedU128::from(2).into_inner(), 10))),
+	];
+
+	let (trades, pools) = event_trades(events, fixed_fee).unwrap();
+
+	assert_eq!(trades, vec![
+		Trade::pool_swap(BTC, 1_000, PDX, 2_000, 3),
+		Trade::swap(Market::WeightedPool(4, BTC, PDX), PDX, 2_000, 990, 6),
+		Trade::swap(Market::ConcentratedPool(7, BTC, PDX), BTC, 1_000, 1_990, 3),
+		Trade::orderbook_trade(BTC, FixedU128::from(2).into_inner(), 10),
+	]);
+	// Only the reserves of constant product and StableSwap pairs are tracked
+	assert_eq!(pools.into_iter().collect::<Vec<_>>(), vec![Market::Pool(BTC, PDX)]);
+}
+
+#[test]
+fn event_trades_track_pools_changed_without_trades() {
+	let events = vec![
+		record(Event::polkapool(polkapool::RawEvent::AddLiquidity(alice(), BTC, 1_000, PDX, 2_000, 1_000))),
+		record(Event::polkapool(polkapool::RawEvent::FlashSwap(alice(), BTC, 100, 101, PDX, 0, 0))),
+	];
+
+	let (trades, pools) = event_trades(events, fixed_fee).unwrap();
+
+	assert!(trades.is_empty());
+	assert_eq!(pools.into_iter().collect::<Vec<_>>(), vec![Market::Pool(BTC, PDX)]);
+}
+
+#[test]
+fn event_trades_fail_without_the_pool_fee() {
+	let events = vec![record(Event::polkapool(polkapool::RawEvent::WeightedPoolSwap(alice(), 4, PDX, 2_000, BTC, 990)))];
+
+	let result = event_trades(events, |pool| Err(IndexError::Decode(format!("{:?}", pool))));
+
+	match result {
+		Err(IndexError::Decode(e)) => assert_eq!(e, "Weighted(4)"),
+		result => panic!("Unexpected result {:?}", result),
+	}
+}
+
+#[test]
+fn import_block_aggregates_trades_into_candles() {
+	let (_dir, store) = open_store();
+	assert_eq!(store.last_indexed().unwrap(), None);
+
+	let market = Market::Pool(BTC, PDX);
+	store.import_block(1, MINUTE + 1_000, &[
+		Trade::pool_swap(BTC, 1_000, PDX, 2_000, 3),
+		Trade::pool_swap(PDX, 3_000, BTC, 1_000, 9),
+	], &[]).unwrap();
+	// A later block of the same minute
+	store.import_block(2, MINUTE + 7_000, &[Trade::pool_swap(BTC, 1_000, PDX, 1_000, 3)], &[]).unwrap();
+	// The next minute
+	store.import_block(3, 2 * MINUTE, &[Trade::pool_swap(BTC, 1_000, PDX, 4_000, 3)], &[]).unwrap();
+
+	assert_eq!(store.last_indexed().unwrap(), Some((3, 2 * MINUTE)));
+	assert_eq!(store.candles(market, MINUTE, 0, 3 * MINUTE).unwrap(), vec![
+		Candle {
+			start: MINUTE,
+			open: price(2_000, 1_000),
+			high: price(3_000, 1_000),
+			low: price(1_000, 1_000),
+			close: price(1_000, 1_000),
+			base_volume: 3_000,
+			quote_volume: 6_000,
+			base_fee: 6,
+			quote_fee: 9,
+			trades: 3,
+		},
+		Candle {
+			start: 2 * MINUTE,
+			open: price(4_000, 1_000),
+			high: price(4_000, 1_000),
+			low: price(4_000, 1_000),
+			close: price(4_000, 1_000),
+			base_volume: 1_000,
+			quote_volume: 4_000,
+			base_fee: 3,
+			quote_fee: 0,
+			trades: 1,
+		},
+	]);
+
+	// Larger intervals merge both minutes
+	let hourly = store.candles(market, 3_600_000, 0, 0).unwrap();
+	assert_eq!(hourly.len(), 1);
+	assert_eq!((hourly[0].open, hourly[0].close, hourly[0].trades), (price(2_000, 1_000), price(4_000, 1_000), 4));
+	// Other markets are unaffected
+	assert!(store.candles(Market::Orderbook(BTC), MINUTE, 0, 3 * MINUTE).unwrap().is_empty());
+}
+
+#[test]
+fn reserve_history_keeps_the_last_reserves_of_each_interval() {
+	let (_dir, store) = open_store();
+	let market = Market::Pool(BTC, PDX);
+
+	store.import_block(1, MINUTE, &[], &[(market, (1_000, 2_000, 1_000))]).unwrap();
+	store.import_block(2, MINUTE + 6_000, &[], &[(market, (1_100, 1_900, 1_000))]).unwrap();
+	store.import_block(3, 3 * MINUTE, &[], &[(market, (1_200, 1_800, 1_000))]).unwrap();
+
+	let history = store.reserve_history(market, MINUTE, 0, 4 * MINUTE).unwrap();
+	assert_eq!(history, vec![
+		ReserveSnapshot { start: MINUTE, block: 2, reserve_0: 1_100, reserve_1: 1_900, total_shares: 1_000 },
+		ReserveSnapshot { start: 3 * MINUTE, block: 3, reserve_0: 1_200, reserve_1: 1_800, total_shares: 1_000 },
+	]);
+}
+
+#[test]
+fn stats_24h_only_cover_the_last_day() {
+	let (_dir, store) = open_store();
+	let market = Market::Pool(BTC, PDX);
+	assert_eq!(store.stats_24h(market).unwrap(), Candle::default());
+
+	store.import_block(1, MINUTE, &[Trade::pool_swap(BTC, 1_000, PDX, 2_000, 3)], &[]).unwrap();
+	store.import_block(2, DAY, &[Trade::pool_swap(BTC, 1_000, PDX, 3_000, 3)], &[]).unwrap();
+	store.import_block(3, DAY + 10 * MINUTE, &[Trade::pool_swap(BTC, 1_000, PDX, 4_000, 3)], &[]).unwrap();
+
+	let stats = store.stats_24h(market).unwrap();
+	assert_eq!(stats.start, 10 * MINUTE);
+	assert_eq!((stats.open, stats.close, stats.trades), (price(3_000, 1_000), price(4_000, 1_000), 2));
+	assert_eq!((stats.base_volume, stats.quote_volume, stats.base_fee), (2_000, 7_000, 6));
+}
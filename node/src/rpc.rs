@@ -7,12 +7,13 @@
 
 use std::sync::Arc;
 
-use node_polkadex_runtime::{opaque::Block, AccountId, Balance, Index};
+use node_polkadex_runtime::{opaque::Block, AccountId, AssetId, Balance, Index};
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::{Error as BlockChainError, HeaderMetadata, HeaderBackend};
 use sp_block_builder::BlockBuilder;
 pub use sc_rpc_api::DenyUnsafe;
 use sp_transaction_pool::TransactionPool;
+use crate::indexer::IndexerStore;
 
 
 /// Full client dependencies.
@@ -23,6 +24,8 @@ pub struct FullDeps<C, P> {
 	pub pool: Arc<P>,
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
+	/// Trade indexer database, if the indexer is enabled.
+	pub indexer: Option<Arc<IndexerStore>>,
 }
 
 /// Instantiate all full RPC extensions.
@@ -34,17 +37,21 @@ pub fn create_full<C, P>(
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+	C::Api: polkapool_rpc::PolkapoolRuntimeApi<Block, AccountId, AssetId, Balance>,
 	C::Api: BlockBuilder<Block>,
 	P: TransactionPool + 'static,
 {
 	use substrate_frame_rpc_system::{FullSystem, SystemApi};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
+	use polkapool_rpc::{Polkapool, PolkapoolApi};
+	use crate::indexer::{Indexer, IndexerApi};
 
 	let mut io = jsonrpc_core::IoHandler::default();
 	let FullDeps {
 		client,
 		pool,
 		deny_unsafe,
+		indexer,
 	} = deps;
 
 	io.extend_with(
@@ -55,6 +62,16 @@ pub fn create_full<C, P>(
 		TransactionPaymentApi::to_delegate(TransactionPayment::new(client.clone()))
 	);
 
+	io.extend_with(
+		PolkapoolApi::to_delegate(Polkapool::new(client.clone()))
+	);
+
+	if let Some(store) = indexer {
+		io.extend_with(
+			IndexerApi::to_delegate(Indexer::new(store))
+		);
+	}
+
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
 	// to call into the runtime.
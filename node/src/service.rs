@@ -97,8 +97,19 @@ fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
 	Err("Remote Keystore not supported.")
 }
 
-/// Builds a new service for a full client.
-pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError> {
+/// Opens the trade indexer database next to the chain database.
+fn open_indexer_store(config: &Configuration) -> Result<Arc<crate::indexer::IndexerStore>, ServiceError> {
+	let path = config.database.path()
+		.and_then(|path| path.parent())
+		.map(|path| path.join("indexer"))
+		.ok_or_else(|| ServiceError::Other("The indexer needs an on-disk chain database.".into()))?;
+	let store = crate::indexer::IndexerStore::open(&path)
+		.map_err(|e| ServiceError::Other(format!("Unable to open the indexer database: {}", e)))?;
+	Ok(Arc::new(store))
+}
+
+/// Builds a new service for a full client, along with the trade indexer if `enable_indexer` is set.
+pub fn new_full(mut config: Configuration, enable_indexer: bool) -> Result<TaskManager, ServiceError> {
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -147,6 +158,17 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	let enable_grandpa = !config.disable_grandpa;
 	let prometheus_registry = config.prometheus_registry().cloned();
 
+	let indexer_store = if enable_indexer {
+		let store = open_indexer_store(&config)?;
+		task_manager.spawn_handle().spawn_blocking(
+			"indexer",
+			crate::indexer::run::<_, FullBackend>(client.clone(), store.clone()),
+		);
+		Some(store)
+	} else {
+		None
+	};
+
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
@@ -156,6 +178,7 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 				client: client.clone(),
 				pool: pool.clone(),
 				deny_unsafe,
+				indexer: indexer_store.clone(),
 			};
 
 			crate::rpc::create_full(deps)
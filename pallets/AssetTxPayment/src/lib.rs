@@ -0,0 +1,204 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Transaction fees paid in any asset with a Polkapool pool against the native asset.
+//!
+//! `ChargeAssetTxPayment` replaces `ChargeTransactionPayment` in the signed extensions of the
+//! runtime. Fees are computed in the native asset as usual. When the signer picks another asset,
+//! the fee is converted at the time-weighted average price of its pool against the native asset,
+//! so that traders holding only bridged assets can still transact. Like native fees, a share of
+//! the asset paid goes to the fee account and the rest is burned.
+
+use codec::{Decode, Encode};
+use frame_support::{decl_event, decl_module};
+use frame_support::traits::Get;
+use frame_support::weights::{DispatchInfo, PostDispatchInfo};
+use orml_traits::MultiCurrency;
+use pallet_transaction_payment::{ChargeTransactionPayment, OnChargeTransaction};
+use sp_core::U256;
+use sp_runtime::{FixedPointNumber, FixedPointOperand, FixedU128, Permill, SaturatedConversion};
+use sp_runtime::traits::{DispatchInfoOf, Dispatchable, PostDispatchInfoOf, Saturating, SignedExtension, Zero};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionPriority, TransactionValidity, TransactionValidityError, ValidTransaction,
+};
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+/// Balance in which fees are computed.
+pub type NativeBalanceOf<T> = <<T as pallet_transaction_payment::Config>::OnChargeTransaction as OnChargeTransaction<T>>::Balance;
+/// Balance of the assets of Polkapool.
+pub type AssetBalanceOf<T> = <T as polkapool::Config>::Balance;
+
+/// Configure the pallet by specifying the parameters and types on which it depends.
+pub trait Config: pallet_transaction_payment::Config + polkapool::Config {
+    /// Because this pallet emits events, it depends on the runtime's definition of an event.
+    type Event: From<Event<Self>> + Into<<Self as frame_system::Config>::Event>;
+    /// Asset in which fees are computed
+    type NativeAssetId: Get<Self::CurrencyId>;
+    /// Account receiving the fees paid in other assets
+    type FeeAccount: Get<Self::AccountId>;
+    /// Share of the fees paid in other assets sent to `FeeAccount`, the rest is burned
+    type FeeAccountShare: Get<Permill>;
+    /// Number of blocks of the average pool price fees are converted at, so that moving the
+    /// price within a block does not lower the fee
+    type PriceWindow: Get<Self::BlockNumber>;
+}
+
+decl_event!(
+	pub enum Event<T> where
+		AccountId = <T as frame_system::Config>::AccountId,
+		AssetId = <T as polkapool::Config>::CurrencyId,
+		AssetBalance = AssetBalanceOf<T>,
+		NativeBalance = NativeBalanceOf<T>
+	{
+		/// Transaction fee paid in a non-native asset, of which `burned` was not sent to the fee
+		/// account. \[who, asset_id, asset_amount, burned, fee\]
+		AssetTxFeePaid(AccountId, AssetId, AssetBalance, AssetBalance, NativeBalance),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+	}
+}
+
+impl<T: Config> Module<T> {
+    /// Amount of `asset_id` worth `fee` at the average price of its pool against the native
+    /// asset over the last `PriceWindow` blocks, rounded up.
+    pub fn asset_fee(asset_id: T::CurrencyId, fee: NativeBalanceOf<T>) -> Option<AssetBalanceOf<T>> {
+        let price = polkapool::Module::<T>::get_twap(T::NativeAssetId::get(), asset_id, T::PriceWindow::get()).ok()?;
+        if price.is_zero() {
+            return None;
+        }
+        let numerator = U256::from(fee.saturated_into::<u128>()).checked_mul(U256::from(price.into_inner()))?;
+        let denominator = U256::from(FixedU128::accuracy());
+        let amount = (numerator + denominator - U256::one()) / denominator;
+        if amount > U256::from(u128::max_value()) {
+            return None;
+        }
+        Some(amount.low_u128().saturated_into())
+    }
+
+    /// Deposits `amount` of `asset_id` to `who`, returning the amount credited. Nothing is
+    /// credited when the deposit fails or would leave the account below the existential deposit.
+    fn deposit_asset(asset_id: T::CurrencyId, who: &T::AccountId, amount: AssetBalanceOf<T>) -> AssetBalanceOf<T> {
+        let before = <T as polkapool::Config>::Currency::free_balance(asset_id, who);
+        match <T as polkapool::Config>::Currency::deposit(asset_id, who, amount) {
+            Ok(()) => <T as polkapool::Config>::Currency::free_balance(asset_id, who).saturating_sub(before),
+            Err(_) => Zero::zero(),
+        }
+    }
+}
+
+/// State kept between `pre_dispatch` and `post_dispatch`.
+pub enum Pre<T: Config> {
+    /// Fee paid in the native asset by `ChargeTransactionPayment`.
+    Native((NativeBalanceOf<T>, T::AccountId, <<T as pallet_transaction_payment::Config>::OnChargeTransaction as OnChargeTransaction<T>>::LiquidityInfo)),
+    /// Fee paid in another asset. The tip, payer, asset, fee and amount of the asset paid.
+    Asset(NativeBalanceOf<T>, T::AccountId, T::CurrencyId, NativeBalanceOf<T>, AssetBalanceOf<T>),
+}
+
+impl<T: Config> Default for Pre<T> {
+    fn default() -> Self {
+        Pre::Native(Default::default())
+    }
+}
+
+/// Pays the transaction fee, plus a tip, in the native asset or in `asset_id`.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct ChargeAssetTxPayment<T: Config> {
+    #[codec(compact)]
+    tip: NativeBalanceOf<T>,
+    asset_id: Option<T::CurrencyId>,
+}
+
+impl<T: Config> ChargeAssetTxPayment<T> {
+    pub fn from(tip: NativeBalanceOf<T>, asset_id: Option<T::CurrencyId>) -> Self {
+        Self { tip, asset_id }
+    }
+
+    /// Asset the fee is paid in, `None` for the native asset.
+    fn fee_asset(&self) -> Option<T::CurrencyId> {
+        self.asset_id.filter(|asset_id| *asset_id != T::NativeAssetId::get())
+    }
+}
+
+impl<T: Config> sp_std::fmt::Debug for ChargeAssetTxPayment<T> {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        write!(f, "ChargeAssetTxPayment<{:?}, {:?}>", self.tip, self.asset_id)
+    }
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        Ok(())
+    }
+}
+
+impl<T: Config + Send + Sync> SignedExtension for ChargeAssetTxPayment<T> where
+    <T as frame_system::Config>::Call: Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>,
+    NativeBalanceOf<T>: Send + Sync + From<u64> + FixedPointOperand,
+{
+    const IDENTIFIER: &'static str = "ChargeAssetTxPayment";
+    type AccountId = T::AccountId;
+    type Call = <T as frame_system::Config>::Call;
+    type AdditionalSigned = ();
+    type Pre = Pre<T>;
+
+    fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(&self, who: &Self::AccountId, call: &Self::Call, info: &DispatchInfoOf<Self::Call>, len: usize) -> TransactionValidity {
+        let asset_id = match self.fee_asset() {
+            Some(asset_id) => asset_id,
+            None => return ChargeTransactionPayment::<T>::from(self.tip).validate(who, call, info, len),
+        };
+        let fee = pallet_transaction_payment::Module::<T>::compute_fee(len as u32, info, self.tip);
+        let asset_fee = Module::<T>::asset_fee(asset_id, fee).ok_or(InvalidTransaction::Payment)?;
+        <T as polkapool::Config>::Currency::ensure_can_withdraw(asset_id, who, asset_fee)
+            .map_err(|_| InvalidTransaction::Payment)?;
+        Ok(ValidTransaction {
+            priority: fee.saturated_into::<TransactionPriority>(),
+            ..Default::default()
+        })
+    }
+
+    fn pre_dispatch(self, who: &Self::AccountId, call: &Self::Call, info: &DispatchInfoOf<Self::Call>, len: usize) -> Result<Self::Pre, TransactionValidityError> {
+        let asset_id = match self.fee_asset() {
+            Some(asset_id) => asset_id,
+            None => return ChargeTransactionPayment::<T>::from(self.tip).pre_dispatch(who, call, info, len).map(Pre::Native),
+        };
+        let fee = pallet_transaction_payment::Module::<T>::compute_fee(len as u32, info, self.tip);
+        let asset_fee = Module::<T>::asset_fee(asset_id, fee).ok_or(InvalidTransaction::Payment)?;
+        // Withdrawn whole and split once the actual fee is known
+        <T as polkapool::Config>::Currency::withdraw(asset_id, who, asset_fee)
+            .map_err(|_| InvalidTransaction::Payment)?;
+        Ok(Pre::Asset(self.tip, who.clone(), asset_id, fee, asset_fee))
+    }
+
+    fn post_dispatch(pre: Self::Pre, info: &DispatchInfoOf<Self::Call>, post_info: &PostDispatchInfoOf<Self::Call>, len: usize, result: &sp_runtime::DispatchResult) -> Result<(), TransactionValidityError> {
+        let (tip, who, asset_id, fee, asset_fee) = match pre {
+            Pre::Native(pre) => return ChargeTransactionPayment::<T>::post_dispatch(pre, info, post_info, len, result),
+            Pre::Asset(tip, who, asset_id, fee, asset_fee) => (tip, who, asset_id, fee, asset_fee),
+        };
+        let actual_fee = pallet_transaction_payment::Module::<T>::compute_actual_fee(len as u32, info, post_info, tip);
+        // Refunds the overpaid share of the asset, at the price it was paid at
+        let refund = if actual_fee < fee {
+            let overpaid = U256::from(fee.saturating_sub(actual_fee).saturated_into::<u128>());
+            let refund = U256::from(asset_fee.saturated_into::<u128>()).saturating_mul(overpaid) / U256::from(fee.saturated_into::<u128>());
+            refund.low_u128().saturated_into()
+        } else {
+            Zero::zero()
+        };
+        // A refund the payer cannot receive is paid, and what the fee account cannot receive is
+        // burned along with the rest
+        let paid = asset_fee.saturating_sub(Module::<T>::deposit_asset(asset_id, &who, refund));
+        let burned = paid.saturating_sub(Module::<T>::deposit_asset(asset_id, &T::FeeAccount::get(), T::FeeAccountShare::get() * paid));
+        Module::<T>::deposit_event(RawEvent::AssetTxFeePaid(who, asset_id, paid, burned, actual_fee));
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use crate as asset_tx_payment;
+use frame_support::parameter_types;
+use frame_support::weights::IdentityFee;
+use frame_system::EnsureRoot;
+use orml_traits::parameter_type_with_key;
+use pallet_transaction_payment::CurrencyAdapter;
+use polkadex_primitives::assets::AssetId;
+use sp_core::{H160, H256};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	Permill,
+};
+
+pub type AccountId = u64;
+pub type Balance = u128;
+pub type Amount = i128;
+pub type BlockNumber = u64;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+/// Receiver of the share of the fees paid in non-native assets that is not burned.
+pub const FEE_ACCOUNT: AccountId = 99;
+
+pub const NATIVE: AssetId = AssetId::POLKADEX;
+pub const USDT: AssetId = AssetId::ChainsafeErc20(H160([1u8; 20]));
+/// Minimum balance of a `USDT` account, no other asset has one.
+pub const USDT_EXISTENTIAL_DEPOSIT: Balance = 1_000;
+/// Asset without a pool against the native asset.
+pub const DAI: AssetId = AssetId::ChainsafeErc20(H160([2u8; 20]));
+
+/// Balance of each asset given to `ALICE` and `BOB` at genesis.
+pub const INITIAL_BALANCE: Balance = 1_000_000_000_000_000_000;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet. Fees in the native asset are paid from
+// `Balances`, while the pools and the other assets live in `Tokens`.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
+		TransactionPayment: pallet_transaction_payment::{Module, Storage},
+		Tokens: orml_tokens::{Module, Call, Storage, Event<T>, Config<T>},
+		Polkapool: polkapool::{Module, Call, Storage, Event<T>},
+		AssetTxPayment: asset_tx_payment::{Module, Event<T>},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const SS58Prefix: u8 = 42;
+}
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<Balance>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = SS58Prefix;
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: Balance = 1;
+	pub const MaxLocks: u32 = 50;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = MaxLocks;
+	type Balance = Balance;
+	type Event = Event;
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const TransactionByteFee: Balance = 1;
+}
+
+impl pallet_transaction_payment::Config for Test {
+	type OnChargeTransaction = CurrencyAdapter<Balances, ()>;
+	type TransactionByteFee = TransactionByteFee;
+	type WeightToFee = IdentityFee<Balance>;
+	type FeeMultiplierUpdate = ();
+}
+
+parameter_type_with_key! {
+	pub ExistentialDeposits: |currency_id: AssetId| -> Balance {
+		if *currency_id == USDT {
+			USDT_EXISTENTIAL_DEPOSIT
+		} else {
+			0
+		}
+	};
+}
+
+impl orml_tokens::Config for Test {
+	type Event = Event;
+	type Balance = Balance;
+	type Amount = Amount;
+	type CurrencyId = AssetId;
+	type WeightInfo = ();
+	type ExistentialDeposits = ExistentialDeposits;
+	type OnDust = ();
+}
+
+parameter_types! {
+	pub const TradingPathLimit: usize = 3;
+	pub const TwapObservationPeriod: u32 = 10;
+	pub const MaxAmplification: u32 = 1_000_000;
+	pub const MinAmplificationRampDuration: BlockNumber = 10;
+	pub const MaxWeightedPoolAssets: u32 = 4;
+	pub const MaxTicksPerPool: u32 = 4;
+	pub const ProtocolFeeAccount: AccountId = FEE_ACCOUNT;
+}
+
+impl polkapool::Config for Test {
+	type Event = Event;
+	type TradingPathLimit = TradingPathLimit;
+	type Balance = Balance;
+	type TwapObservationPeriod = TwapObservationPeriod;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+	type MaxAmplification = MaxAmplification;
+	type MinAmplificationRampDuration = MinAmplificationRampDuration;
+	type MaxWeightedPoolAssets = MaxWeightedPoolAssets;
+	type MaxTicksPerPool = MaxTicksPerPool;
+	type CurrencyId = AssetId;
+	type Currency = Tokens;
+	type ProtocolFeeAccount = ProtocolFeeAccount;
+	type Call = Call;
+}
+
+parameter_types! {
+	pub const NativeAssetId: AssetId = NATIVE;
+	pub const FeeAccount: AccountId = FEE_ACCOUNT;
+	pub const FeeAccountShare: Permill = Permill::from_percent(20);
+	pub const PriceWindow: BlockNumber = 5;
+}
+
+impl asset_tx_payment::Config for Test {
+	type Event = Event;
+	type NativeAssetId = NativeAssetId;
+	type FeeAccount = FeeAccount;
+	type FeeAccountShare = FeeAccountShare;
+	type PriceWindow = PriceWindow;
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(ALICE, INITIAL_BALANCE), (BOB, INITIAL_BALANCE)],
+	}.assimilate_storage(&mut t).unwrap();
+
+	let mut endowed_accounts = Vec::new();
+	for who in [ALICE, BOB].iter() {
+		for currency_id in [NATIVE, USDT, DAI].iter() {
+			endowed_accounts.push((*who, *currency_id, INITIAL_BALANCE));
+		}
+	}
+	orml_tokens::GenesisConfig::<Test> { endowed_accounts }.assimilate_storage(&mut t).unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	// Events are not recorded in the genesis block
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
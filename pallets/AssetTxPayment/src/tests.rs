@@ -0,0 +1,201 @@
+use crate::{mock::*, ChargeAssetTxPayment, Pre, RawEvent};
+use frame_support::assert_ok;
+use frame_support::weights::{DispatchInfo, Pays, PostDispatchInfo};
+use orml_traits::MultiCurrency;
+use polkapool::types::PoolKind;
+use sp_runtime::Permill;
+use sp_runtime::traits::SignedExtension;
+use sp_runtime::transaction_validity::{InvalidTransaction, TransactionValidityError};
+
+/// Reserve of the native asset in the USDT pool, which holds twice as much USDT.
+const POOL: Balance = 1_000_000_000_000_000;
+const LEN: usize = 10;
+
+fn call() -> Call {
+	Call::System(frame_system::Call::remark(vec![]))
+}
+
+fn info(weight: u64) -> DispatchInfo {
+	DispatchInfo { weight, ..Default::default() }
+}
+
+fn post_info(actual_weight: u64) -> PostDispatchInfo {
+	PostDispatchInfo { actual_weight: Some(actual_weight), pays_fee: Pays::Yes }
+}
+
+/// Registers the USDT pool and waits for its average price over `PriceWindow` blocks.
+fn register_pool() {
+	assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), USDT, NATIVE, 2 * POOL, POOL, PoolKind::ConstantProduct));
+	System::set_block_number(1 + PriceWindow::get());
+}
+
+/// Share of `amount` sent to the fee account, the rest is burned.
+fn fee_account_share(amount: Balance) -> Balance {
+	Permill::from_percent(20) * amount
+}
+
+fn last_event() -> Event {
+	System::events().pop().expect("Event expected").event
+}
+
+#[test]
+fn asset_fee_converts_at_pool_price() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(AssetTxPayment::asset_fee(USDT, 100), None);
+		register_pool();
+		assert_eq!(AssetTxPayment::asset_fee(USDT, 100), Some(200));
+		assert_eq!(AssetTxPayment::asset_fee(DAI, 100), None);
+	});
+}
+
+#[test]
+fn asset_fee_needs_a_price_history() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), USDT, NATIVE, 2 * POOL, POOL, PoolKind::ConstantProduct));
+		assert_eq!(AssetTxPayment::asset_fee(USDT, 100), None);
+		System::set_block_number(PriceWindow::get());
+		assert_eq!(AssetTxPayment::asset_fee(USDT, 100), None);
+		System::set_block_number(1 + PriceWindow::get());
+		assert_eq!(AssetTxPayment::asset_fee(USDT, 100), Some(200));
+	});
+}
+
+#[test]
+fn asset_fee_ignores_price_moves_within_the_block() {
+	new_test_ext().execute_with(|| {
+		register_pool();
+		// Makes the native asset cheap in USDT right before paying
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(ALICE), vec![NATIVE, USDT], POOL, 0, None));
+		assert_eq!(AssetTxPayment::asset_fee(USDT, 100), Some(200));
+
+		// The new price only counts for the blocks it lasts
+		System::set_block_number(2 + PriceWindow::get());
+		let fee = AssetTxPayment::asset_fee(USDT, 1_000_000).unwrap();
+		assert!(fee < 2_000_000 && fee > 1_600_000);
+	});
+}
+
+#[test]
+fn asset_fee_rounds_up() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), USDT, NATIVE, POOL, 3 * POOL, PoolKind::ConstantProduct));
+		System::set_block_number(1 + PriceWindow::get());
+		assert_eq!(AssetTxPayment::asset_fee(USDT, 3), Some(1));
+		assert_eq!(AssetTxPayment::asset_fee(USDT, 4), Some(2));
+	});
+}
+
+#[test]
+fn native_fee_is_charged_from_balances() {
+	new_test_ext().execute_with(|| {
+		let fee = TransactionPayment::compute_fee(LEN as u32, &info(1000), 0);
+		let pre = ChargeAssetTxPayment::<Test>::from(0, None).pre_dispatch(&BOB, &call(), &info(1000), LEN).unwrap();
+		assert_eq!(Balances::free_balance(BOB), INITIAL_BALANCE - fee);
+		assert!(matches!(pre, Pre::Native(_)));
+		assert_ok!(ChargeAssetTxPayment::<Test>::post_dispatch(pre, &info(1000), &post_info(1000), LEN, &Ok(())));
+		assert_eq!(Tokens::free_balance(USDT, &BOB), INITIAL_BALANCE);
+	});
+}
+
+#[test]
+fn native_asset_id_uses_native_fee() {
+	new_test_ext().execute_with(|| {
+		register_pool();
+		let fee = TransactionPayment::compute_fee(LEN as u32, &info(1000), 0);
+		let pre = ChargeAssetTxPayment::<Test>::from(0, Some(NATIVE)).pre_dispatch(&BOB, &call(), &info(1000), LEN).unwrap();
+		assert!(matches!(pre, Pre::Native(_)));
+		assert_eq!(Balances::free_balance(BOB), INITIAL_BALANCE - fee);
+		assert_eq!(Tokens::free_balance(NATIVE, &BOB), INITIAL_BALANCE);
+	});
+}
+
+#[test]
+fn asset_fee_is_split_between_fee_account_and_burn() {
+	new_test_ext().execute_with(|| {
+		register_pool();
+		let issuance = Tokens::total_issuance(USDT);
+		let fee = TransactionPayment::compute_fee(LEN as u32, &info(1000), 0);
+		let pre = ChargeAssetTxPayment::<Test>::from(0, Some(USDT)).pre_dispatch(&BOB, &call(), &info(1000), LEN).unwrap();
+		assert_eq!(Balances::free_balance(BOB), INITIAL_BALANCE);
+		assert_eq!(Tokens::free_balance(USDT, &BOB), INITIAL_BALANCE - 2 * fee);
+		assert_eq!(Tokens::free_balance(USDT, &FEE_ACCOUNT), 0);
+
+		assert_ok!(ChargeAssetTxPayment::<Test>::post_dispatch(pre, &info(1000), &post_info(1000), LEN, &Ok(())));
+		assert_eq!(Tokens::free_balance(USDT, &FEE_ACCOUNT), fee_account_share(2 * fee));
+		assert_eq!(Tokens::total_issuance(USDT), issuance - 2 * fee + fee_account_share(2 * fee));
+		assert_eq!(last_event(), Event::asset_tx_payment(RawEvent::AssetTxFeePaid(BOB, USDT, 2 * fee, 2 * fee - fee_account_share(2 * fee), fee)));
+	});
+}
+
+#[test]
+fn asset_fee_is_refunded_for_unused_weight() {
+	new_test_ext().execute_with(|| {
+		register_pool();
+		let fee = TransactionPayment::compute_fee(LEN as u32, &info(1000), 0);
+		let pre = ChargeAssetTxPayment::<Test>::from(0, Some(USDT)).pre_dispatch(&BOB, &call(), &info(1000), LEN).unwrap();
+		assert_ok!(ChargeAssetTxPayment::<Test>::post_dispatch(pre, &info(1000), &post_info(500), LEN, &Ok(())));
+
+		let actual_fee = fee - 500;
+		assert_eq!(Tokens::free_balance(USDT, &BOB), INITIAL_BALANCE - 2 * actual_fee);
+		assert_eq!(Tokens::free_balance(USDT, &FEE_ACCOUNT), fee_account_share(2 * actual_fee));
+		assert_eq!(last_event(), Event::asset_tx_payment(RawEvent::AssetTxFeePaid(BOB, USDT, 2 * actual_fee, 2 * actual_fee - fee_account_share(2 * actual_fee), actual_fee)));
+	});
+}
+
+#[test]
+fn asset_fee_refund_below_existential_deposit_is_paid() {
+	new_test_ext().execute_with(|| {
+		register_pool();
+		let fee = TransactionPayment::compute_fee(LEN as u32, &info(1000), 0);
+		// Bob only holds the fee, so his account is removed when paying it
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), ALICE, USDT, INITIAL_BALANCE - 2 * fee));
+		let pre = ChargeAssetTxPayment::<Test>::from(0, Some(USDT)).pre_dispatch(&BOB, &call(), &info(1000), LEN).unwrap();
+		assert_eq!(Tokens::free_balance(USDT, &BOB), 0);
+
+		// A refund of 200 cannot open his account again
+		assert!(2 * 100 < USDT_EXISTENTIAL_DEPOSIT);
+		assert_ok!(ChargeAssetTxPayment::<Test>::post_dispatch(pre, &info(1000), &post_info(900), LEN, &Ok(())));
+		assert_eq!(Tokens::free_balance(USDT, &BOB), 0);
+		assert_eq!(Tokens::free_balance(USDT, &FEE_ACCOUNT), fee_account_share(2 * fee));
+		assert_eq!(
+			last_event(),
+			Event::asset_tx_payment(RawEvent::AssetTxFeePaid(BOB, USDT, 2 * fee, 2 * fee - fee_account_share(2 * fee), fee - 100)),
+		);
+	});
+}
+
+#[test]
+fn asset_without_pool_is_rejected() {
+	new_test_ext().execute_with(|| {
+		register_pool();
+		assert_eq!(
+			ChargeAssetTxPayment::<Test>::from(0, Some(DAI)).validate(&BOB, &call(), &info(1000), LEN),
+			Err(TransactionValidityError::Invalid(InvalidTransaction::Payment))
+		);
+		assert!(ChargeAssetTxPayment::<Test>::from(0, Some(DAI)).pre_dispatch(&BOB, &call(), &info(1000), LEN).is_err());
+		assert_eq!(Tokens::free_balance(DAI, &BOB), INITIAL_BALANCE);
+	});
+}
+
+#[test]
+fn insufficient_asset_balance_is_rejected() {
+	new_test_ext().execute_with(|| {
+		register_pool();
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), ALICE, USDT, INITIAL_BALANCE));
+		assert_eq!(
+			ChargeAssetTxPayment::<Test>::from(0, Some(USDT)).validate(&BOB, &call(), &info(1000), LEN),
+			Err(TransactionValidityError::Invalid(InvalidTransaction::Payment))
+		);
+		assert!(ChargeAssetTxPayment::<Test>::from(0, Some(USDT)).pre_dispatch(&BOB, &call(), &info(1000), LEN).is_err());
+	});
+}
+
+#[test]
+fn validate_prioritizes_by_fee() {
+	new_test_ext().execute_with(|| {
+		register_pool();
+		let fee = TransactionPayment::compute_fee(LEN as u32, &info(1000), 0);
+		let valid = ChargeAssetTxPayment::<Test>::from(0, Some(USDT)).validate(&BOB, &call(), &info(1000), LEN).unwrap();
+		assert_eq!(valid.priority, fee as u64);
+	});
+}
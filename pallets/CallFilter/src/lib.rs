@@ -0,0 +1,169 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Filter of the calls of the runtime during launch phases and incidents.
+//!
+//! The runtime sorts its calls into features with `ClassifyCall` and uses `PhaseFilter` as its
+//! `BaseCallFilter`. Each launch phase enables a set of features, so that mainnet features can
+//! be rolled out gradually, and governance can pause a feature during an incident, all without
+//! a runtime upgrade. Calls outside of any feature, such as governance calls, are never filtered.
+
+use codec::{Decode, Encode};
+use frame_support::{decl_event, decl_module, decl_storage, dispatch};
+use frame_support::traits::{EnsureOrigin, Filter};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::RuntimeDebug;
+use sp_std::marker::PhantomData;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+/// Features of the runtime that can be switched off.
+#[derive(Encode, Decode, Copy, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum Feature {
+    /// Transfers of native and bridged assets.
+    Transfers,
+    /// Settlement of orderbook trades by registered providers.
+    Orderbook,
+    /// Polkapool swaps and liquidity, including hybrid routes.
+    Swaps,
+    /// Transfers through the bridge.
+    Bridge,
+}
+
+/// Launch phase of the chain, each enabling more features than the previous one.
+#[derive(Encode, Decode, Copy, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum LaunchPhase {
+    /// Only governance, staking and system calls.
+    Launch,
+    /// Orderbook trading by the registered providers.
+    Orderbook,
+    /// All features.
+    Live,
+}
+
+impl Default for LaunchPhase {
+    fn default() -> Self {
+        LaunchPhase::Live
+    }
+}
+
+impl LaunchPhase {
+    /// Whether `feature` is available in this phase.
+    pub fn enables(&self, feature: Feature) -> bool {
+        match self {
+            LaunchPhase::Launch => false,
+            LaunchPhase::Orderbook => feature == Feature::Orderbook,
+            LaunchPhase::Live => true,
+        }
+    }
+}
+
+/// Sorts the calls of the runtime into features, `None` for calls that are always allowed.
+pub trait ClassifyCall<Call> {
+    fn feature(call: &Call) -> Option<Feature>;
+}
+
+/// Configure the pallet by specifying the parameters and types on which it depends.
+pub trait Config: frame_system::Config {
+    /// Because this pallet emits events, it depends on the runtime's definition of an event.
+    type Event: From<Event> + Into<<Self as frame_system::Config>::Event>;
+    /// Features of the calls of the runtime
+    type CallClassifier: ClassifyCall<<Self as frame_system::Config>::Call>;
+    /// Origin allowed to change the launch phase and to resume paused features
+    type UpdateOrigin: EnsureOrigin<Self::Origin>;
+    /// Origin allowed to pause features
+    type PauseOrigin: EnsureOrigin<Self::Origin>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as CallFilter {
+		/// Current launch phase.
+		Phase get(fn phase) config(): LaunchPhase;
+		/// Features paused whatever the launch phase.
+		PausedFeatures get(fn is_paused): map hasher(twox_64_concat) Feature => bool;
+	}
+}
+
+decl_event!(
+	pub enum Event {
+		/// Launch phase changed. \[phase\]
+		PhaseChanged(LaunchPhase),
+		/// Feature paused. \[feature\]
+		FeaturePaused(Feature),
+		/// Feature resumed. \[feature\]
+		FeatureResumed(Feature),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		/// Moves the chain to another launch phase.
+        /// # Arguments
+        ///
+        /// * `origin` - Update origin, usually governance.
+        ///
+        /// * `phase` - New launch phase.
+		#[weight = 10000]
+		pub fn set_phase(origin, phase: LaunchPhase) -> dispatch::DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			Phase::put(phase);
+			Self::deposit_event(Event::PhaseChanged(phase));
+			Ok(())
+		}
+
+		/// Pauses a feature, for instance during an incident.
+        /// # Arguments
+        ///
+        /// * `origin` - Pause origin.
+        ///
+        /// * `feature` - Feature paused.
+		#[weight = 10000]
+		pub fn pause_feature(origin, feature: Feature) -> dispatch::DispatchResult {
+			T::PauseOrigin::ensure_origin(origin)?;
+			PausedFeatures::insert(feature, true);
+			Self::deposit_event(Event::FeaturePaused(feature));
+			Ok(())
+		}
+
+		/// Resumes a paused feature.
+        /// # Arguments
+        ///
+        /// * `origin` - Update origin, usually governance.
+        ///
+        /// * `feature` - Feature resumed.
+		#[weight = 10000]
+		pub fn resume_feature(origin, feature: Feature) -> dispatch::DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			PausedFeatures::remove(feature);
+			Self::deposit_event(Event::FeatureResumed(feature));
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Module<T> {
+    /// Whether `feature` is enabled by the launch phase and not paused.
+    pub fn is_enabled(feature: Feature) -> bool {
+        Self::phase().enables(feature) && !Self::is_paused(feature)
+    }
+}
+
+/// `BaseCallFilter` allowing the calls of the enabled features only.
+pub struct PhaseFilter<T>(PhantomData<T>);
+
+impl<T: Config> Filter<<T as frame_system::Config>::Call> for PhaseFilter<T> {
+    fn filter(call: &<T as frame_system::Config>::Call) -> bool {
+        match T::CallClassifier::feature(call) {
+            Some(feature) => Module::<T>::is_enabled(feature),
+            None => true,
+        }
+    }
+}
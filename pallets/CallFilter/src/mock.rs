@@ -0,0 +1,95 @@
+use crate as call_filter;
+use crate::{ClassifyCall, Feature, LaunchPhase, PhaseFilter};
+use frame_support::{ord_parameter_types, parameter_types};
+use frame_system::{EnsureRoot, EnsureSignedBy};
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+pub type AccountId = u64;
+
+pub const ALICE: AccountId = 1;
+pub const GUARDIAN: AccountId = 2;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		CallFilter: call_filter::{Module, Call, Storage, Event, Config},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const SS58Prefix: u8 = 42;
+}
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = PhaseFilter<Test>;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = SS58Prefix;
+}
+
+/// Remarks stand for transfers and heap page changes for orderbook settlements.
+pub struct TestCallClassifier;
+
+impl ClassifyCall<Call> for TestCallClassifier {
+	fn feature(call: &Call) -> Option<Feature> {
+		match call {
+			Call::System(frame_system::Call::remark(_)) => Some(Feature::Transfers),
+			Call::System(frame_system::Call::set_heap_pages(_)) => Some(Feature::Orderbook),
+			_ => None,
+		}
+	}
+}
+
+ord_parameter_types! {
+	pub const Guardian: AccountId = GUARDIAN;
+}
+
+impl call_filter::Config for Test {
+	type Event = Event;
+	type CallClassifier = TestCallClassifier;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+	type PauseOrigin = EnsureSignedBy<Guardian, AccountId>;
+}
+
+// Build genesis storage according to the mock runtime, starting in `phase`.
+pub fn new_test_ext(phase: LaunchPhase) -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+	call_filter::GenesisConfig { phase }.assimilate_storage::<Test>(&mut t).unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	// Events are not recorded in the genesis block
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
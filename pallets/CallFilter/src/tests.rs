@@ -0,0 +1,94 @@
+use crate::{mock::*, Event as CallFilterEvent, Feature, LaunchPhase, PhaseFilter};
+use frame_support::{assert_noop, assert_ok};
+use frame_support::traits::Filter;
+use sp_runtime::traits::BadOrigin;
+
+fn transfer() -> Call {
+	Call::System(frame_system::Call::remark(vec![]))
+}
+
+fn settlement() -> Call {
+	Call::System(frame_system::Call::set_heap_pages(8))
+}
+
+fn governance() -> Call {
+	Call::CallFilter(crate::Call::set_phase(LaunchPhase::Live))
+}
+
+fn allowed(call: &Call) -> bool {
+	PhaseFilter::<Test>::filter(call)
+}
+
+fn last_event() -> Event {
+	System::events().pop().expect("Event expected").event
+}
+
+#[test]
+fn launch_phase_only_allows_unclassified_calls() {
+	new_test_ext(LaunchPhase::Launch).execute_with(|| {
+		assert!(!allowed(&transfer()));
+		assert!(!allowed(&settlement()));
+		assert!(allowed(&governance()));
+	});
+}
+
+#[test]
+fn orderbook_phase_allows_settlements() {
+	new_test_ext(LaunchPhase::Orderbook).execute_with(|| {
+		assert!(!allowed(&transfer()));
+		assert!(allowed(&settlement()));
+		assert!(allowed(&governance()));
+	});
+}
+
+#[test]
+fn live_phase_allows_everything() {
+	new_test_ext(LaunchPhase::Live).execute_with(|| {
+		assert!(allowed(&transfer()));
+		assert!(allowed(&settlement()));
+		assert!(allowed(&governance()));
+	});
+}
+
+#[test]
+fn set_phase_works() {
+	new_test_ext(LaunchPhase::Launch).execute_with(|| {
+		assert_noop!(CallFilter::set_phase(Origin::signed(ALICE), LaunchPhase::Live), BadOrigin);
+		assert_ok!(CallFilter::set_phase(Origin::root(), LaunchPhase::Orderbook));
+		assert_eq!(CallFilter::phase(), LaunchPhase::Orderbook);
+		assert_eq!(last_event(), Event::call_filter(CallFilterEvent::PhaseChanged(LaunchPhase::Orderbook)));
+		assert!(allowed(&settlement()));
+	});
+}
+
+#[test]
+fn paused_feature_is_filtered_in_any_phase() {
+	new_test_ext(LaunchPhase::Live).execute_with(|| {
+		assert_noop!(CallFilter::pause_feature(Origin::signed(ALICE), Feature::Transfers), BadOrigin);
+		assert_ok!(CallFilter::pause_feature(Origin::signed(GUARDIAN), Feature::Transfers));
+		assert_eq!(last_event(), Event::call_filter(CallFilterEvent::FeaturePaused(Feature::Transfers)));
+		assert!(!allowed(&transfer()));
+		assert!(allowed(&settlement()));
+		assert!(!CallFilter::is_enabled(Feature::Transfers));
+	});
+}
+
+#[test]
+fn resume_feature_works() {
+	new_test_ext(LaunchPhase::Live).execute_with(|| {
+		assert_ok!(CallFilter::pause_feature(Origin::signed(GUARDIAN), Feature::Transfers));
+		// Resuming is left to governance
+		assert_noop!(CallFilter::resume_feature(Origin::signed(GUARDIAN), Feature::Transfers), BadOrigin);
+		assert_ok!(CallFilter::resume_feature(Origin::root(), Feature::Transfers));
+		assert_eq!(last_event(), Event::call_filter(CallFilterEvent::FeatureResumed(Feature::Transfers)));
+		assert!(allowed(&transfer()));
+	});
+}
+
+#[test]
+fn resume_does_not_override_phase() {
+	new_test_ext(LaunchPhase::Launch).execute_with(|| {
+		assert_ok!(CallFilter::resume_feature(Origin::root(), Feature::Transfers));
+		assert!(!allowed(&transfer()));
+	});
+}
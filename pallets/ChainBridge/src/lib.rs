@@ -0,0 +1,433 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Bridge of ERC20 and ERC721 assets compatible with ChainSafe's ChainBridge.
+//!
+//! Deposits on another chain are relayed as proposals. A set of relayers votes on each proposal
+//! and the transfer is executed once the relayer threshold approves it. Resource ids, shared by
+//! the bridge contracts of every chain, map to the `AssetId` of the bridged asset. Bridged ERC20
+//! and ERC721 tokens are minted on arrival and burnt on departure, while POLKADEX leaving the
+//! chain is locked in the bridge account until it comes back.
+
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, dispatch, ensure, transactional};
+use frame_support::dispatch::DispatchResult;
+use frame_support::traits::{EnsureOrigin, Get};
+use frame_system::ensure_signed;
+use orml_traits::MultiCurrency;
+use polkadex_primitives::assets::AssetId;
+use polkadex_primitives::nft::NonFungibleLedger;
+use sp_core::U256;
+use sp_runtime::ModuleId;
+use sp_runtime::traits::{AccountIdConversion, Saturating};
+use sp_std::vec::Vec;
+
+use types::{ChainId, DepositNonce, Proposal, ProposalStatus, ProposalVotes, ResourceId};
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+pub mod types;
+
+/// Relayer threshold until governance sets one.
+const DEFAULT_RELAYER_THRESHOLD: u32 = 1;
+
+pub type BalanceOf<T> = <<T as Config>::Currency as MultiCurrency<<T as frame_system::Config>::AccountId>>::Balance;
+pub type ProposalOf<T> = Proposal<<T as frame_system::Config>::AccountId, BalanceOf<T>>;
+
+/// Configure the pallet by specifying the parameters and types on which it depends.
+pub trait Config: frame_system::Config {
+    /// Because this pallet emits events, it depends on the runtime's definition of an event.
+    type Event: From<Event<Self>> + Into<<Self as frame_system::Config>::Event>;
+    /// Multi-currency ledger of the bridged assets
+    type Currency: MultiCurrency<Self::AccountId, CurrencyId = AssetId>;
+    /// Ledger of the bridged ERC721 tokens
+    type Nft: NonFungibleLedger<Self::AccountId, AssetId>;
+    /// Origin allowed to manage the relayers, chains and resources
+    type AdminOrigin: EnsureOrigin<Self::Origin>;
+    /// Identifier of this chain in the ChainBridge deployment
+    type ChainId: Get<ChainId>;
+    /// Number of blocks during which a proposal can be voted on
+    type ProposalLifetime: Get<Self::BlockNumber>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as ChainBridge {
+		/// Nonce of the last deposit to each whitelisted chain.
+		ChainNonces get(fn chain_nonce): map hasher(twox_64_concat) ChainId => Option<DepositNonce>;
+		/// Number of relayer votes needed to approve or reject a proposal.
+		RelayerThreshold get(fn relayer_threshold): u32 = DEFAULT_RELAYER_THRESHOLD;
+		/// Accounts allowed to vote on proposals.
+		Relayers get(fn relayers): map hasher(blake2_128_concat) T::AccountId => bool;
+		RelayerCount get(fn relayer_count): u32;
+		/// Asset bridged under each resource id.
+		Resources get(fn resource): map hasher(blake2_128_concat) ResourceId => Option<AssetId>;
+		/// Votes on the proposals, by chain of origin, deposit nonce and proposal.
+		Votes get(fn votes): double_map hasher(twox_64_concat) ChainId, hasher(blake2_128_concat) (DepositNonce, ProposalOf<T>) => Option<ProposalVotes<T::AccountId, T::BlockNumber>>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		AccountId = <T as frame_system::Config>::AccountId,
+		Balance = BalanceOf<T>
+	{
+		/// Vote threshold changed. \[threshold\]
+		RelayerThresholdChanged(u32),
+		/// Chain allowed as a destination and origin of transfers. \[chain_id\]
+		ChainWhitelisted(ChainId),
+		/// Relayer added to the set. \[relayer\]
+		RelayerAdded(AccountId),
+		/// Relayer removed from the set. \[relayer\]
+		RelayerRemoved(AccountId),
+		/// Resource id mapped to an asset. \[resource_id, asset_id\]
+		ResourceSet(ResourceId, AssetId),
+		/// Resource id no longer bridged. \[resource_id\]
+		ResourceRemoved(ResourceId),
+		/// Fungible asset sent to another chain. \[dest_id, nonce, resource_id, amount, recipient\]
+		FungibleTransfer(ChainId, DepositNonce, ResourceId, Balance, Vec<u8>),
+		/// Non-fungible token sent to another chain. \[dest_id, nonce, resource_id, token_id, recipient, metadata\]
+		NonFungibleTransfer(ChainId, DepositNonce, ResourceId, U256, Vec<u8>, Vec<u8>),
+		/// Relayer voted for a proposal. \[src_id, nonce, relayer\]
+		VoteFor(ChainId, DepositNonce, AccountId),
+		/// Relayer voted against a proposal. \[src_id, nonce, relayer\]
+		VoteAgainst(ChainId, DepositNonce, AccountId),
+		/// Proposal approved by the relayers. \[src_id, nonce\]
+		ProposalApproved(ChainId, DepositNonce),
+		/// Proposal rejected by the relayers. \[src_id, nonce\]
+		ProposalRejected(ChainId, DepositNonce),
+		/// Approved proposal executed. \[src_id, nonce\]
+		ProposalSucceeded(ChainId, DepositNonce),
+		/// Approved proposal could not be executed. \[src_id, nonce\]
+		ProposalFailed(ChainId, DepositNonce),
+	}
+);
+
+decl_error! {
+	pub enum Error for Module<T: Config> {
+		/// Relayer threshold must be greater than zero
+		InvalidThreshold,
+		/// This chain cannot be whitelisted
+		InvalidChainId,
+		/// Chain already whitelisted
+		ChainAlreadyWhitelisted,
+		/// Chain not whitelisted
+		ChainNotWhitelisted,
+		/// Resource id not mapped to an asset
+		ResourceDoesNotExist,
+		/// Asset of the resource cannot be bridged this way
+		InvalidResourceAsset,
+		/// Account already a relayer
+		RelayerAlreadyExists,
+		/// Account not a relayer
+		RelayerInvalid,
+		/// Caller is not a relayer
+		MustBeRelayer,
+		/// Relayer already voted on the proposal
+		RelayerAlreadyVoted,
+		/// Proposal already approved or rejected
+		ProposalAlreadyComplete,
+		/// No vote on the proposal yet
+		ProposalDoesNotExist,
+		/// Proposal can no longer be voted on
+		ProposalExpired,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		const ChainId: ChainId = T::ChainId::get();
+		const ProposalLifetime: T::BlockNumber = T::ProposalLifetime::get();
+
+		fn deposit_event() = default;
+
+		/// Sets the number of relayer votes needed to settle a proposal.
+        /// # Arguments
+        ///
+        /// * `origin` - Admin origin.
+        ///
+        /// * `threshold` - Number of votes, at least one.
+		#[weight = 10000]
+		pub fn set_threshold(origin, threshold: u32) -> dispatch::DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(threshold > 0, Error::<T>::InvalidThreshold);
+			RelayerThreshold::put(threshold);
+			Self::deposit_event(RawEvent::RelayerThresholdChanged(threshold));
+			Ok(())
+		}
+
+		/// Maps a resource id to the asset bridged under it.
+        /// # Arguments
+        ///
+        /// * `origin` - Admin origin.
+        ///
+        /// * `resource_id` - Resource id of the bridge contracts.
+        ///
+        /// * `asset_id` - Bridged asset.
+		#[weight = 10000]
+		pub fn set_resource(origin, resource_id: ResourceId, asset_id: AssetId) -> dispatch::DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			Resources::insert(resource_id, asset_id);
+			Self::deposit_event(RawEvent::ResourceSet(resource_id, asset_id));
+			Ok(())
+		}
+
+		/// Stops bridging the asset of a resource id.
+        /// # Arguments
+        ///
+        /// * `origin` - Admin origin.
+        ///
+        /// * `resource_id` - Resource id of the bridge contracts.
+		#[weight = 10000]
+		pub fn remove_resource(origin, resource_id: ResourceId) -> dispatch::DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(Resources::contains_key(resource_id), Error::<T>::ResourceDoesNotExist);
+			Resources::remove(resource_id);
+			Self::deposit_event(RawEvent::ResourceRemoved(resource_id));
+			Ok(())
+		}
+
+		/// Allows transfers to and from a chain.
+        /// # Arguments
+        ///
+        /// * `origin` - Admin origin.
+        ///
+        /// * `chain_id` - Identifier of the chain in the ChainBridge deployment.
+		#[weight = 10000]
+		pub fn whitelist_chain(origin, chain_id: ChainId) -> dispatch::DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(chain_id != T::ChainId::get(), Error::<T>::InvalidChainId);
+			ensure!(!ChainNonces::contains_key(chain_id), Error::<T>::ChainAlreadyWhitelisted);
+			ChainNonces::insert(chain_id, 0);
+			Self::deposit_event(RawEvent::ChainWhitelisted(chain_id));
+			Ok(())
+		}
+
+		/// Adds an account to the relayer set.
+        /// # Arguments
+        ///
+        /// * `origin` - Admin origin.
+        ///
+        /// * `relayer` - New relayer.
+		#[weight = 10000]
+		pub fn add_relayer(origin, relayer: T::AccountId) -> dispatch::DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(!Self::relayers(&relayer), Error::<T>::RelayerAlreadyExists);
+			Relayers::<T>::insert(&relayer, true);
+			RelayerCount::mutate(|count| *count += 1);
+			Self::deposit_event(RawEvent::RelayerAdded(relayer));
+			Ok(())
+		}
+
+		/// Removes an account from the relayer set.
+        /// # Arguments
+        ///
+        /// * `origin` - Admin origin.
+        ///
+        /// * `relayer` - Removed relayer.
+		#[weight = 10000]
+		pub fn remove_relayer(origin, relayer: T::AccountId) -> dispatch::DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(Self::relayers(&relayer), Error::<T>::RelayerInvalid);
+			Relayers::<T>::remove(&relayer);
+			RelayerCount::mutate(|count| *count -= 1);
+			Self::deposit_event(RawEvent::RelayerRemoved(relayer));
+			Ok(())
+		}
+
+		/// Votes for a proposal, executing it once the threshold is reached.
+        /// # Arguments
+        ///
+        /// * `origin` - Relayer.
+        ///
+        /// * `nonce` - Nonce of the deposit on the chain of origin.
+        ///
+        /// * `src_id` - Chain of origin.
+        ///
+        /// * `proposal` - Transfer to execute.
+		#[weight = 100000]
+		pub fn acknowledge_proposal(origin, nonce: DepositNonce, src_id: ChainId, proposal: ProposalOf<T>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_valid_vote(&who, src_id, &proposal)?;
+			Self::commit_vote(who, nonce, src_id, proposal.clone(), true)?;
+			Self::try_resolve_proposal(nonce, src_id, proposal)
+		}
+
+		/// Votes against a proposal, rejecting it once approving it is no longer possible.
+        /// # Arguments
+        ///
+        /// * `origin` - Relayer.
+        ///
+        /// * `nonce` - Nonce of the deposit on the chain of origin.
+        ///
+        /// * `src_id` - Chain of origin.
+        ///
+        /// * `proposal` - Transfer voted against.
+		#[weight = 10000]
+		pub fn reject_proposal(origin, nonce: DepositNonce, src_id: ChainId, proposal: ProposalOf<T>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_valid_vote(&who, src_id, &proposal)?;
+			Self::commit_vote(who, nonce, src_id, proposal.clone(), false)?;
+			Self::try_resolve_proposal(nonce, src_id, proposal)
+		}
+
+		/// Settles a proposal whose votes reach the threshold after a change of the relayer set.
+        /// # Arguments
+        ///
+        /// * `origin` - Any signed account.
+        ///
+        /// * `nonce` - Nonce of the deposit on the chain of origin.
+        ///
+        /// * `src_id` - Chain of origin.
+        ///
+        /// * `proposal` - Transfer voted on.
+		#[weight = 100000]
+		pub fn eval_vote_state(origin, nonce: DepositNonce, src_id: ChainId, proposal: ProposalOf<T>) -> dispatch::DispatchResult {
+			ensure_signed(origin)?;
+			Self::try_resolve_proposal(nonce, src_id, proposal)
+		}
+
+		/// Sends a fungible asset to another chain.
+        /// # Arguments
+        ///
+        /// * `origin` - Sender.
+        ///
+        /// * `amount` - Amount sent.
+        ///
+        /// * `recipient` - Recipient on the destination chain.
+        ///
+        /// * `dest_id` - Destination chain.
+        ///
+        /// * `resource_id` - Resource id of the asset.
+		#[weight = 100000]
+		pub fn transfer_fungible(origin, #[compact] amount: BalanceOf<T>, recipient: Vec<u8>, dest_id: ChainId, resource_id: ResourceId) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(ChainNonces::contains_key(dest_id), Error::<T>::ChainNotWhitelisted);
+			let asset_id = Self::resource(resource_id).ok_or(Error::<T>::ResourceDoesNotExist)?;
+			match asset_id {
+				AssetId::POLKADEX => T::Currency::transfer(asset_id, &who, &Self::account_id(), amount)?,
+				AssetId::ChainsafeErc20(_) => T::Currency::withdraw(asset_id, &who, amount)?,
+				_ => return Err(Error::<T>::InvalidResourceAsset.into()),
+			}
+			let nonce = Self::bump_nonce(dest_id);
+			Self::deposit_event(RawEvent::FungibleTransfer(dest_id, nonce, resource_id, amount, recipient));
+			Ok(())
+		}
+
+		/// Sends a bridged ERC721 token to another chain.
+        /// # Arguments
+        ///
+        /// * `origin` - Owner of the token.
+        ///
+        /// * `token_id` - Token sent.
+        ///
+        /// * `recipient` - Recipient on the destination chain.
+        ///
+        /// * `dest_id` - Destination chain.
+        ///
+        /// * `resource_id` - Resource id of the collection.
+		#[weight = 100000]
+		pub fn transfer_nonfungible(origin, token_id: U256, recipient: Vec<u8>, dest_id: ChainId, resource_id: ResourceId) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(ChainNonces::contains_key(dest_id), Error::<T>::ChainNotWhitelisted);
+			let asset_id = Self::resource(resource_id).ok_or(Error::<T>::ResourceDoesNotExist)?;
+			ensure!(T::Nft::is_collection(&asset_id), Error::<T>::InvalidResourceAsset);
+			let metadata = T::Nft::burn(&asset_id, token_id, &who)?;
+			let nonce = Self::bump_nonce(dest_id);
+			Self::deposit_event(RawEvent::NonFungibleTransfer(dest_id, nonce, resource_id, token_id, recipient, metadata));
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Module<T> {
+    /// Holds the POLKADEX sent to other chains.
+    pub fn account_id() -> T::AccountId {
+        ModuleId(*b"pdx/brdg").into_account()
+    }
+
+    fn bump_nonce(dest_id: ChainId) -> DepositNonce {
+        let nonce = Self::chain_nonce(dest_id).unwrap_or_default().saturating_add(1);
+        ChainNonces::insert(dest_id, nonce);
+        nonce
+    }
+
+    fn ensure_valid_vote(who: &T::AccountId, src_id: ChainId, proposal: &ProposalOf<T>) -> DispatchResult {
+        ensure!(Self::relayers(who), Error::<T>::MustBeRelayer);
+        ensure!(ChainNonces::contains_key(src_id), Error::<T>::ChainNotWhitelisted);
+        let resource_id = match proposal {
+            Proposal::Fungible { resource_id, .. } | Proposal::NonFungible { resource_id, .. } => resource_id,
+        };
+        ensure!(Resources::contains_key(resource_id), Error::<T>::ResourceDoesNotExist);
+        Ok(())
+    }
+
+    fn commit_vote(who: T::AccountId, nonce: DepositNonce, src_id: ChainId, proposal: ProposalOf<T>, in_favour: bool) -> DispatchResult {
+        let now = <frame_system::Module<T>>::block_number();
+        let key = (nonce, proposal);
+        let mut votes = Self::votes(src_id, &key).unwrap_or_else(|| ProposalVotes {
+            expiry: now.saturating_add(T::ProposalLifetime::get()),
+            ..Default::default()
+        });
+        ensure!(!votes.is_complete(), Error::<T>::ProposalAlreadyComplete);
+        ensure!(!votes.is_expired(now), Error::<T>::ProposalExpired);
+        ensure!(!votes.has_voted(&who), Error::<T>::RelayerAlreadyVoted);
+
+        if in_favour {
+            votes.votes_for.push(who.clone());
+            Self::deposit_event(RawEvent::VoteFor(src_id, nonce, who));
+        } else {
+            votes.votes_against.push(who.clone());
+            Self::deposit_event(RawEvent::VoteAgainst(src_id, nonce, who));
+        }
+        Votes::<T>::insert(src_id, &key, votes);
+        Ok(())
+    }
+
+    fn try_resolve_proposal(nonce: DepositNonce, src_id: ChainId, proposal: ProposalOf<T>) -> DispatchResult {
+        let now = <frame_system::Module<T>>::block_number();
+        let key = (nonce, proposal);
+        let mut votes = Self::votes(src_id, &key).ok_or(Error::<T>::ProposalDoesNotExist)?;
+        ensure!(!votes.is_complete(), Error::<T>::ProposalAlreadyComplete);
+        ensure!(!votes.is_expired(now), Error::<T>::ProposalExpired);
+
+        let status = votes.try_to_complete(Self::relayer_threshold(), Self::relayer_count());
+        Votes::<T>::insert(src_id, &key, votes);
+        match status {
+            ProposalStatus::Approved => {
+                Self::deposit_event(RawEvent::ProposalApproved(src_id, nonce));
+                // The vote stands even if the transfer cannot be executed
+                if Self::execute_proposal(key.1).is_ok() {
+                    Self::deposit_event(RawEvent::ProposalSucceeded(src_id, nonce));
+                } else {
+                    Self::deposit_event(RawEvent::ProposalFailed(src_id, nonce));
+                }
+            }
+            ProposalStatus::Rejected => Self::deposit_event(RawEvent::ProposalRejected(src_id, nonce)),
+            ProposalStatus::Initiated => {}
+        }
+        Ok(())
+    }
+
+    /// Mints or releases the assets of an approved proposal.
+    #[transactional]
+    fn execute_proposal(proposal: ProposalOf<T>) -> DispatchResult {
+        match proposal {
+            Proposal::Fungible { recipient, amount, resource_id } => {
+                match Self::resource(resource_id).ok_or(Error::<T>::ResourceDoesNotExist)? {
+                    AssetId::POLKADEX => T::Currency::transfer(AssetId::POLKADEX, &Self::account_id(), &recipient, amount),
+                    asset_id @ AssetId::ChainsafeErc20(_) => T::Currency::deposit(asset_id, &recipient, amount),
+                    _ => Err(Error::<T>::InvalidResourceAsset.into()),
+                }
+            }
+            Proposal::NonFungible { recipient, token_id, resource_id, metadata } => {
+                let asset_id = Self::resource(resource_id).ok_or(Error::<T>::ResourceDoesNotExist)?;
+                ensure!(T::Nft::is_collection(&asset_id), Error::<T>::InvalidResourceAsset);
+                T::Nft::mint(&asset_id, token_id, &recipient, metadata)
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate as chainbridge;
+use frame_support::parameter_types;
+use frame_system::EnsureRoot;
+use orml_traits::parameter_type_with_key;
+use polkadex_primitives::assets::AssetId;
+use sp_core::{H160, H256};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+use crate::types::{ChainId, ResourceId};
+
+pub type AccountId = u64;
+pub type Balance = u128;
+pub type Amount = i128;
+pub type BlockNumber = u64;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const RELAYER_A: AccountId = 10;
+pub const RELAYER_B: AccountId = 11;
+pub const RELAYER_C: AccountId = 12;
+
+/// Identifier of the mock chain.
+pub const THIS_CHAIN: ChainId = 5;
+/// Chain on the other side of the bridge.
+pub const ETHEREUM: ChainId = 0;
+
+pub const ERC20_TOKEN: AssetId = AssetId::ChainsafeErc20(H160([1u8; 20]));
+pub const ERC721_TOKEN: AssetId = AssetId::ChainsafeErc721(H160([2u8; 20]));
+pub const NATIVE_RESOURCE: ResourceId = [1u8; 32];
+pub const ERC20_RESOURCE: ResourceId = [2u8; 32];
+pub const ERC721_RESOURCE: ResourceId = [3u8; 32];
+
+/// Balance of each asset given to `ALICE` at genesis.
+pub const INITIAL_BALANCE: Balance = 1_000_000_000_000;
+pub const PROPOSAL_LIFETIME: BlockNumber = 50;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		Tokens: orml_tokens::{Module, Call, Storage, Event<T>, Config<T>},
+		Nft: nft::{Module, Call, Storage, Event<T>},
+		ChainBridge: chainbridge::{Module, Call, Storage, Event<T>},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const SS58Prefix: u8 = 42;
+}
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = SS58Prefix;
+}
+
+parameter_type_with_key! {
+	pub ExistentialDeposits: |_currency_id: AssetId| -> Balance {
+		0
+	};
+}
+
+impl orml_tokens::Config for Test {
+	type Event = Event;
+	type Balance = Balance;
+	type Amount = Amount;
+	type CurrencyId = AssetId;
+	type WeightInfo = ();
+	type ExistentialDeposits = ExistentialDeposits;
+	type OnDust = ();
+}
+
+parameter_types! {
+	pub const MaxMetadataLength: u32 = 256;
+}
+
+impl nft::Config for Test {
+	type Event = Event;
+	type MaxMetadataLength = MaxMetadataLength;
+}
+
+parameter_types! {
+	pub const BridgeChainId: ChainId = THIS_CHAIN;
+	pub const ProposalLifetime: BlockNumber = PROPOSAL_LIFETIME;
+}
+
+impl chainbridge::Config for Test {
+	type Event = Event;
+	type Currency = Tokens;
+	type Nft = Nft;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type ChainId = BridgeChainId;
+	type ProposalLifetime = ProposalLifetime;
+}
+
+// Build genesis storage according to the mock runtime, with a local relayer set of three
+// relayers and a threshold of two.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+	orml_tokens::GenesisConfig::<Test> {
+		endowed_accounts: vec![
+			(ALICE, AssetId::POLKADEX, INITIAL_BALANCE),
+			(ALICE, ERC20_TOKEN, INITIAL_BALANCE),
+		],
+	}.assimilate_storage(&mut t).unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| {
+		// Events are not recorded in the genesis block
+		System::set_block_number(1);
+		let root = Origin::root();
+		ChainBridge::set_threshold(root.clone(), 2).unwrap();
+		ChainBridge::whitelist_chain(root.clone(), ETHEREUM).unwrap();
+		ChainBridge::set_resource(root.clone(), NATIVE_RESOURCE, AssetId::POLKADEX).unwrap();
+		ChainBridge::set_resource(root.clone(), ERC20_RESOURCE, ERC20_TOKEN).unwrap();
+		ChainBridge::set_resource(root.clone(), ERC721_RESOURCE, ERC721_TOKEN).unwrap();
+		for relayer in [RELAYER_A, RELAYER_B, RELAYER_C].iter() {
+			ChainBridge::add_relayer(root.clone(), *relayer).unwrap();
+		}
+	});
+	ext
+}
@@ -0,0 +1,287 @@
+use crate::{mock::*, Error, RawEvent};
+use crate::types::{Proposal, ProposalStatus};
+use frame_support::{assert_noop, assert_ok};
+use orml_traits::MultiCurrency;
+use polkadex_primitives::assets::AssetId;
+use polkadex_primitives::nft::NonFungibleLedger;
+use sp_core::{H160, U256};
+use sp_runtime::traits::BadOrigin;
+
+const RECIPIENT: &[u8] = b"0x0000000000000000000000000000000000000abc";
+
+fn free_balance(asset_id: AssetId, who: AccountId) -> Balance {
+	Tokens::free_balance(asset_id, &who)
+}
+
+fn has_event(event: RawEvent<AccountId, Balance>) -> bool {
+	System::events().iter().any(|record| record.event == Event::chainbridge(event.clone()))
+}
+
+fn erc20_proposal(amount: Balance) -> Proposal<AccountId, Balance> {
+	Proposal::Fungible { recipient: BOB, amount, resource_id: ERC20_RESOURCE }
+}
+
+fn erc721_proposal(token_id: u64) -> Proposal<AccountId, Balance> {
+	Proposal::NonFungible { recipient: BOB, token_id: U256::from(token_id), resource_id: ERC721_RESOURCE, metadata: b"ipfs://token".to_vec() }
+}
+
+fn approve(nonce: u64, proposal: Proposal<AccountId, Balance>) {
+	assert_ok!(ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_A), nonce, ETHEREUM, proposal.clone()));
+	assert_ok!(ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_B), nonce, ETHEREUM, proposal));
+}
+
+#[test]
+fn set_threshold_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ChainBridge::set_threshold(Origin::root(), 3));
+		assert_eq!(ChainBridge::relayer_threshold(), 3);
+		assert!(has_event(RawEvent::RelayerThresholdChanged(3)));
+	});
+}
+
+#[test]
+fn set_threshold_fails() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(ChainBridge::set_threshold(Origin::root(), 0), Error::<Test>::InvalidThreshold);
+		assert_noop!(ChainBridge::set_threshold(Origin::signed(ALICE), 1), BadOrigin);
+	});
+}
+
+#[test]
+fn whitelist_chain_fails() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(ChainBridge::whitelist_chain(Origin::root(), THIS_CHAIN), Error::<Test>::InvalidChainId);
+		assert_noop!(ChainBridge::whitelist_chain(Origin::root(), ETHEREUM), Error::<Test>::ChainAlreadyWhitelisted);
+		assert_noop!(ChainBridge::whitelist_chain(Origin::signed(ALICE), 1), BadOrigin);
+	});
+}
+
+#[test]
+fn add_and_remove_relayer_works() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(ChainBridge::relayer_count(), 3);
+		assert_noop!(ChainBridge::add_relayer(Origin::root(), RELAYER_A), Error::<Test>::RelayerAlreadyExists);
+
+		assert_ok!(ChainBridge::remove_relayer(Origin::root(), RELAYER_A));
+		assert!(!ChainBridge::relayers(RELAYER_A));
+		assert_eq!(ChainBridge::relayer_count(), 2);
+		assert!(has_event(RawEvent::RelayerRemoved(RELAYER_A)));
+		assert_noop!(ChainBridge::remove_relayer(Origin::root(), RELAYER_A), Error::<Test>::RelayerInvalid);
+		assert_noop!(ChainBridge::add_relayer(Origin::signed(ALICE), ALICE), BadOrigin);
+	});
+}
+
+#[test]
+fn remove_resource_works() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(ChainBridge::resource(ERC20_RESOURCE), Some(ERC20_TOKEN));
+		assert_ok!(ChainBridge::remove_resource(Origin::root(), ERC20_RESOURCE));
+		assert_eq!(ChainBridge::resource(ERC20_RESOURCE), None);
+		assert!(has_event(RawEvent::ResourceRemoved(ERC20_RESOURCE)));
+		assert_noop!(ChainBridge::remove_resource(Origin::root(), ERC20_RESOURCE), Error::<Test>::ResourceDoesNotExist);
+	});
+}
+
+#[test]
+fn transfer_fungible_burns_erc20() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ChainBridge::transfer_fungible(Origin::signed(ALICE), 100, RECIPIENT.to_vec(), ETHEREUM, ERC20_RESOURCE));
+		assert_eq!(free_balance(ERC20_TOKEN, ALICE), INITIAL_BALANCE - 100);
+		assert_eq!(Tokens::total_issuance(ERC20_TOKEN), INITIAL_BALANCE - 100);
+		assert!(has_event(RawEvent::FungibleTransfer(ETHEREUM, 1, ERC20_RESOURCE, 100, RECIPIENT.to_vec())));
+
+		// Deposit nonces increase per destination chain
+		assert_ok!(ChainBridge::transfer_fungible(Origin::signed(ALICE), 5, RECIPIENT.to_vec(), ETHEREUM, ERC20_RESOURCE));
+		assert_eq!(ChainBridge::chain_nonce(ETHEREUM), Some(2));
+	});
+}
+
+#[test]
+fn transfer_fungible_locks_native_asset() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ChainBridge::transfer_fungible(Origin::signed(ALICE), 100, RECIPIENT.to_vec(), ETHEREUM, NATIVE_RESOURCE));
+		assert_eq!(free_balance(AssetId::POLKADEX, ALICE), INITIAL_BALANCE - 100);
+		assert_eq!(free_balance(AssetId::POLKADEX, ChainBridge::account_id()), 100);
+		assert_eq!(Tokens::total_issuance(AssetId::POLKADEX), INITIAL_BALANCE);
+
+		// Returning from the other chain releases the locked funds
+		approve(1, Proposal::Fungible { recipient: BOB, amount: 60, resource_id: NATIVE_RESOURCE });
+		assert_eq!(free_balance(AssetId::POLKADEX, BOB), 60);
+		assert_eq!(free_balance(AssetId::POLKADEX, ChainBridge::account_id()), 40);
+		assert!(has_event(RawEvent::ProposalSucceeded(ETHEREUM, 1)));
+	});
+}
+
+#[test]
+fn transfer_fungible_fails() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			ChainBridge::transfer_fungible(Origin::signed(ALICE), 100, RECIPIENT.to_vec(), 9, ERC20_RESOURCE),
+			Error::<Test>::ChainNotWhitelisted
+		);
+		assert_noop!(
+			ChainBridge::transfer_fungible(Origin::signed(ALICE), 100, RECIPIENT.to_vec(), ETHEREUM, [9u8; 32]),
+			Error::<Test>::ResourceDoesNotExist
+		);
+		assert_noop!(
+			ChainBridge::transfer_fungible(Origin::signed(ALICE), 100, RECIPIENT.to_vec(), ETHEREUM, ERC721_RESOURCE),
+			Error::<Test>::InvalidResourceAsset
+		);
+		assert_noop!(
+			ChainBridge::transfer_fungible(Origin::signed(BOB), 100, RECIPIENT.to_vec(), ETHEREUM, ERC20_RESOURCE),
+			orml_tokens::Error::<Test>::BalanceTooLow
+		);
+	});
+}
+
+#[test]
+fn acknowledge_proposal_mints_erc20_at_threshold() {
+	new_test_ext().execute_with(|| {
+		let proposal = erc20_proposal(500);
+		assert_ok!(ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_A), 1, ETHEREUM, proposal.clone()));
+		assert_eq!(free_balance(ERC20_TOKEN, BOB), 0);
+		assert!(has_event(RawEvent::VoteFor(ETHEREUM, 1, RELAYER_A)));
+
+		assert_ok!(ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_B), 1, ETHEREUM, proposal.clone()));
+		assert_eq!(free_balance(ERC20_TOKEN, BOB), 500);
+		assert!(has_event(RawEvent::ProposalApproved(ETHEREUM, 1)));
+		assert!(has_event(RawEvent::ProposalSucceeded(ETHEREUM, 1)));
+
+		let votes = ChainBridge::votes(ETHEREUM, (1, proposal.clone())).unwrap();
+		assert_eq!(votes.votes_for, vec![RELAYER_A, RELAYER_B]);
+		assert_eq!(votes.status, ProposalStatus::Approved);
+		assert_eq!(votes.expiry, 1 + PROPOSAL_LIFETIME);
+
+		// An approved proposal is executed only once
+		assert_noop!(
+			ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_C), 1, ETHEREUM, proposal),
+			Error::<Test>::ProposalAlreadyComplete
+		);
+	});
+}
+
+#[test]
+fn acknowledge_proposal_fails() {
+	new_test_ext().execute_with(|| {
+		let proposal = erc20_proposal(500);
+		assert_noop!(
+			ChainBridge::acknowledge_proposal(Origin::signed(ALICE), 1, ETHEREUM, proposal.clone()),
+			Error::<Test>::MustBeRelayer
+		);
+		assert_noop!(
+			ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_A), 1, 9, proposal.clone()),
+			Error::<Test>::ChainNotWhitelisted
+		);
+		assert_noop!(
+			ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_A), 1, ETHEREUM, Proposal::Fungible { recipient: BOB, amount: 1, resource_id: [9u8; 32] }),
+			Error::<Test>::ResourceDoesNotExist
+		);
+
+		assert_ok!(ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_A), 1, ETHEREUM, proposal.clone()));
+		assert_noop!(
+			ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_A), 1, ETHEREUM, proposal.clone()),
+			Error::<Test>::RelayerAlreadyVoted
+		);
+		assert_noop!(
+			ChainBridge::reject_proposal(Origin::signed(RELAYER_A), 1, ETHEREUM, proposal),
+			Error::<Test>::RelayerAlreadyVoted
+		);
+	});
+}
+
+#[test]
+fn acknowledge_proposal_fails_once_expired() {
+	new_test_ext().execute_with(|| {
+		let proposal = erc20_proposal(500);
+		assert_ok!(ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_A), 1, ETHEREUM, proposal.clone()));
+		System::set_block_number(1 + PROPOSAL_LIFETIME);
+		assert_noop!(
+			ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_B), 1, ETHEREUM, proposal),
+			Error::<Test>::ProposalExpired
+		);
+		assert_eq!(free_balance(ERC20_TOKEN, BOB), 0);
+	});
+}
+
+#[test]
+fn reject_proposal_works() {
+	new_test_ext().execute_with(|| {
+		let proposal = erc20_proposal(500);
+		assert_ok!(ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_A), 1, ETHEREUM, proposal.clone()));
+		assert_ok!(ChainBridge::reject_proposal(Origin::signed(RELAYER_B), 1, ETHEREUM, proposal.clone()));
+		assert!(has_event(RawEvent::VoteAgainst(ETHEREUM, 1, RELAYER_B)));
+		assert_eq!(ChainBridge::votes(ETHEREUM, (1, proposal.clone())).unwrap().status, ProposalStatus::Initiated);
+
+		// Two of three relayers against leaves no way to reach the threshold
+		assert_ok!(ChainBridge::reject_proposal(Origin::signed(RELAYER_C), 1, ETHEREUM, proposal.clone()));
+		assert_eq!(ChainBridge::votes(ETHEREUM, (1, proposal)).unwrap().status, ProposalStatus::Rejected);
+		assert!(has_event(RawEvent::ProposalRejected(ETHEREUM, 1)));
+		assert_eq!(free_balance(ERC20_TOKEN, BOB), 0);
+	});
+}
+
+#[test]
+fn eval_vote_state_works_after_threshold_change() {
+	new_test_ext().execute_with(|| {
+		let proposal = erc20_proposal(500);
+		assert_noop!(
+			ChainBridge::eval_vote_state(Origin::signed(ALICE), 1, ETHEREUM, proposal.clone()),
+			Error::<Test>::ProposalDoesNotExist
+		);
+
+		assert_ok!(ChainBridge::acknowledge_proposal(Origin::signed(RELAYER_A), 1, ETHEREUM, proposal.clone()));
+		assert_ok!(ChainBridge::set_threshold(Origin::root(), 1));
+		assert_ok!(ChainBridge::eval_vote_state(Origin::signed(ALICE), 1, ETHEREUM, proposal.clone()));
+		assert_eq!(free_balance(ERC20_TOKEN, BOB), 500);
+
+		assert_noop!(
+			ChainBridge::eval_vote_state(Origin::signed(ALICE), 1, ETHEREUM, proposal),
+			Error::<Test>::ProposalAlreadyComplete
+		);
+	});
+}
+
+#[test]
+fn failed_execution_keeps_the_vote() {
+	new_test_ext().execute_with(|| {
+		// Nothing is locked in the bridge account yet
+		let proposal = Proposal::Fungible { recipient: BOB, amount: 60, resource_id: NATIVE_RESOURCE };
+		approve(1, proposal.clone());
+		assert!(has_event(RawEvent::ProposalFailed(ETHEREUM, 1)));
+		assert_eq!(free_balance(AssetId::POLKADEX, BOB), 0);
+		assert_eq!(ChainBridge::votes(ETHEREUM, (1, proposal)).unwrap().status, ProposalStatus::Approved);
+	});
+}
+
+#[test]
+fn nonfungible_round_trip_works() {
+	new_test_ext().execute_with(|| {
+		let token_id = U256::from(7);
+		approve(1, erc721_proposal(7));
+		assert_eq!(Nft::owner(&ERC721_TOKEN, token_id), Some(BOB));
+		assert_eq!(Nft::token(H160([2u8; 20]), token_id).map(|token| token.metadata), Some(b"ipfs://token".to_vec()));
+
+		assert_noop!(
+			ChainBridge::transfer_nonfungible(Origin::signed(ALICE), token_id, RECIPIENT.to_vec(), ETHEREUM, ERC721_RESOURCE),
+			nft::Error::<Test>::NotTokenOwner
+		);
+		assert_noop!(
+			ChainBridge::transfer_nonfungible(Origin::signed(BOB), token_id, RECIPIENT.to_vec(), ETHEREUM, ERC20_RESOURCE),
+			Error::<Test>::InvalidResourceAsset
+		);
+
+		assert_ok!(ChainBridge::transfer_nonfungible(Origin::signed(BOB), token_id, RECIPIENT.to_vec(), ETHEREUM, ERC721_RESOURCE));
+		assert_eq!(Nft::owner(&ERC721_TOKEN, token_id), None);
+		assert!(has_event(RawEvent::NonFungibleTransfer(ETHEREUM, 1, ERC721_RESOURCE, token_id, RECIPIENT.to_vec(), b"ipfs://token".to_vec())));
+	});
+}
+
+#[test]
+fn nonfungible_proposal_fails_for_existing_token() {
+	new_test_ext().execute_with(|| {
+		approve(1, erc721_proposal(7));
+		approve(2, erc721_proposal(7));
+		assert!(has_event(RawEvent::ProposalFailed(ETHEREUM, 2)));
+		assert_eq!(Nft::owner(&ERC721_TOKEN, U256::from(7)), Some(BOB));
+	});
+}
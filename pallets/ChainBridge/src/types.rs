@@ -0,0 +1,81 @@
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::U256;
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+/// Identifier of a chain connected to the bridge, as assigned by the ChainBridge deployment.
+pub type ChainId = u8;
+/// Nonce of a deposit, unique per chain of origin.
+pub type DepositNonce = u64;
+/// Identifier of a bridged asset, shared by the bridge contracts of every chain.
+pub type ResourceId = [u8; 32];
+
+/// Transfer from another chain submitted by the relayers.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum Proposal<AccountId, Balance> {
+    /// Mints `amount` of the fungible asset of the resource to the recipient.
+    Fungible {
+        recipient: AccountId,
+        amount: Balance,
+        resource_id: ResourceId,
+    },
+    /// Mints the token `token_id` of the non-fungible asset of the resource to the recipient.
+    NonFungible {
+        recipient: AccountId,
+        token_id: U256,
+        resource_id: ResourceId,
+        metadata: Vec<u8>,
+    },
+}
+
+/// Outcome of the vote on a proposal.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ProposalStatus {
+    Initiated,
+    Approved,
+    Rejected,
+}
+
+impl Default for ProposalStatus {
+    fn default() -> Self {
+        ProposalStatus::Initiated
+    }
+}
+
+/// Votes of the relayers on a proposal.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct ProposalVotes<AccountId, BlockNumber> {
+    pub votes_for: Vec<AccountId>,
+    pub votes_against: Vec<AccountId>,
+    pub status: ProposalStatus,
+    /// Block after which the proposal can no longer be voted on.
+    pub expiry: BlockNumber,
+}
+
+impl<AccountId: PartialEq, BlockNumber: PartialOrd> ProposalVotes<AccountId, BlockNumber> {
+    /// Settles the vote once either side reaches `threshold` out of `total` relayers.
+    pub fn try_to_complete(&mut self, threshold: u32, total: u32) -> ProposalStatus {
+        if self.votes_for.len() >= threshold as usize {
+            self.status = ProposalStatus::Approved;
+        } else if total >= threshold && self.votes_against.len() as u32 + threshold > total {
+            // Not enough relayers left to approve
+            self.status = ProposalStatus::Rejected;
+        }
+        self.status
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.status != ProposalStatus::Initiated
+    }
+
+    pub fn has_voted(&self, who: &AccountId) -> bool {
+        self.votes_for.contains(who) || self.votes_against.contains(who)
+    }
+
+    pub fn is_expired(&self, now: BlockNumber) -> bool {
+        self.expiry <= now
+    }
+}
@@ -1,12 +1,14 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use codec::{Decode, Encode};
-use frame_support::{decl_error, decl_event, decl_module, decl_storage, dispatch, Parameter};
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, dispatch, ensure, transactional, Parameter};
 use frame_support::sp_std::fmt::Debug;
-use frame_support::traits::Get;
+use frame_support::traits::{EnsureOrigin, Get};
+use frame_support::weights::{Pays, PostDispatchInfo};
 use frame_system::ensure_signed;
+use polkadex_primitives::nft::NonFungibleLedger;
 use sp_core::Hasher;
-use sp_runtime::traits::{AtLeast32BitUnsigned, IdentifyAccount, MaybeSerializeDeserialize, Member, Verify};
+use sp_runtime::traits::{AtLeast32BitUnsigned, CheckedAdd, IdentifyAccount, MaybeSerializeDeserialize, Member, One, Saturating, Verify, Zero};
 
 use types::{AccountData, Order, OrderType::AskLimit, OrderType::AskMarket, OrderType::BidLimit, OrderType::BidMarket};
 
@@ -15,7 +17,7 @@ mod mock;
 
 #[cfg(test)]
 mod tests;
-mod types;
+pub mod types;
 
 /// Configure the pallet by specifying the parameters and types on which it depends.
 pub trait Config: frame_system::Config {
@@ -23,24 +25,46 @@ pub trait Config: frame_system::Config {
     type Event: From<Event<Self>> + Into<<Self as frame_system::Config>::Event>;
     /// Balance Type
     type Balance: Parameter + Member + AtLeast32BitUnsigned + Default + Copy + Debug + MaybeSerializeDeserialize;
+    /// Identifier of the traded assets
+    type AssetId: Parameter + Member + Copy + Ord + Debug + MaybeSerializeDeserialize;
+    /// Ledger of the non-fungible tokens, traded one per order
+    type Nft: NonFungibleLedger<Self::AccountId, Self::AssetId>;
     /// Public Key of the trader
     type Public: IdentifyAccount<AccountId=Self::AccountId>;
     /// Signature provided by the trade
     type Signature: Verify<Signer=Self::Public> + Member + Decode + Encode;
+    /// Successful settlements each provider can submit without fees in a block
+    type FreeSettlementsPerBlock: Get<u32>;
+    /// Origin allowed to register and remove providers
+    type AdminOrigin: EnsureOrigin<Self::Origin>;
 }
 
 decl_storage! {
 	trait Store for Module<T: Config> as Engine {
 	    Providers get(fn get_providers): map hasher(blake2_128_concat) T::AccountId => Option<u32>;
-	    Traders get(fn get_traders): map hasher(blake2_128_concat) T::AccountId => AccountData<T::Hash,T::Balance>;
+	    Traders get(fn get_traders): map hasher(blake2_128_concat) T::AccountId => AccountData<T::AssetId,T::Balance>;
+	    /// Quantity of orders already filled, by trader and order nonce.
+	    OrderFills get(fn order_fills): double_map hasher(blake2_128_concat) T::AccountId, hasher(twox_64_concat) u64 => T::Balance;
+	    /// Fee-less settlements of each provider, with the block they were counted in.
+	    FreeSettlements get(fn free_settlements): map hasher(blake2_128_concat) T::AccountId => (T::BlockNumber, u32);
 	}
 }
 
 
 decl_event!(
-	pub enum Event<T> where AccountId = <T as frame_system::Config>::AccountId {
+	pub enum Event<T> where
+		AccountId = <T as frame_system::Config>::AccountId,
+		AssetId = <T as Config>::AssetId,
+		Balance = <T as Config>::Balance
+	{
 		/// parameters. [something, who]
 		SomethingStored(u32, AccountId),
+		/// Provider registered. \[provider, provider_id\]
+		ProviderRegistered(AccountId, u32),
+		/// Provider removed. \[provider\]
+		ProviderRemoved(AccountId),
+		/// Trade between a maker and a taker order settled. \[maker, taker, asset_id, price, quantity\]
+		TradeSettled(AccountId, AccountId, AssetId, Balance, Balance),
 	}
 );
 
@@ -55,6 +79,16 @@ decl_error! {
 		NonceAlreadyUsed,
 		/// OrderType Given For Maker and Taker is invalid
 		InvalidOrderTypeCombination,
+		/// Fill exceeds the remaining quantity of the order
+		OrderOverfilled,
+		/// Orders of non-fungible tokens must be for a quantity of one of the same token
+		InvalidNonFungibleOrder,
+		/// Seller does not own the non-fungible token
+		NonFungibleNotOwned,
+		/// The provider is already registered
+		ProviderAlreadyRegistered,
+		/// Maker and taker orders are for different assets
+		AssetMismatch,
 	}
 }
 
@@ -67,27 +101,85 @@ decl_module! {
 		// Events must be initialized if they are used by the pallet.
 		fn deposit_event() = default;
 
-		#[weight = 0]
-		pub fn settle_trade(origin, maker: Order<T::Balance, T::AccountId, T::Hash, T::Signature>, taker: Order<T::Balance, T::AccountId, T::Hash, T::Signature>) -> dispatch::DispatchResult {
+		const FreeSettlementsPerBlock: u32 = T::FreeSettlementsPerBlock::get();
+
+		/// Settles a trade between a maker and a taker order. Successful settlements by registered
+		/// providers are free, up to `FreeSettlementsPerBlock` in each block, while failed ones are
+		/// always charged. Each settlement records the fills of both orders, so a filled order
+		/// cannot be replayed to use up the free quota.
+        /// # Arguments
+        ///
+        /// * `origin` - Registered provider.
+        ///
+        /// * `maker` - Resting order.
+        ///
+        /// * `taker` - Order matched against `maker`.
+		#[weight = 10000]
+		#[transactional]
+		pub fn settle_trade(origin, maker: Order<T::Balance, T::AccountId, T::AssetId, T::Signature>, taker: Order<T::Balance, T::AccountId, T::AssetId, T::Signature>) -> dispatch::DispatchResultWithPostInfo {
 			let cloud_provider = ensure_signed(origin)?;
-			Self::settle(cloud_provider, maker, taker)?;
-			// Return a successful DispatchResult
+			Self::settle(cloud_provider.clone(), maker, taker)?;
+			Ok(PostDispatchInfo { actual_weight: None, pays_fee: Self::use_free_settlement(&cloud_provider) })
+		}
+
+		/// Registers a provider allowed to settle trades.
+        /// # Arguments
+        ///
+        /// * `origin` - Admin origin, usually governance.
+        ///
+        /// * `provider` - Account of the provider.
+        ///
+        /// * `provider_id` - Identifier of the provider.
+		#[weight = 10000]
+		pub fn register_provider(origin, provider: T::AccountId, provider_id: u32) -> dispatch::DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(!<Providers<T>>::contains_key(&provider), Error::<T>::ProviderAlreadyRegistered);
+			<Providers<T>>::insert(&provider, provider_id);
+			Self::deposit_event(RawEvent::ProviderRegistered(provider, provider_id));
+			Ok(())
+		}
+
+		/// Removes a provider.
+        /// # Arguments
+        ///
+        /// * `origin` - Admin origin, usually governance.
+        ///
+        /// * `provider` - Account of the provider.
+		#[weight = 10000]
+		pub fn remove_provider(origin, provider: T::AccountId) -> dispatch::DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(<Providers<T>>::contains_key(&provider), Error::<T>::CallerNotARegisteredProvider);
+			<Providers<T>>::remove(&provider);
+			<FreeSettlements<T>>::remove(&provider);
+			Self::deposit_event(RawEvent::ProviderRemoved(provider));
 			Ok(())
 		}
 	}
 }
 
 impl<T: Config> Module<T> {
-    fn settle(provider: T::AccountId, maker: Order<T::Balance, T::AccountId, T::Hash, T::Signature>, taker: Order<T::Balance, T::AccountId, T::Hash, T::Signature>) -> Result<(), Error<T>> {
+    fn settle(provider: T::AccountId, maker: Order<T::Balance, T::AccountId, T::AssetId, T::Signature>, taker: Order<T::Balance, T::AccountId, T::AssetId, T::Signature>) -> Result<(), Error<T>> {
         // Checks if the caller is a registered member of callers
         if <Providers<T>>::contains_key(provider) {
             // Checks if the signatures are valid for maker and taker
             if Self::verify_signatures(&maker, &taker) {
                 // Verify nonce
-                let maker_account: AccountData<T::Hash, T::Balance> = <Traders<T>>::get(&maker.trader);
-                let taker_account: AccountData<T::Hash, T::Balance> = <Traders<T>>::get(&taker.trader);
+                let maker_account: AccountData<T::AssetId, T::Balance> = <Traders<T>>::get(&maker.trader);
+                let taker_account: AccountData<T::AssetId, T::Balance> = <Traders<T>>::get(&taker.trader);
                 if Self::verify_nonces(&maker_account, &maker, &taker_account, &taker) {
+                    ensure!(maker.asset_id == taker.asset_id, Error::<T>::AssetMismatch);
+                    ensure!(maker.token_id == taker.token_id, Error::<T>::InvalidNonFungibleOrder);
+                    Self::verify_order_asset(&maker)?;
+                    Self::verify_order_asset(&taker)?;
+                    // Fills are shared with `fill_order`, so an order cannot be settled here and
+                    // matched again by another pallet
+                    let quantity = sp_std::cmp::min(Self::remaining_quantity(&maker), Self::remaining_quantity(&taker));
                     Self::execute(&maker_account, &maker, &taker_account, &taker)?;
+                    Self::transfer_non_fungible(&maker, &taker)?;
+                    Self::add_fill(&maker, quantity)?;
+                    Self::add_fill(&taker, quantity)?;
+                    // Trades execute at the maker's price
+                    Self::deposit_event(RawEvent::TradeSettled(maker.trader.clone(), taker.trader.clone(), maker.asset_id, maker.price, quantity));
                     Ok(())
                 } else {
                     Err(Error::<T>::NonceAlreadyUsed)
@@ -100,10 +192,97 @@ impl<T: Config> Module<T> {
         }
     }
 
-    fn verify_signatures(maker: &Order<T::Balance, T::AccountId, T::Hash, T::Signature>, taker: &Order<T::Balance, T::AccountId, T::Hash, T::Signature>) -> bool {
-        let maker_msg = (maker.price, maker.quantity, maker.order_type, maker.nonce).using_encoded(<T as frame_system::Config>::Hashing::hash);
-        let taker_msg = (taker.price, taker.quantity, taker.order_type, taker.nonce).using_encoded(<T as frame_system::Config>::Hashing::hash);
-        maker.signature.verify(&(maker_msg.encode()[..]), &maker.trader) && taker.signature.verify(&(taker_msg.encode()[..]), &taker.trader)
+    /// Counts a settlement of `provider` against its quota of the current block, returning
+    /// whether it pays fees.
+    fn use_free_settlement(provider: &T::AccountId) -> Pays {
+        let now = <frame_system::Module<T>>::block_number();
+        <FreeSettlements<T>>::mutate(provider, |(block, count)| {
+            if *block != now {
+                *block = now;
+                *count = 0;
+            }
+            if *count < T::FreeSettlementsPerBlock::get() {
+                *count += 1;
+                Pays::No
+            } else {
+                Pays::Yes
+            }
+        })
+    }
+
+    fn verify_signatures(maker: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>, taker: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>) -> bool {
+        Self::verify_signature(maker) && Self::verify_signature(taker)
+    }
+
+    /// Checks that an order is signed by its trader. The signed message covers the asset so that
+    /// an order cannot be replayed on another market.
+    pub fn verify_signature(order: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>) -> bool {
+        let msg = (order.price, order.quantity, order.order_type, order.nonce, order.asset_id, order.token_id).using_encoded(<T as frame_system::Config>::Hashing::hash);
+        order.signature.verify(&(msg.encode()[..]), &order.trader)
+    }
+
+    /// Quantity of an order not filled yet.
+    pub fn remaining_quantity(order: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>) -> T::Balance {
+        order.quantity.saturating_sub(<OrderFills<T>>::get(&order.trader, order.nonce))
+    }
+
+    /// Fills `quantity` of a resting maker order against `taker`, outside of a provider
+    /// settlement. The caller is responsible for moving the funds.
+    pub fn fill_order(maker: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>, taker: &T::AccountId, quantity: T::Balance) -> Result<(), Error<T>> {
+        if !Self::verify_signature(maker) {
+            return Err(Error::<T>::TraderSignatureMismatch);
+        }
+        match maker.order_type {
+            BidLimit | AskLimit => {}
+            _ => return Err(Error::<T>::InvalidOrderTypeCombination),
+        }
+        Self::verify_order_asset(maker)?;
+        Self::add_fill(maker, quantity)?;
+        Self::deposit_event(RawEvent::TradeSettled(maker.trader.clone(), taker.clone(), maker.asset_id, maker.price, quantity));
+        Ok(())
+    }
+
+    /// Adds `quantity` to the filled quantity of an order, which cannot exceed its quantity.
+    fn add_fill(order: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>, quantity: T::Balance) -> Result<(), Error<T>> {
+        <OrderFills<T>>::try_mutate(&order.trader, order.nonce, |filled| -> Result<(), Error<T>> {
+            let total = filled.checked_add(&quantity).ok_or(Error::<T>::OrderOverfilled)?;
+            if total > order.quantity {
+                return Err(Error::<T>::OrderOverfilled);
+            }
+            *filled = total;
+            Ok(())
+        })
+    }
+
+    /// Checks that an order of a non-fungible token is for exactly one token and, when selling,
+    /// that the trader owns it. Orders of fungible assets must not name a token.
+    fn verify_order_asset(order: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>) -> Result<(), Error<T>> {
+        if !T::Nft::is_collection(&order.asset_id) {
+            ensure!(order.token_id.is_none(), Error::<T>::InvalidNonFungibleOrder);
+            return Ok(());
+        }
+        let token_id = order.token_id.ok_or(Error::<T>::InvalidNonFungibleOrder)?;
+        ensure!(order.quantity == One::one(), Error::<T>::InvalidNonFungibleOrder);
+        match order.order_type {
+            AskLimit | AskMarket => {
+                ensure!(T::Nft::owner(&order.asset_id, token_id).as_ref() == Some(&order.trader), Error::<T>::NonFungibleNotOwned);
+            }
+            BidLimit | BidMarket => {}
+        }
+        Ok(())
+    }
+
+    /// Moves the token of a non-fungible trade from the seller to the buyer.
+    fn transfer_non_fungible(maker: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>, taker: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>) -> Result<(), Error<T>> {
+        let token_id = match maker.token_id {
+            Some(token_id) => token_id,
+            None => return Ok(()),
+        };
+        let (seller, buyer) = match maker.order_type {
+            AskLimit | AskMarket => (&maker.trader, &taker.trader),
+            BidLimit | BidMarket => (&taker.trader, &maker.trader),
+        };
+        T::Nft::transfer(&maker.asset_id, token_id, seller, buyer).map_err(|_| Error::<T>::NonFungibleNotOwned)
     }
 
     /// When verifying nonce take into account,
@@ -112,16 +291,19 @@ impl<T: Config> Module<T> {
     /// 3) Storage Access ( Storage shouldn't increase too much)
     /// 4) Easy to Verify
     /// The first principle is to prevent replay attacks.
-    fn verify_nonces(maker_account: &AccountData<T::Hash, T::Balance>, maker: &Order<T::Balance, T::AccountId, T::Hash, T::Signature>,
-                     taker_account: &AccountData<T::Hash, T::Balance>, taker: &Order<T::Balance, T::AccountId, T::Hash, T::Signature>) -> bool {
-        // FIXME: Implement an efficient nonce verification
-
-        true
+    ///
+    /// An order is identified by the nonce of its trader and can be settled as long as it is not
+    /// filled, which covers partial orders. `OrderFills` keeps one entry per order settled, so the
+    /// maker and the taker must not share a trader and a nonce.
+    fn verify_nonces(_maker_account: &AccountData<T::AssetId, T::Balance>, maker: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>,
+                     _taker_account: &AccountData<T::AssetId, T::Balance>, taker: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>) -> bool {
+        (maker.trader != taker.trader || maker.nonce != taker.nonce)
+            && !Self::remaining_quantity(maker).is_zero() && !Self::remaining_quantity(taker).is_zero()
     }
 
     /// TODO: Transfer the funds between maker & taker
-    fn execute(mut maker_account: &AccountData<T::Hash, T::Balance>, maker: &Order<T::Balance, T::AccountId, T::Hash, T::Signature>,
-               mut taker_account: &AccountData<T::Hash, T::Balance>, taker: &Order<T::Balance, T::AccountId, T::Hash, T::Signature>) -> Result<(), Error<T>> {
+    fn execute(mut maker_account: &AccountData<T::AssetId, T::Balance>, maker: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>,
+               mut taker_account: &AccountData<T::AssetId, T::Balance>, taker: &Order<T::Balance, T::AccountId, T::AssetId, T::Signature>) -> Result<(), Error<T>> {
         match (maker.order_type, taker.order_type) {
             (BidLimit, AskLimit) => {
                 Ok(())
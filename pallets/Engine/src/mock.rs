@@ -1,61 +1,137 @@
-use crate::{Module, Config};
-use sp_core::H256;
-use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
+use crate as orderbook_engine;
+use codec::Encode;
+use frame_support::parameter_types;
+use frame_system::EnsureRoot;
+use polkadex_primitives::assets;
+use sp_core::{sr25519, Pair, H160, H256, U256};
 use sp_runtime::{
-	traits::{BlakeTwo256, IdentityLookup}, testing::Header, Perbill,
+	testing::Header,
+	traits::{BlakeTwo256, Hash, IdentifyAccount, IdentityLookup, Verify},
+	MultiSignature,
 };
-use frame_system as system;
 
-impl_outer_origin! {
-	pub enum Origin for Test {}
-}
+use crate::types::{Order, OrderType};
+
+pub type Signature = MultiSignature;
+pub type Public = <Signature as Verify>::Signer;
+pub type AccountId = <Public as IdentifyAccount>::AccountId;
+pub type Balance = u128;
+pub type AssetId = assets::AssetId;
+pub type TestOrder = Order<Balance, AccountId, AssetId, Signature>;
+
+pub const DOT: AssetId = AssetId::ChainsafeErc20(H160([2u8; 20]));
+pub const BTC: AssetId = AssetId::ChainsafeErc20(H160([3u8; 20]));
+pub const KITTIES: AssetId = AssetId::ChainsafeErc721(H160([4u8; 20]));
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
 
 // Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		Nft: nft::{Module, Call, Storage, Event<T>},
+		OrderbookEngine: orderbook_engine::{Module, Call, Storage, Event<T>},
+	}
+);
 
-#[derive(Clone, Eq, PartialEq)]
-pub struct Test;
 parameter_types! {
 	pub const BlockHashCount: u64 = 250;
-	pub const MaximumBlockWeight: Weight = 1024;
-	pub const MaximumBlockLength: u32 = 2 * 1024;
-	pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+	pub const SS58Prefix: u8 = 42;
 }
 
-impl system::Config for Test {
+impl frame_system::Config for Test {
 	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
 	type Origin = Origin;
-	type Call = ();
+	type Call = Call;
 	type Index = u64;
 	type BlockNumber = u64;
 	type Hash = H256;
 	type Hashing = BlakeTwo256;
-	type AccountId = u64;
+	type AccountId = AccountId;
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
-	type Event = ();
+	type Event = Event;
 	type BlockHashCount = BlockHashCount;
-	type MaximumBlockWeight = MaximumBlockWeight;
-	type DbWeight = ();
-	type BlockExecutionWeight = ();
-	type ExtrinsicBaseWeight = ();
-	type MaximumExtrinsicWeight = MaximumBlockWeight;
-	type MaximumBlockLength = MaximumBlockLength;
-	type AvailableBlockRatio = AvailableBlockRatio;
 	type Version = ();
-	type PalletInfo = ();
+	type PalletInfo = PalletInfo;
 	type AccountData = ();
 	type OnNewAccount = ();
 	type OnKilledAccount = ();
 	type SystemWeightInfo = ();
+	type SS58Prefix = SS58Prefix;
+}
+
+parameter_types! {
+	pub const MaxMetadataLength: u32 = 256;
+}
+
+impl nft::Config for Test {
+	type Event = Event;
+	type MaxMetadataLength = MaxMetadataLength;
+}
+
+parameter_types! {
+	pub const FreeSettlementsPerBlock: u32 = 2;
+}
+
+impl orderbook_engine::Config for Test {
+	type Event = Event;
+	type Balance = Balance;
+	type AssetId = AssetId;
+	type Nft = Nft;
+	type Public = Public;
+	type Signature = Signature;
+	type FreeSettlementsPerBlock = FreeSettlementsPerBlock;
+	type AdminOrigin = EnsureRoot<AccountId>;
+}
+
+/// sr25519 test key derived from `//<name>`.
+pub fn key(name: &str) -> sr25519::Pair {
+	sr25519::Pair::from_string(&format!("//{}", name), None).expect("static values are valid; qed")
 }
 
-impl Config for Test {
-	type Event = ();
+/// Account id of the key derived from `//<name>`.
+pub fn account(name: &str) -> AccountId {
+	Public::from(key(name).public()).into_account()
 }
 
-pub type TemplateModule = Module<Test>;
+/// Builds an order for the trader of `pair` and signs it the way `verify_signature` expects.
+pub fn signed_order(pair: &sr25519::Pair, price: Balance, quantity: Balance, order_type: OrderType, nonce: u64, asset_id: AssetId) -> TestOrder {
+	sign(pair, price, quantity, order_type, nonce, asset_id, None)
+}
+
+/// Builds a signed order for one non-fungible token of a collection.
+pub fn signed_nft_order(pair: &sr25519::Pair, price: Balance, order_type: OrderType, nonce: u64, asset_id: AssetId, token_id: U256) -> TestOrder {
+	sign(pair, price, 1, order_type, nonce, asset_id, Some(token_id))
+}
+
+fn sign(pair: &sr25519::Pair, price: Balance, quantity: Balance, order_type: OrderType, nonce: u64, asset_id: AssetId, token_id: Option<U256>) -> TestOrder {
+	let msg = BlakeTwo256::hash_of(&(price, quantity, order_type, nonce, asset_id, token_id));
+	Order {
+		price,
+		quantity,
+		order_type,
+		trader: Public::from(pair.public()).into_account(),
+		nonce,
+		asset_id,
+		token_id,
+		signature: pair.sign(&msg.encode()).into(),
+	}
+}
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
-}
\ No newline at end of file
+	let t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	// Events are not recorded in the genesis block
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
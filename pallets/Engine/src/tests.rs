@@ -1,23 +1,464 @@
-use crate::{Error, mock::*};
-use frame_support::{assert_ok, assert_noop};
+use crate::{mock::*, Error, Providers, RawEvent};
+use crate::types::OrderType::{self, AskLimit, AskMarket, BidLimit, BidMarket};
+use frame_support::{assert_noop, assert_ok, dispatch::DispatchResult};
+use frame_support::weights::Pays;
+use polkadex_primitives::nft::NonFungibleLedger;
+use sp_core::U256;
+use sp_runtime::traits::BadOrigin;
+
+const PRICE: Balance = 100;
+const QUANTITY: Balance = 10;
+
+fn register_provider(provider: &AccountId) {
+	Providers::<Test>::insert(provider, 1);
+}
+
+fn fill(maker: &TestOrder, taker: &AccountId, quantity: Balance) -> DispatchResult {
+	OrderbookEngine::fill_order(maker, taker, quantity).map_err(Into::into)
+}
+
+fn last_event() -> Event {
+	System::events().pop().expect("Event expected").event
+}
+
+/// Maker order from Alice and taker order from Bob on `DOT`.
+fn orders(maker_type: OrderType, taker_type: OrderType) -> (TestOrder, TestOrder) {
+	orders_with_nonce(maker_type, taker_type, 1)
+}
+
+/// Maker and taker orders of `orders` with another nonce, so that they are not filled yet.
+fn orders_with_nonce(maker_type: OrderType, taker_type: OrderType, nonce: u64) -> (TestOrder, TestOrder) {
+	(
+		signed_order(&key("Alice"), PRICE, QUANTITY, maker_type, nonce, DOT),
+		signed_order(&key("Bob"), PRICE + 5, QUANTITY / 2, taker_type, nonce, DOT),
+	)
+}
+
+#[test]
+fn settle_trade_works() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		let (maker, taker) = orders(AskLimit, BidLimit);
+
+		assert_ok!(OrderbookEngine::settle_trade(Origin::signed(provider), maker, taker));
+		// Trades execute at the maker's price for the smaller of the two quantities
+		assert_eq!(
+			last_event(),
+			Event::orderbook_engine(RawEvent::TradeSettled(account("Alice"), account("Bob"), DOT, PRICE, QUANTITY / 2)),
+		);
+	});
+}
+
+#[test]
+fn settle_trade_accepts_valid_order_type_combinations() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		for (nonce, &(maker_type, taker_type)) in [
+			(BidLimit, AskLimit),
+			(BidLimit, AskMarket),
+			(AskLimit, BidLimit),
+			(AskLimit, BidMarket),
+		].iter().enumerate() {
+			let (maker, taker) = orders_with_nonce(maker_type, taker_type, nonce as u64);
+			assert_ok!(OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker, taker));
+		}
+	});
+}
+
+#[test]
+fn settle_trade_fails_for_invalid_order_type_combinations() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		for &(maker_type, taker_type) in [
+			(BidLimit, BidLimit),
+			(BidLimit, BidMarket),
+			(AskLimit, AskLimit),
+			(AskLimit, AskMarket),
+			// Market orders never rest on the book, so they cannot be makers
+			(BidMarket, AskLimit),
+			(BidMarket, AskMarket),
+			(AskMarket, BidLimit),
+			(AskMarket, BidMarket),
+		].iter() {
+			let (maker, taker) = orders(maker_type, taker_type);
+			assert_noop!(
+				OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker, taker),
+				Error::<Test>::InvalidOrderTypeCombination
+			);
+		}
+	});
+}
+
+#[test]
+fn settle_trade_records_fills() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		let (maker, taker) = orders(AskLimit, BidLimit);
+
+		assert_ok!(OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker.clone(), taker.clone()));
+		assert_eq!(OrderbookEngine::remaining_quantity(&maker), QUANTITY - QUANTITY / 2);
+		assert_eq!(OrderbookEngine::remaining_quantity(&taker), 0);
+
+		// The filled taker order cannot be settled again
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker.clone(), taker),
+			Error::<Test>::NonceAlreadyUsed
+		);
+
+		// Another taker only gets the rest of the maker order
+		let taker = signed_order(&key("Bob"), PRICE, QUANTITY, BidLimit, 2, DOT);
+		assert_ok!(OrderbookEngine::settle_trade(Origin::signed(provider), maker.clone(), taker.clone()));
+		assert_eq!(
+			last_event(),
+			Event::orderbook_engine(RawEvent::TradeSettled(account("Alice"), account("Bob"), DOT, PRICE, QUANTITY - QUANTITY / 2)),
+		);
+		assert_eq!(OrderbookEngine::remaining_quantity(&maker), 0);
+		assert_eq!(OrderbookEngine::remaining_quantity(&taker), QUANTITY / 2);
+	});
+}
+
+#[test]
+fn orders_settled_by_providers_cannot_be_filled_again() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		let (maker, taker) = orders(AskLimit, BidLimit);
+		assert_ok!(OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker.clone(), taker));
+
+		assert_noop!(fill(&maker, &account("Dave"), QUANTITY), Error::<Test>::OrderOverfilled);
+		assert_ok!(fill(&maker, &account("Dave"), QUANTITY - QUANTITY / 2));
+
+		// And the other way around
+		let taker = signed_order(&key("Bob"), PRICE, QUANTITY, BidLimit, 2, DOT);
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider), maker, taker),
+			Error::<Test>::NonceAlreadyUsed
+		);
+	});
+}
+
+#[test]
+fn settle_trade_fails_for_orders_with_the_same_nonce() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		// Both orders would be recorded under the same fill
+		let maker = signed_order(&key("Alice"), PRICE, QUANTITY, AskLimit, 1, DOT);
+		let taker = signed_order(&key("Alice"), PRICE, QUANTITY, BidLimit, 1, DOT);
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker.clone(), taker),
+			Error::<Test>::NonceAlreadyUsed
+		);
+
+		// The same nonce of different traders identifies different orders
+		let taker = signed_order(&key("Bob"), PRICE, QUANTITY, BidLimit, 1, DOT);
+		assert_ok!(OrderbookEngine::settle_trade(Origin::signed(provider), maker, taker));
+	});
+}
+
+#[test]
+fn register_provider_works() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		assert_ok!(OrderbookEngine::register_provider(Origin::root(), provider.clone(), 7));
+		assert_eq!(OrderbookEngine::get_providers(&provider), Some(7));
+		assert_eq!(last_event(), Event::orderbook_engine(RawEvent::ProviderRegistered(provider.clone(), 7)));
+
+		assert_noop!(
+			OrderbookEngine::register_provider(Origin::root(), provider, 8),
+			Error::<Test>::ProviderAlreadyRegistered
+		);
+	});
+}
+
+#[test]
+fn remove_provider_works() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		assert_noop!(
+			OrderbookEngine::remove_provider(Origin::root(), provider.clone()),
+			Error::<Test>::CallerNotARegisteredProvider
+		);
+		register_provider(&provider);
+		assert_ok!(OrderbookEngine::remove_provider(Origin::root(), provider.clone()));
+		assert_eq!(OrderbookEngine::get_providers(&provider), None);
+		assert_eq!(last_event(), Event::orderbook_engine(RawEvent::ProviderRemoved(provider.clone())));
+
+		let (maker, taker) = orders(AskLimit, BidLimit);
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider), maker, taker),
+			Error::<Test>::CallerNotARegisteredProvider
+		);
+	});
+}
+
+#[test]
+fn provider_administration_requires_admin_origin() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		assert_noop!(OrderbookEngine::register_provider(Origin::signed(provider.clone()), provider.clone(), 1), BadOrigin);
+		register_provider(&provider);
+		assert_noop!(OrderbookEngine::remove_provider(Origin::signed(provider.clone()), provider), BadOrigin);
+	});
+}
+
+#[test]
+fn settlements_are_free_up_to_the_quota() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		let settle = |nonce| {
+			let (maker, taker) = orders_with_nonce(AskLimit, BidLimit, nonce);
+			OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker, taker).unwrap().pays_fee
+		};
+
+		assert_eq!(settle(1), Pays::No);
+		assert_eq!(settle(2), Pays::No);
+		assert_eq!(settle(3), Pays::Yes);
+		assert_eq!(OrderbookEngine::free_settlements(&provider), (1, 2));
+
+		// The quota is renewed in every block
+		System::set_block_number(2);
+		assert_eq!(settle(4), Pays::No);
+		assert_eq!(OrderbookEngine::free_settlements(&provider), (2, 1));
+	});
+}
+
+#[test]
+fn failed_settlements_are_charged() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		let (maker, taker) = orders(BidLimit, BidLimit);
+		let error = OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker, taker).unwrap_err();
+		assert_eq!(error.post_info.pays_fee, Pays::Yes);
+		assert_eq!(OrderbookEngine::free_settlements(&provider), (0, 0));
+
+		let (maker, taker) = orders(AskLimit, BidLimit);
+		let error = OrderbookEngine::settle_trade(Origin::signed(account("Dave")), maker, taker).unwrap_err();
+		assert_eq!(error.post_info.pays_fee, Pays::Yes);
+	});
+}
+
+#[test]
+fn replayed_settlements_are_charged() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		let (maker, taker) = orders(AskLimit, BidLimit);
+		assert_eq!(OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker.clone(), taker.clone()).unwrap().pays_fee, Pays::No);
+
+		let error = OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker, taker).unwrap_err();
+		assert_eq!(error.error, Error::<Test>::NonceAlreadyUsed.into());
+		assert_eq!(error.post_info.pays_fee, Pays::Yes);
+		assert_eq!(OrderbookEngine::free_settlements(&provider), (1, 1));
+	});
+}
+
+#[test]
+fn settle_trade_fails_for_unregistered_provider() {
+	new_test_ext().execute_with(|| {
+		let (maker, taker) = orders(AskLimit, BidLimit);
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(account("Charlie")), maker, taker),
+			Error::<Test>::CallerNotARegisteredProvider
+		);
+	});
+}
+
+#[test]
+fn settle_trade_fails_for_unsigned_origin() {
+	new_test_ext().execute_with(|| {
+		let (maker, taker) = orders(AskLimit, BidLimit);
+		assert_noop!(OrderbookEngine::settle_trade(Origin::none(), maker, taker), BadOrigin);
+	});
+}
+
+#[test]
+fn settle_trade_fails_for_signature_mismatch() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+
+		// Maker order signed by a key other than the trader's
+		let (mut maker, taker) = orders(AskLimit, BidLimit);
+		maker.trader = account("Dave");
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker, taker),
+			Error::<Test>::TraderSignatureMismatch
+		);
+
+		// Taker price changed after signing
+		let (maker, mut taker) = orders(AskLimit, BidLimit);
+		taker.price += 1;
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker, taker),
+			Error::<Test>::TraderSignatureMismatch
+		);
+
+		// Order signed for another market
+		let (mut maker, taker) = orders(AskLimit, BidLimit);
+		maker.asset_id = BTC;
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider), maker, taker),
+			Error::<Test>::TraderSignatureMismatch
+		);
+	});
+}
+
+#[test]
+fn fill_order_works() {
+	new_test_ext().execute_with(|| {
+		let maker = signed_order(&key("Alice"), PRICE, QUANTITY, BidLimit, 7, DOT);
+		let taker = account("Bob");
+		assert_eq!(OrderbookEngine::remaining_quantity(&maker), QUANTITY);
+
+		assert_ok!(fill(&maker, &taker, 4));
+		assert_eq!(OrderbookEngine::remaining_quantity(&maker), QUANTITY - 4);
+		assert_eq!(
+			last_event(),
+			Event::orderbook_engine(RawEvent::TradeSettled(account("Alice"), taker.clone(), DOT, PRICE, 4)),
+		);
+
+		assert_ok!(fill(&maker, &taker, QUANTITY - 4));
+		assert_eq!(OrderbookEngine::remaining_quantity(&maker), 0);
+	});
+}
+
+#[test]
+fn fill_order_fails_when_overfilled() {
+	new_test_ext().execute_with(|| {
+		let maker = signed_order(&key("Alice"), PRICE, QUANTITY, AskLimit, 7, DOT);
+		let taker = account("Bob");
+		assert_ok!(fill(&maker, &taker, QUANTITY - 1));
+		assert_noop!(fill(&maker, &taker, 2), Error::<Test>::OrderOverfilled);
+		assert_noop!(fill(&maker, &taker, Balance::max_value()), Error::<Test>::OrderOverfilled);
+	});
+}
+
+#[test]
+fn fill_order_fails_for_market_or_unsigned_maker() {
+	new_test_ext().execute_with(|| {
+		let taker = account("Bob");
+		let maker = signed_order(&key("Alice"), PRICE, QUANTITY, AskMarket, 7, DOT);
+		assert_noop!(fill(&maker, &taker, 1), Error::<Test>::InvalidOrderTypeCombination);
+
+		let mut maker = signed_order(&key("Alice"), PRICE, QUANTITY, AskLimit, 7, DOT);
+		maker.quantity += 1;
+		assert_noop!(fill(&maker, &taker, 1), Error::<Test>::TraderSignatureMismatch);
+	});
+}
+
+fn mint_kitty(owner: &AccountId, token_id: u64) {
+	assert_ok!(<Nft as NonFungibleLedger<_, _>>::mint(&KITTIES, U256::from(token_id), owner, Vec::new()));
+}
 
 #[test]
-fn it_works_for_default_value() {
+fn settle_trade_works_for_non_fungible_token() {
 	new_test_ext().execute_with(|| {
-		// Dispatch a signed extrinsic.
-		assert_ok!(TemplateModule::do_something(Origin::signed(1), 42));
-		// Read pallet storage and assert an expected result.
-		assert_eq!(TemplateModule::something(), Some(42));
+		let provider = account("Charlie");
+		register_provider(&provider);
+		mint_kitty(&account("Alice"), 7);
+		let maker = signed_nft_order(&key("Alice"), PRICE, AskLimit, 1, KITTIES, U256::from(7));
+		let taker = signed_nft_order(&key("Bob"), PRICE, BidMarket, 1, KITTIES, U256::from(7));
+
+		assert_ok!(OrderbookEngine::settle_trade(Origin::signed(provider), maker, taker));
+		assert_eq!(<Nft as NonFungibleLedger<_, _>>::owner(&KITTIES, U256::from(7)), Some(account("Bob")));
+		assert_eq!(
+			last_event(),
+			Event::orderbook_engine(RawEvent::TradeSettled(account("Alice"), account("Bob"), KITTIES, PRICE, 1)),
+		);
 	});
 }
 
 #[test]
-fn correct_error_for_none_value() {
+fn settle_trade_transfers_non_fungible_token_to_bidding_maker() {
 	new_test_ext().execute_with(|| {
-		// Ensure the expected error is thrown when no value is present.
+		let provider = account("Charlie");
+		register_provider(&provider);
+		mint_kitty(&account("Bob"), 7);
+		let maker = signed_nft_order(&key("Alice"), PRICE, BidLimit, 1, KITTIES, U256::from(7));
+		let taker = signed_nft_order(&key("Bob"), PRICE, AskMarket, 1, KITTIES, U256::from(7));
+
+		assert_ok!(OrderbookEngine::settle_trade(Origin::signed(provider), maker, taker));
+		assert_eq!(<Nft as NonFungibleLedger<_, _>>::owner(&KITTIES, U256::from(7)), Some(account("Alice")));
+	});
+}
+
+#[test]
+fn settle_trade_fails_for_orders_of_different_assets() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		let maker = signed_order(&key("Alice"), PRICE, QUANTITY, AskLimit, 1, DOT);
+		let taker = signed_order(&key("Bob"), PRICE, QUANTITY, BidLimit, 1, BTC);
 		assert_noop!(
-			TemplateModule::cause_error(Origin::signed(1)),
-			Error::<Test>::NoneValue
+			OrderbookEngine::settle_trade(Origin::signed(provider), maker, taker),
+			Error::<Test>::AssetMismatch
 		);
 	});
-}
\ No newline at end of file
+}
+
+#[test]
+fn settle_trade_fails_for_invalid_non_fungible_orders() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		mint_kitty(&account("Alice"), 7);
+		mint_kitty(&account("Alice"), 8);
+		let maker = signed_nft_order(&key("Alice"), PRICE, AskLimit, 1, KITTIES, U256::from(7));
+
+		// Orders for different tokens of the collection
+		let taker = signed_nft_order(&key("Bob"), PRICE, BidLimit, 1, KITTIES, U256::from(8));
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker.clone(), taker),
+			Error::<Test>::InvalidNonFungibleOrder
+		);
+
+		// Order of the collection without a token, for more than one token
+		let taker = signed_order(&key("Bob"), PRICE, 2, BidLimit, 1, KITTIES);
+		let maker_without_token = signed_order(&key("Alice"), PRICE, 2, AskLimit, 1, KITTIES);
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider.clone()), maker_without_token, taker),
+			Error::<Test>::InvalidNonFungibleOrder
+		);
+
+		// Fungible orders cannot name a token
+		let maker = signed_nft_order(&key("Alice"), PRICE, AskLimit, 1, DOT, U256::from(7));
+		let taker = signed_nft_order(&key("Bob"), PRICE, BidLimit, 1, DOT, U256::from(7));
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider), maker, taker),
+			Error::<Test>::InvalidNonFungibleOrder
+		);
+	});
+}
+
+#[test]
+fn settle_trade_fails_when_seller_does_not_own_the_token() {
+	new_test_ext().execute_with(|| {
+		let provider = account("Charlie");
+		register_provider(&provider);
+		mint_kitty(&account("Dave"), 7);
+		let maker = signed_nft_order(&key("Alice"), PRICE, AskLimit, 1, KITTIES, U256::from(7));
+		let taker = signed_nft_order(&key("Bob"), PRICE, BidLimit, 1, KITTIES, U256::from(7));
+		assert_noop!(
+			OrderbookEngine::settle_trade(Origin::signed(provider), maker, taker),
+			Error::<Test>::NonFungibleNotOwned
+		);
+	});
+}
+
+#[test]
+fn fill_order_fills_non_fungible_token_once() {
+	new_test_ext().execute_with(|| {
+		let maker = signed_nft_order(&key("Alice"), PRICE, BidLimit, 7, KITTIES, U256::from(7));
+		let taker = account("Bob");
+		assert_ok!(fill(&maker, &taker, 1));
+		assert_eq!(OrderbookEngine::remaining_quantity(&maker), 0);
+		assert_noop!(fill(&maker, &taker, 1), Error::<Test>::OrderOverfilled);
+	});
+}
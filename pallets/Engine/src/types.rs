@@ -1,4 +1,5 @@
 use codec::{Decode, Encode};
+use sp_core::U256;
 use sp_std::collections::btree_map;
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
@@ -22,6 +23,8 @@ pub struct Order<Balance, AccountId, AssetID, Signature> {
     pub trader: AccountId,
     pub nonce: u64,
     pub asset_id: AssetID,
+    /// Token traded when the asset is a collection of non-fungible tokens.
+    pub token_id: Option<U256>,
     pub signature: Signature,
 }
 
@@ -0,0 +1,168 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Ledger of the non-fungible tokens of ERC721 collections.
+//!
+//! Tokens are identified by the address of their collection and a token id, as on the chain the
+//! collection comes from. They are minted and burnt by the bridge through `NonFungibleLedger`
+//! and can be transferred between accounts, including by orderbook settlements.
+
+use codec::{Decode, Encode};
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, dispatch, ensure};
+use frame_support::dispatch::{DispatchError, DispatchResult};
+use frame_support::traits::Get;
+use frame_system::ensure_signed;
+use polkadex_primitives::assets::AssetId;
+use polkadex_primitives::nft::NonFungibleLedger;
+use sp_core::{H160, U256};
+use sp_runtime::RuntimeDebug;
+use sp_runtime::traits::StaticLookup;
+use sp_std::vec::Vec;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+/// Owner and metadata of a token.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct TokenInfo<AccountId> {
+    pub owner: AccountId,
+    /// Metadata of the token on its chain of origin, usually a token URI.
+    pub metadata: Vec<u8>,
+}
+
+/// Configure the pallet by specifying the parameters and types on which it depends.
+pub trait Config: frame_system::Config {
+    /// Because this pallet emits events, it depends on the runtime's definition of an event.
+    type Event: From<Event<Self>> + Into<<Self as frame_system::Config>::Event>;
+    /// Maximum length of the metadata of a token
+    type MaxMetadataLength: Get<u32>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as Nft {
+		/// Tokens by collection and token id.
+		Tokens get(fn token): double_map hasher(blake2_128_concat) H160, hasher(blake2_128_concat) U256 => Option<TokenInfo<T::AccountId>>;
+		/// Tokens held by each account.
+		OwnedTokens get(fn owned_token): double_map hasher(blake2_128_concat) T::AccountId, hasher(blake2_128_concat) (H160, U256) => bool;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		AccountId = <T as frame_system::Config>::AccountId
+	{
+		/// Token created. \[collection, token_id, owner\]
+		Minted(H160, U256, AccountId),
+		/// Token destroyed. \[collection, token_id, owner\]
+		Burned(H160, U256, AccountId),
+		/// Token transferred. \[collection, token_id, from, to\]
+		Transferred(H160, U256, AccountId, AccountId),
+	}
+);
+
+decl_error! {
+	pub enum Error for Module<T: Config> {
+		/// Asset is not a collection of non-fungible tokens
+		NotACollection,
+		/// Token does not exist
+		TokenNotFound,
+		/// Token already exists
+		TokenAlreadyExists,
+		/// Account does not own the token
+		NotTokenOwner,
+		/// Metadata longer than the maximum
+		MetadataTooLong,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		const MaxMetadataLength: u32 = T::MaxMetadataLength::get();
+
+		fn deposit_event() = default;
+
+		/// Transfers a token to another account.
+        /// # Arguments
+        ///
+        /// * `origin` - Owner of the token.
+        ///
+        /// * `dest` - Receiver of the token.
+        ///
+        /// * `collection` - Address of the collection.
+        ///
+        /// * `token_id` - Token transferred.
+		#[weight = 10000]
+		pub fn transfer(origin, dest: <T::Lookup as StaticLookup>::Source, collection: H160, token_id: U256) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			let dest = T::Lookup::lookup(dest)?;
+			Self::do_transfer(collection, token_id, &who, &dest)
+		}
+	}
+}
+
+impl<T: Config> Module<T> {
+    fn collection(asset_id: &AssetId) -> Result<H160, Error<T>> {
+        match asset_id {
+            AssetId::ChainsafeErc721(collection) => Ok(*collection),
+            _ => Err(Error::<T>::NotACollection),
+        }
+    }
+
+    pub fn do_mint(collection: H160, token_id: U256, owner: &T::AccountId, metadata: Vec<u8>) -> DispatchResult {
+        ensure!(metadata.len() <= T::MaxMetadataLength::get() as usize, Error::<T>::MetadataTooLong);
+        ensure!(!Tokens::<T>::contains_key(collection, token_id), Error::<T>::TokenAlreadyExists);
+        Tokens::<T>::insert(collection, token_id, TokenInfo { owner: owner.clone(), metadata });
+        OwnedTokens::<T>::insert(owner, (collection, token_id), true);
+        Self::deposit_event(RawEvent::Minted(collection, token_id, owner.clone()));
+        Ok(())
+    }
+
+    pub fn do_burn(collection: H160, token_id: U256, owner: &T::AccountId) -> Result<Vec<u8>, DispatchError> {
+        let token = Self::token(collection, token_id).ok_or(Error::<T>::TokenNotFound)?;
+        ensure!(token.owner == *owner, Error::<T>::NotTokenOwner);
+        Tokens::<T>::remove(collection, token_id);
+        OwnedTokens::<T>::remove(owner, (collection, token_id));
+        Self::deposit_event(RawEvent::Burned(collection, token_id, owner.clone()));
+        Ok(token.metadata)
+    }
+
+    pub fn do_transfer(collection: H160, token_id: U256, from: &T::AccountId, to: &T::AccountId) -> DispatchResult {
+        Tokens::<T>::try_mutate(collection, token_id, |token| -> DispatchResult {
+            let token = token.as_mut().ok_or(Error::<T>::TokenNotFound)?;
+            ensure!(token.owner == *from, Error::<T>::NotTokenOwner);
+            token.owner = to.clone();
+            Ok(())
+        })?;
+        OwnedTokens::<T>::remove(from, (collection, token_id));
+        OwnedTokens::<T>::insert(to, (collection, token_id), true);
+        Self::deposit_event(RawEvent::Transferred(collection, token_id, from.clone(), to.clone()));
+        Ok(())
+    }
+}
+
+impl<T: Config> NonFungibleLedger<T::AccountId, AssetId> for Module<T> {
+    fn is_collection(asset_id: &AssetId) -> bool {
+        matches!(asset_id, AssetId::ChainsafeErc721(_))
+    }
+
+    fn owner(asset_id: &AssetId, token_id: U256) -> Option<T::AccountId> {
+        let collection = Self::collection(asset_id).ok()?;
+        Self::token(collection, token_id).map(|token| token.owner)
+    }
+
+    fn mint(asset_id: &AssetId, token_id: U256, owner: &T::AccountId, metadata: Vec<u8>) -> DispatchResult {
+        Self::do_mint(Self::collection(asset_id)?, token_id, owner, metadata)
+    }
+
+    fn burn(asset_id: &AssetId, token_id: U256, owner: &T::AccountId) -> Result<Vec<u8>, DispatchError> {
+        Self::do_burn(Self::collection(asset_id)?, token_id, owner)
+    }
+
+    fn transfer(asset_id: &AssetId, token_id: U256, from: &T::AccountId, to: &T::AccountId) -> DispatchResult {
+        Self::do_transfer(Self::collection(asset_id)?, token_id, from, to)
+    }
+}
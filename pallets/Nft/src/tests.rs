@@ -0,0 +1,89 @@
+use crate::{mock::*, Error, RawEvent, TokenInfo};
+use frame_support::{assert_noop, assert_ok};
+use polkadex_primitives::assets::AssetId;
+use polkadex_primitives::nft::NonFungibleLedger;
+use sp_core::{H160, U256};
+use sp_runtime::traits::BadOrigin;
+
+const TOKEN: U256 = U256([42, 0, 0, 0]);
+
+fn last_event() -> Event {
+	System::events().pop().expect("Event expected").event
+}
+
+fn mint_to_alice() {
+	assert_ok!(<Nft as NonFungibleLedger<_, _>>::mint(&COLLECTION_ASSET, TOKEN, &ALICE, b"ipfs://42".to_vec()));
+}
+
+#[test]
+fn mint_works() {
+	new_test_ext().execute_with(|| {
+		mint_to_alice();
+		assert_eq!(Nft::token(COLLECTION, TOKEN), Some(TokenInfo { owner: ALICE, metadata: b"ipfs://42".to_vec() }));
+		assert!(Nft::owned_token(ALICE, (COLLECTION, TOKEN)));
+		assert_eq!(Nft::owner(&COLLECTION_ASSET, TOKEN), Some(ALICE));
+		assert_eq!(last_event(), Event::nft(RawEvent::Minted(COLLECTION, TOKEN, ALICE)));
+	});
+}
+
+#[test]
+fn mint_fails() {
+	new_test_ext().execute_with(|| {
+		mint_to_alice();
+		assert_noop!(
+			<Nft as NonFungibleLedger<_, _>>::mint(&COLLECTION_ASSET, TOKEN, &BOB, Vec::new()),
+			Error::<Test>::TokenAlreadyExists
+		);
+		assert_noop!(
+			<Nft as NonFungibleLedger<_, _>>::mint(&AssetId::ChainsafeErc20(COLLECTION), U256::one(), &BOB, Vec::new()),
+			Error::<Test>::NotACollection
+		);
+		assert_noop!(
+			<Nft as NonFungibleLedger<_, _>>::mint(&COLLECTION_ASSET, U256::one(), &BOB, vec![0u8; MAX_METADATA_LENGTH as usize + 1]),
+			Error::<Test>::MetadataTooLong
+		);
+	});
+}
+
+#[test]
+fn is_collection_works() {
+	assert!(Nft::is_collection(&COLLECTION_ASSET));
+	assert!(!Nft::is_collection(&AssetId::ChainsafeErc20(COLLECTION)));
+	assert!(!Nft::is_collection(&AssetId::POLKADEX));
+}
+
+#[test]
+fn transfer_works() {
+	new_test_ext().execute_with(|| {
+		mint_to_alice();
+		assert_ok!(Nft::transfer(Origin::signed(ALICE), BOB, COLLECTION, TOKEN));
+		assert_eq!(Nft::owner(&COLLECTION_ASSET, TOKEN), Some(BOB));
+		assert!(!Nft::owned_token(ALICE, (COLLECTION, TOKEN)));
+		assert!(Nft::owned_token(BOB, (COLLECTION, TOKEN)));
+		assert_eq!(last_event(), Event::nft(RawEvent::Transferred(COLLECTION, TOKEN, ALICE, BOB)));
+	});
+}
+
+#[test]
+fn transfer_fails() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(Nft::transfer(Origin::signed(ALICE), BOB, COLLECTION, TOKEN), Error::<Test>::TokenNotFound);
+		mint_to_alice();
+		assert_noop!(Nft::transfer(Origin::signed(BOB), BOB, COLLECTION, TOKEN), Error::<Test>::NotTokenOwner);
+		assert_noop!(Nft::transfer(Origin::signed(ALICE), BOB, H160([8u8; 20]), TOKEN), Error::<Test>::TokenNotFound);
+		assert_noop!(Nft::transfer(Origin::none(), BOB, COLLECTION, TOKEN), BadOrigin);
+	});
+}
+
+#[test]
+fn burn_works() {
+	new_test_ext().execute_with(|| {
+		mint_to_alice();
+		assert_noop!(Nft::burn(&COLLECTION_ASSET, TOKEN, &BOB), Error::<Test>::NotTokenOwner);
+		assert_eq!(Nft::burn(&COLLECTION_ASSET, TOKEN, &ALICE), Ok(b"ipfs://42".to_vec()));
+		assert_eq!(Nft::token(COLLECTION, TOKEN), None);
+		assert!(!Nft::owned_token(ALICE, (COLLECTION, TOKEN)));
+		assert_eq!(last_event(), Event::nft(RawEvent::Burned(COLLECTION, TOKEN, ALICE)));
+		assert_noop!(Nft::burn(&COLLECTION_ASSET, TOKEN, &ALICE), Error::<Test>::TokenNotFound);
+	});
+}
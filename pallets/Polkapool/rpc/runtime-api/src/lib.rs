@@ -0,0 +1,32 @@
+//! Runtime API definition for the Polkapool pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_runtime::DispatchError;
+use sp_std::vec::Vec;
+
+pub use polkapool::types::{SwapQuote, SwapRoute};
+
+sp_api::decl_runtime_apis! {
+	pub trait PolkapoolApi<AccountId, AssetId, Balance> where
+		AccountId: Codec,
+		AssetId: Codec,
+		Balance: Codec,
+	{
+		/// Per-hop amounts, fees and price impacts of swapping exactly `supply_amount` along `path`.
+		fn quote_swap_with_exact_supply(path: Vec<AssetId>, supply_amount: Balance) -> Result<SwapQuote<Balance>, DispatchError>;
+		/// Per-hop amounts, fees and price impacts of receiving exactly `target_amount` along `path`.
+		fn quote_swap_with_exact_target(path: Vec<AssetId>, target_amount: Balance) -> Result<SwapQuote<Balance>, DispatchError>;
+		/// Reserves of both assets and total LP shares of a pool.
+		fn get_pool_reserves(currency_id_a: AssetId, currency_id_b: AssetId) -> (Balance, Balance, Balance);
+		/// LP shares held by an account in a pool.
+		fn get_lp_holdings(who: AccountId, currency_id_a: AssetId, currency_id_b: AssetId) -> Balance;
+		/// LP shares minted to the protocol fee account in a pool.
+		fn get_accrued_protocol_fee(currency_id_a: AssetId, currency_id_b: AssetId) -> Balance;
+		/// Route with the highest output for exactly `supply_amount`, if any route exists.
+		fn find_best_route_with_exact_supply(supply_currency_id: AssetId, target_currency_id: AssetId, supply_amount: Balance) -> Option<SwapRoute<AssetId, Balance>>;
+		/// Route with the lowest input to receive exactly `target_amount`, if any route exists.
+		fn find_best_route_with_exact_target(supply_currency_id: AssetId, target_currency_id: AssetId, target_amount: Balance) -> Option<SwapRoute<AssetId, Balance>>;
+	}
+}
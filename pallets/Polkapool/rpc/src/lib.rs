@@ -0,0 +1,176 @@
+//! RPC interface for the Polkapool pallet.
+
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_rpc::number::NumberOrHex;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use polkapool_rpc_runtime_api::{PolkapoolApi as PolkapoolRuntimeApi, SwapQuote, SwapRoute};
+
+/// Polkapool RPC methods.
+///
+/// Balances are passed and returned as [`NumberOrHex`], as JSON numbers cannot hold all `u128`
+/// values.
+#[rpc]
+pub trait PolkapoolApi<BlockHash, AccountId, AssetId> {
+	/// Quotes a swap of exactly `supply_amount` of the first asset of `path`.
+	#[rpc(name = "polkapool_quoteSwapWithExactSupply")]
+	fn quote_swap_with_exact_supply(&self, path: Vec<AssetId>, supply_amount: NumberOrHex, at: Option<BlockHash>) -> Result<SwapQuote<NumberOrHex>>;
+
+	/// Quotes a swap receiving exactly `target_amount` of the last asset of `path`.
+	#[rpc(name = "polkapool_quoteSwapWithExactTarget")]
+	fn quote_swap_with_exact_target(&self, path: Vec<AssetId>, target_amount: NumberOrHex, at: Option<BlockHash>) -> Result<SwapQuote<NumberOrHex>>;
+
+	/// Returns the reserves of both assets and the total LP shares of a pool.
+	#[rpc(name = "polkapool_getPoolReserves")]
+	fn get_pool_reserves(&self, currency_id_a: AssetId, currency_id_b: AssetId, at: Option<BlockHash>) -> Result<(NumberOrHex, NumberOrHex, NumberOrHex)>;
+
+	/// Returns the LP shares held by `who` in a pool.
+	#[rpc(name = "polkapool_getLpHoldings")]
+	fn get_lp_holdings(&self, who: AccountId, currency_id_a: AssetId, currency_id_b: AssetId, at: Option<BlockHash>) -> Result<NumberOrHex>;
+
+	/// Returns the LP shares minted to the protocol fee account in a pool.
+	#[rpc(name = "polkapool_getAccruedProtocolFee")]
+	fn get_accrued_protocol_fee(&self, currency_id_a: AssetId, currency_id_b: AssetId, at: Option<BlockHash>) -> Result<NumberOrHex>;
+
+	/// Searches all pools for the route giving the most output for exactly `supply_amount`.
+	#[rpc(name = "polkapool_findBestRouteWithExactSupply")]
+	fn find_best_route_with_exact_supply(&self, supply_currency_id: AssetId, target_currency_id: AssetId, supply_amount: NumberOrHex, at: Option<BlockHash>) -> Result<Option<SwapRoute<AssetId, NumberOrHex>>>;
+
+	/// Searches all pools for the route needing the least input to receive exactly `target_amount`.
+	#[rpc(name = "polkapool_findBestRouteWithExactTarget")]
+	fn find_best_route_with_exact_target(&self, supply_currency_id: AssetId, target_currency_id: AssetId, target_amount: NumberOrHex, at: Option<BlockHash>) -> Result<Option<SwapRoute<AssetId, NumberOrHex>>>;
+}
+
+/// Error code returned when the runtime API call fails.
+const RUNTIME_ERROR: i64 = 1;
+/// Error code returned when the pallet rejects the quote.
+const QUOTE_ERROR: i64 = 2;
+/// Error code returned when an amount does not fit in the runtime's balance type.
+const INVALID_AMOUNT_ERROR: i64 = 3;
+
+/// Implements the [`PolkapoolApi`] RPC trait by calling into the runtime.
+pub struct Polkapool<C, B, Balance> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<(B, Balance)>,
+}
+
+impl<C, B, Balance> Polkapool<C, B, Balance> {
+	/// Creates a new instance of the Polkapool RPC handler.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+fn runtime_error(err: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Unable to query Polkapool.".into(),
+		data: Some(format!("{:?}", err).into()),
+	}
+}
+
+fn quote_error(err: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(QUOTE_ERROR),
+		message: "Unable to quote swap.".into(),
+		data: Some(format!("{:?}", err).into()),
+	}
+}
+
+fn invalid_amount_error(amount: NumberOrHex) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(INVALID_AMOUNT_ERROR),
+		message: "Amount does not fit in a balance.".into(),
+		data: Some(format!("{:?}", amount).into()),
+	}
+}
+
+fn to_balance<Balance: TryFrom<NumberOrHex>>(amount: NumberOrHex) -> Result<Balance> {
+	Balance::try_from(amount.clone()).map_err(|_| invalid_amount_error(amount))
+}
+
+fn to_rpc_quote<Balance: Into<NumberOrHex>>(quote: SwapQuote<Balance>) -> SwapQuote<NumberOrHex> {
+	SwapQuote {
+		amounts: quote.amounts.into_iter().map(Into::into).collect(),
+		fees: quote.fees.into_iter().map(Into::into).collect(),
+		price_impacts: quote.price_impacts,
+	}
+}
+
+fn to_rpc_route<AssetId, Balance: Into<NumberOrHex>>(route: SwapRoute<AssetId, Balance>) -> SwapRoute<AssetId, NumberOrHex> {
+	SwapRoute {
+		path: route.path,
+		quote: to_rpc_quote(route.quote),
+	}
+}
+
+impl<C, Block, AccountId, AssetId, Balance> PolkapoolApi<<Block as BlockT>::Hash, AccountId, AssetId> for Polkapool<C, Block, Balance>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: PolkapoolRuntimeApi<Block, AccountId, AssetId, Balance>,
+	AccountId: Codec,
+	AssetId: Codec,
+	Balance: Codec + TryFrom<NumberOrHex> + Into<NumberOrHex> + Send + Sync + 'static,
+{
+	fn quote_swap_with_exact_supply(&self, path: Vec<AssetId>, supply_amount: NumberOrHex, at: Option<<Block as BlockT>::Hash>) -> Result<SwapQuote<NumberOrHex>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.quote_swap_with_exact_supply(&at, path, to_balance(supply_amount)?)
+			.map_err(runtime_error)?
+			.map(to_rpc_quote)
+			.map_err(quote_error)
+	}
+
+	fn quote_swap_with_exact_target(&self, path: Vec<AssetId>, target_amount: NumberOrHex, at: Option<<Block as BlockT>::Hash>) -> Result<SwapQuote<NumberOrHex>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.quote_swap_with_exact_target(&at, path, to_balance(target_amount)?)
+			.map_err(runtime_error)?
+			.map(to_rpc_quote)
+			.map_err(quote_error)
+	}
+
+	fn get_pool_reserves(&self, currency_id_a: AssetId, currency_id_b: AssetId, at: Option<<Block as BlockT>::Hash>) -> Result<(NumberOrHex, NumberOrHex, NumberOrHex)> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.get_pool_reserves(&at, currency_id_a, currency_id_b)
+			.map(|(pool_a, pool_b, total_shares)| (pool_a.into(), pool_b.into(), total_shares.into()))
+			.map_err(runtime_error)
+	}
+
+	fn get_lp_holdings(&self, who: AccountId, currency_id_a: AssetId, currency_id_b: AssetId, at: Option<<Block as BlockT>::Hash>) -> Result<NumberOrHex> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.get_lp_holdings(&at, who, currency_id_a, currency_id_b).map(Into::into).map_err(runtime_error)
+	}
+
+	fn get_accrued_protocol_fee(&self, currency_id_a: AssetId, currency_id_b: AssetId, at: Option<<Block as BlockT>::Hash>) -> Result<NumberOrHex> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.get_accrued_protocol_fee(&at, currency_id_a, currency_id_b).map(Into::into).map_err(runtime_error)
+	}
+
+	fn find_best_route_with_exact_supply(&self, supply_currency_id: AssetId, target_currency_id: AssetId, supply_amount: NumberOrHex, at: Option<<Block as BlockT>::Hash>) -> Result<Option<SwapRoute<AssetId, NumberOrHex>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.find_best_route_with_exact_supply(&at, supply_currency_id, target_currency_id, to_balance(supply_amount)?)
+			.map(|route| route.map(to_rpc_route))
+			.map_err(runtime_error)
+	}
+
+	fn find_best_route_with_exact_target(&self, supply_currency_id: AssetId, target_currency_id: AssetId, target_amount: NumberOrHex, at: Option<<Block as BlockT>::Hash>) -> Result<Option<SwapRoute<AssetId, NumberOrHex>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.find_best_route_with_exact_target(&at, supply_currency_id, target_currency_id, to_balance(target_amount)?)
+			.map(|route| route.map(to_rpc_route))
+			.map_err(runtime_error)
+	}
+}
@@ -0,0 +1,203 @@
+//! Uniswap v3 style concentrated liquidity math.
+//!
+//! Prices are stored as square roots in unsigned Q64.64 fixed point, tick `i` corresponding to
+//! the price `1.0001 ^ i` of the first asset of the pool quoted in the second one. Liquidity is
+//! the `L` of the virtual reserves `x = L / √P` and `y = L * √P` of the active range.
+
+use frame_support::sp_std::convert::TryInto;
+use sp_arithmetic::Permill;
+use sp_core::{U256, U512};
+
+/// Smallest tick, the price `1.0001 ^ MIN_TICK` is about `2 ^ -64`.
+pub const MIN_TICK: i32 = -443_636;
+/// Largest tick, the price `1.0001 ^ MAX_TICK` is about `2 ^ 64`.
+pub const MAX_TICK: i32 = 443_636;
+/// Square root price at `MIN_TICK`.
+pub const MIN_SQRT_PRICE: u128 = 0x1_0001_3b51;
+/// Square root price at `MAX_TICK`.
+pub const MAX_SQRT_PRICE: u128 = 0xfffe_c4b1_35bb_7f32_a81b_33b6;
+
+/// `1 / √1.0001 ^ (2 ^ i)` in Q128.128, rounded up.
+const TICK_FACTORS: [u128; 19] = [
+    0xfffcb933bd6fad37aa2d162d1a594002,
+    0xfff97272373d413259a46990580e213a,
+    0xfff2e50f5f656932ef12357cf3c7fdcc,
+    0xffe5caca7e10e4e61c3624eaa0941cd0,
+    0xffcb9843d60f6159c9db58835c926644,
+    0xff973b41fa98c081472e6896dfb254c0,
+    0xff2ea16466c96a3843ec78b326b52861,
+    0xfe5dee046a99a2a811c461f1969c3053,
+    0xfcbe86c7900a88aedcffc83b479aa3a4,
+    0xf987a7253ac413176f2b074cf7815e54,
+    0xf3392b0822b70005940c7a398e4b70f3,
+    0xe7159475a2c29b7443b29c7fa6e889d9,
+    0xd097f3bdfd2022b8845ad8f792aa5826,
+    0xa9f746462d870fdf8a65dc1f90e061e5,
+    0x70d869a156d2a1b890bb3df62baf32f7,
+    0x31be135f97d08fd981231505542fcfa6,
+    0x9aa508b5b7a84e1c677de54f3e99bc9,
+    0x5d6af8dedb81196699c329225ee605,
+    0x2216e584f5fa1ea926041bedfe98,
+];
+
+/// Result of a single step of a swap within one tick range.
+pub struct SwapStep {
+    pub sqrt_price_next: u128,
+    pub amount_in: u128,
+    pub amount_out: u128,
+    pub fee_amount: u128,
+}
+
+fn mul_div(a: U256, b: U256, denominator: U256, round_up: bool) -> Option<U256> {
+    if denominator.is_zero() {
+        return None;
+    }
+    let product: U512 = a.full_mul(b);
+    let denominator = U512::from(denominator);
+    let mut result = product / denominator;
+    if round_up && !(product % denominator).is_zero() {
+        result = result.checked_add(U512::one())?;
+    }
+    result.try_into().ok()
+}
+
+/// Square root price at `tick`, `√(1.0001 ^ tick)` in Q64.64.
+pub fn sqrt_price_at_tick(tick: i32) -> Option<u128> {
+    if tick < MIN_TICK || tick > MAX_TICK {
+        return None;
+    }
+    let abs_tick = (tick as i64).abs() as u32;
+    let mut ratio = U256::one() << 128;
+    for (bit, factor) in TICK_FACTORS.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = ratio.checked_mul(U256::from(*factor))? >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::max_value() / ratio;
+    }
+    // Q128.128 to Q64.64, rounded up
+    let remainder = ratio & U256::from(u64::max_value());
+    let sqrt_price = (ratio >> 64) + if remainder.is_zero() { U256::zero() } else { U256::one() };
+    sqrt_price.try_into().ok()
+}
+
+/// Greatest tick whose square root price is at most `sqrt_price`.
+pub fn tick_at_sqrt_price(sqrt_price: u128) -> Option<i32> {
+    if sqrt_price < MIN_SQRT_PRICE || sqrt_price > MAX_SQRT_PRICE {
+        return None;
+    }
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if sqrt_price_at_tick(mid)? <= sqrt_price {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Some(low)
+}
+
+/// Amount of the first asset between two square root prices: `L * (√b - √a) / (√a * √b)`.
+pub fn amount_0_delta(sqrt_price_a: u128, sqrt_price_b: u128, liquidity: u128, round_up: bool) -> Option<u128> {
+    let (lower, upper) = if sqrt_price_a <= sqrt_price_b { (sqrt_price_a, sqrt_price_b) } else { (sqrt_price_b, sqrt_price_a) };
+    if lower == 0 {
+        return None;
+    }
+    let numerator = U256::from(liquidity) << 64;
+    let amount = mul_div(numerator, U256::from(upper - lower), U256::from(upper), round_up)?;
+    let remainder_non_zero = round_up && !(amount % U256::from(lower)).is_zero();
+    let amount = amount / U256::from(lower) + if remainder_non_zero { U256::one() } else { U256::zero() };
+    amount.try_into().ok()
+}
+
+/// Amount of the second asset between two square root prices: `L * (√b - √a)`.
+pub fn amount_1_delta(sqrt_price_a: u128, sqrt_price_b: u128, liquidity: u128, round_up: bool) -> Option<u128> {
+    let (lower, upper) = if sqrt_price_a <= sqrt_price_b { (sqrt_price_a, sqrt_price_b) } else { (sqrt_price_b, sqrt_price_a) };
+    let amount = mul_div(U256::from(liquidity), U256::from(upper - lower), U256::one() << 64, round_up)?;
+    amount.try_into().ok()
+}
+
+/// Amounts of both assets backing `liquidity` in the range `[sqrt_price_lower, sqrt_price_upper)`
+/// when the pool is at `sqrt_price`.
+pub fn amounts_for_liquidity(sqrt_price: u128, sqrt_price_lower: u128, sqrt_price_upper: u128, liquidity: u128, round_up: bool) -> Option<(u128, u128)> {
+    if sqrt_price < sqrt_price_lower {
+        Some((amount_0_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?, 0))
+    } else if sqrt_price < sqrt_price_upper {
+        Some((
+            amount_0_delta(sqrt_price, sqrt_price_upper, liquidity, round_up)?,
+            amount_1_delta(sqrt_price_lower, sqrt_price, liquidity, round_up)?,
+        ))
+    } else {
+        Some((0, amount_1_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?))
+    }
+}
+
+/// Square root price after adding `amount_in` to the pool, rounded in favour of the pool.
+fn next_sqrt_price_from_input(sqrt_price: u128, liquidity: u128, amount_in: u128, zero_for_one: bool) -> Option<u128> {
+    if zero_for_one {
+        // L * √P / (L + amount * √P)
+        let numerator = U256::from(liquidity) << 64;
+        let denominator = numerator.checked_add(U256::from(amount_in).checked_mul(U256::from(sqrt_price))?)?;
+        mul_div(numerator, U256::from(sqrt_price), denominator, true)?.try_into().ok()
+    } else {
+        // √P + amount / L
+        let delta: u128 = mul_div(U256::from(amount_in), U256::one() << 64, U256::from(liquidity), false)?.try_into().ok()?;
+        sqrt_price.checked_add(delta)
+    }
+}
+
+/// Swaps as much of `amount_remaining` as possible without moving the price past
+/// `sqrt_price_target`. The fee is charged on top of `amount_in`.
+pub fn compute_swap_step(sqrt_price: u128, sqrt_price_target: u128, liquidity: u128, amount_remaining: u128, fee: Permill) -> Option<SwapStep> {
+    let zero_for_one = sqrt_price >= sqrt_price_target;
+    let amount_remaining_less_fee = amount_remaining.saturating_sub(fee.mul_ceil(amount_remaining));
+    let amount_in_to_target = if zero_for_one {
+        amount_0_delta(sqrt_price_target, sqrt_price, liquidity, true)?
+    } else {
+        amount_1_delta(sqrt_price, sqrt_price_target, liquidity, true)?
+    };
+
+    let sqrt_price_next = if amount_remaining_less_fee >= amount_in_to_target {
+        sqrt_price_target
+    } else {
+        next_sqrt_price_from_input(sqrt_price, liquidity, amount_remaining_less_fee, zero_for_one)?
+    };
+    let reached_target = sqrt_price_next == sqrt_price_target;
+
+    let (amount_in, amount_out) = if zero_for_one {
+        let amount_in = if reached_target { amount_in_to_target } else { amount_0_delta(sqrt_price_next, sqrt_price, liquidity, true)? };
+        (amount_in, amount_1_delta(sqrt_price_next, sqrt_price, liquidity, false)?)
+    } else {
+        let amount_in = if reached_target { amount_in_to_target } else { amount_1_delta(sqrt_price, sqrt_price_next, liquidity, true)? };
+        (amount_in, amount_0_delta(sqrt_price, sqrt_price_next, liquidity, false)?)
+    };
+
+    let fee_amount = if reached_target {
+        // amount_in * fee / (1 - fee), rounded up
+        let fee_parts = U256::from(fee.deconstruct());
+        let remaining_parts = U256::from(Permill::ACCURACY - fee.deconstruct());
+        mul_div(U256::from(amount_in), fee_parts, remaining_parts, true)?.try_into().ok()?
+    } else {
+        // the whole remainder is consumed, whatever is not swapped is the fee
+        amount_remaining.saturating_sub(amount_in)
+    };
+
+    Some(SwapStep { sqrt_price_next, amount_in, amount_out, fee_amount })
+}
+
+/// Fee per unit of liquidity in Q64.64, accumulated with wrapping arithmetic.
+pub fn fee_growth(fee_amount: u128, liquidity: u128) -> u128 {
+    if liquidity == 0 {
+        return 0;
+    }
+    mul_div(U256::from(fee_amount), U256::one() << 64, U256::from(liquidity), false)
+        .map(|growth| growth.low_u128())
+        .unwrap_or_default()
+}
+
+/// Fees earned by `liquidity` over a fee growth of `fee_growth_delta`.
+pub fn fees_owed(fee_growth_delta: u128, liquidity: u128) -> u128 {
+    (U256::from(fee_growth_delta).full_mul(U256::from(liquidity)) >> 64).low_u128()
+}
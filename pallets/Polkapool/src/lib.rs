@@ -1,19 +1,28 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 
-use frame_support::{decl_error, decl_event, decl_module, decl_storage, dispatch, ensure, sp_std, Parameter};
-use frame_support::dispatch::DispatchResult;
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, dispatch, ensure, sp_std, transactional, Parameter};
+use frame_support::dispatch::{DispatchError, DispatchResult};
+use frame_support::dispatch::{Dispatchable, PostDispatchInfo};
 
 use frame_support::sp_std::convert::TryInto;
 use frame_support::sp_std::fmt::Debug;
-use frame_support::traits::{ExistenceRequirement, Get};
+use frame_support::traits::{EnsureOrigin, Get};
+use frame_support::weights::GetDispatchInfo;
 use frame_system::ensure_signed;
-use sp_arithmetic::FixedPointNumber;
-use sp_arithmetic::traits::{CheckedDiv, CheckedMul, UniqueSaturatedFrom, AtLeast32BitUnsigned, };
+use orml_traits::MultiCurrency;
+use sp_arithmetic::{FixedPointNumber, FixedU128};
+use sp_arithmetic::traits::{CheckedDiv, CheckedMul, CheckedSub, UniqueSaturatedFrom, UniqueSaturatedInto, AtLeast32BitUnsigned, };
 use sp_std::vec::Vec;
-use sp_runtime::{ ModuleId};
+use sp_std::boxed::Box;
+use sp_std::collections::btree_map::BTreeMap;
+use sp_core::{U256, U512};
+use sp_runtime::{ModuleId, Permill, PerThing};
 use sp_std::vec;
-use sp_runtime::traits::{MaybeSerializeDeserialize, AccountIdConversion, Saturating, Zero, Member};
+use sp_runtime::traits::{MaybeSerializeDeserialize, AccountIdConversion, Saturating, StaticLookup, Zero, One, Member};
+
+use types::{AmplificationRamp, ConcentratedPool, PoolKind, Position, PriceAccumulator, SwapQuote, SwapRoute, TickInfo, WeightedPool};
+use concentrated_math::{MAX_SQRT_PRICE, MAX_TICK, MIN_SQRT_PRICE, MIN_TICK};
 
 
 
@@ -22,6 +31,29 @@ mod mock;
 
 #[cfg(test)]
 mod tests;
+pub mod types;
+mod stable_swap;
+mod weighted_math;
+mod concentrated_math;
+
+/// Maximum factor by which the amplification of a StableSwap pool can change in a single ramp.
+const MAX_AMPLIFICATION_CHANGE: u32 = 10;
+/// Minimum weight of an asset in a weighted pool.
+const MIN_WEIGHT: Permill = Permill::from_percent(1);
+/// Maximum swap fee of a weighted pool.
+const MAX_WEIGHTED_POOL_SWAP_FEE: Permill = Permill::from_percent(10);
+
+/// Maximum fee of a concentrated liquidity pool.
+const MAX_CONCENTRATED_POOL_FEE: Permill = Permill::from_percent(10);
+/// Maximum tick spacing of a concentrated liquidity pool.
+const MAX_TICK_SPACING: u32 = 16_384;
+
+/// Identifier of a weighted pool.
+pub type WeightedPoolId = u32;
+/// Identifier of a concentrated liquidity pool.
+pub type ConcentratedPoolId = u32;
+/// Identifier of a concentrated liquidity position.
+pub type PositionId = u64;
 
 /// Configure the pallet by specifying the parameters and types on which it depends.
 pub trait Config: frame_system::Config {
@@ -31,6 +63,27 @@ pub trait Config: frame_system::Config {
     type TradingPathLimit: Get<usize>;
     /// Balance
     type Balance: Parameter + Member + AtLeast32BitUnsigned + Default + Copy + Debug + MaybeSerializeDeserialize + sp_runtime::FixedPointOperand + sp_runtime::traits::Saturating;
+    /// Number of price observations kept per pool, one per block with a swap or liquidity
+    /// event, bounds the TWAP window
+    type TwapObservationPeriod: Get<u32>;
+    /// Origin allowed to change the pool parameters
+    type UpdateOrigin: EnsureOrigin<Self::Origin>;
+    /// Maximum amplification coefficient of StableSwap pools
+    type MaxAmplification: Get<u32>;
+    /// Minimum number of blocks over which the amplification of a StableSwap pool can be ramped
+    type MinAmplificationRampDuration: Get<Self::BlockNumber>;
+    /// Maximum number of assets in a weighted pool
+    type MaxWeightedPoolAssets: Get<u32>;
+    /// Maximum number of initialized ticks in a concentrated liquidity pool
+    type MaxTicksPerPool: Get<u32>;
+    /// Identifier of the assets traded in the pools
+    type CurrencyId: Parameter + Member + Copy + Ord + Debug + MaybeSerializeDeserialize;
+    /// Multi-currency ledger holding the assets of the pools
+    type Currency: MultiCurrency<Self::AccountId, CurrencyId = Self::CurrencyId, Balance = Self::Balance>;
+    /// Account receiving the protocol's share of swap fees as LP shares
+    type ProtocolFeeAccount: Get<Self::AccountId>;
+    /// The overarching call type, dispatched as the callback of flash swaps
+    type Call: Parameter + Dispatchable<Origin = Self::Origin, PostInfo = PostDispatchInfo> + GetDispatchInfo;
 }
 
 
@@ -40,19 +93,66 @@ decl_storage! {
 	trait Store for Module<T: Config> as PolkadexSwapEngine {
 	    /// Liquidity pool for specific pair(a tuple consisting of two sorted AssetIds).
 		/// (AssetID, AssetID) -> (Amount_0, Amount_1, Total LPShares)
-		LiquidityPool get(fn liquidity_pool): map hasher(twox_64_concat) (T::Hash,T::Hash) => (T::Balance, T::Balance, T::Balance);
+		LiquidityPool get(fn liquidity_pool): map hasher(twox_64_concat) (T::CurrencyId,T::CurrencyId) => (T::Balance, T::Balance, T::Balance);
 		/// LPShare holdings
-		LiquidityPoolHoldings get(fn holdings): map hasher(identity) (T::AccountId,(T::Hash,T::Hash)) => T::Balance;
-		/// Swapping Fee FIXME: This is not correct
-		SwappingFee: T::Balance = 3.into();
+		LiquidityPoolHoldings get(fn holdings): map hasher(identity) (T::AccountId,(T::CurrencyId,T::CurrencyId)) => T::Balance;
+		/// Fee charged on the supply amount of every hop of a swap
+		SwappingFee get(fn swap_fee): Permill = Permill::from_perthousand(3);
+		/// Cumulative prices of each liquidity pool, used to compute time-weighted average prices.
+		PriceAccumulators get(fn price_accumulator): map hasher(twox_64_concat) (T::CurrencyId,T::CurrencyId) => PriceAccumulator<T::BlockNumber>;
+		/// Amplification coefficient of StableSwap pools, pools without an entry are constant product.
+		StableSwapAmplification get(fn amplification_ramp): map hasher(twox_64_concat) (T::CurrencyId,T::CurrencyId) => Option<AmplificationRamp<T::BlockNumber>>;
+		/// Maximum price impact allowed for a single hop of any swap, unless overridden per pool.
+		MaxPriceImpact get(fn max_price_impact): Option<Permill>;
+		/// Maximum price impact allowed for a single hop through a specific pool.
+		PoolMaxPriceImpact get(fn pool_max_price_impact): map hasher(twox_64_concat) (T::CurrencyId,T::CurrencyId) => Option<Permill>;
+		/// Identifier of the next weighted pool.
+		NextWeightedPoolId get(fn next_weighted_pool_id): WeightedPoolId;
+		/// Balancer style weighted pools.
+		WeightedPools get(fn weighted_pool): map hasher(twox_64_concat) WeightedPoolId => Option<WeightedPool<T::CurrencyId, T::Balance>>;
+		/// LP shares of weighted pools.
+		WeightedPoolHoldings get(fn weighted_pool_holdings): map hasher(blake2_128_concat) (T::AccountId, WeightedPoolId) => T::Balance;
+		/// Identifier of the next concentrated liquidity pool.
+		NextConcentratedPoolId get(fn next_concentrated_pool_id): ConcentratedPoolId;
+		/// Uniswap v3 style concentrated liquidity pools.
+		ConcentratedPools get(fn concentrated_pool): map hasher(twox_64_concat) ConcentratedPoolId => Option<ConcentratedPool<T::CurrencyId>>;
+		/// Ticks of concentrated liquidity pools used as a bound by at least one position.
+		Ticks get(fn tick): double_map hasher(twox_64_concat) ConcentratedPoolId, hasher(twox_64_concat) i32 => Option<TickInfo>;
+		/// Sorted initialized ticks of each concentrated liquidity pool.
+		InitializedTicks get(fn initialized_ticks): map hasher(twox_64_concat) ConcentratedPoolId => Vec<i32>;
+		/// Identifier of the next concentrated liquidity position.
+		NextPositionId get(fn next_position_id): PositionId;
+		/// Concentrated liquidity positions.
+		Positions get(fn position): map hasher(twox_64_concat) PositionId => Option<Position<T::AccountId, ConcentratedPoolId>>;
+		/// Fraction of the swap fees minted as LP shares to `ProtocolFeeAccount`, `None` when switched off.
+		ProtocolFee get(fn protocol_fee): Option<Permill>;
+		/// Square root of `pool_0 * pool_1` (`D` for StableSwap pools) after the last liquidity event,
+		/// only tracked while the protocol fee is on.
+		RootKLast get(fn root_k_last): map hasher(twox_64_concat) (T::CurrencyId,T::CurrencyId) => U256;
+		/// Amplification coefficient `RootKLast` of StableSwap pools was computed with, so that
+		/// amplification ramps are not mistaken for fee growth.
+		RootKLastAmplification get(fn root_k_last_amplification): map hasher(twox_64_concat) (T::CurrencyId,T::CurrencyId) => Option<u128>;
+		/// LP shares minted to `ProtocolFeeAccount` per pool since the protocol fee was first switched on.
+		AccruedProtocolFee get(fn accrued_protocol_fee): map hasher(twox_64_concat) (T::CurrencyId,T::CurrencyId) => T::Balance;
+		/// Pools with a flash swap in progress, which cannot be traded against until it is repaid.
+		FlashSwapLocks get(fn flash_swap_locked): map hasher(twox_64_concat) (T::CurrencyId,T::CurrencyId) => bool;
+		/// Ring buffer of snapshots of `PriceAccumulators`, taken at the first swap or liquidity
+		/// event of a block.
+		/// (AssetID, AssetID) -> Index % TwapObservationPeriod -> PriceAccumulator
+		PriceObservations get(fn price_observation): double_map hasher(twox_64_concat) (T::CurrencyId,T::CurrencyId), hasher(twox_64_concat) u32 => Option<PriceAccumulator<T::BlockNumber>>;
+		/// Index of the newest entry of `PriceObservations` of each pool.
+		LatestPriceObservation get(fn latest_price_observation): map hasher(twox_64_concat) (T::CurrencyId,T::CurrencyId) => u32;
 	}
 }
 
 decl_event!(
 	pub enum Event<T> where
 		<T as frame_system::Config>::AccountId,
-		AssetId = <T as frame_system::Config>::Hash,
-		Balance = <T as Config>::Balance
+		AssetId = <T as Config>::CurrencyId,
+		Balance = <T as Config>::Balance,
+		BlockNumber = <T as frame_system::Config>::BlockNumber,
+		PoolId = WeightedPoolId,
+		PositionId = PositionId
 	{
 		/// Add liquidity success. \[who, currency_id_0, pool_0_increment, currency_id_1, pool_1_increment, share_increment\]
 		AddLiquidity(AccountId, AssetId, Balance, AssetId, Balance, Balance),
@@ -60,6 +160,48 @@ decl_event!(
 		RemoveLiquidity(AccountId, AssetId, Balance, AssetId, Balance, Balance),
 		/// Use supply currency to swap target currency. \[trader, trading_path, supply_currency_amount, target_currency_amount\]
 		Swap(AccountId, Vec<AssetId>, Balance, Balance),
+		/// Swap through a single pool, one per hop of a `Swap`. \[supply_currency_id, supply_amount, target_currency_id, target_amount, fee\]
+		PoolSwap(AssetId, Balance, AssetId, Balance, Balance),
+		/// Global maximum price impact updated. \[max_price_impact\]
+		MaxPriceImpactUpdated(Option<Permill>),
+		/// Maximum price impact of a pool updated. \[currency_id_0, currency_id_1, max_price_impact\]
+		PoolMaxPriceImpactUpdated(AssetId, AssetId, Option<Permill>),
+		/// Amplification ramp of a StableSwap pool started. \[currency_id_0, currency_id_1, initial_amplification, future_amplification, future_block\]
+		AmplificationRampStarted(AssetId, AssetId, u32, u32, BlockNumber),
+		/// Amplification ramp of a StableSwap pool stopped. \[currency_id_0, currency_id_1, amplification\]
+		AmplificationRampStopped(AssetId, AssetId, u32),
+		/// Weighted pool created. \[who, pool_id, assets\]
+		WeightedPoolCreated(AccountId, PoolId, Vec<AssetId>),
+		/// Liquidity added to a weighted pool. \[who, pool_id, amounts, share_increment\]
+		WeightedPoolJoined(AccountId, PoolId, Vec<Balance>, Balance),
+		/// Liquidity removed from a weighted pool. \[who, pool_id, amounts, share_decrement\]
+		WeightedPoolExited(AccountId, PoolId, Vec<Balance>, Balance),
+		/// Swap within a weighted pool. \[who, pool_id, supply_currency_id, supply_amount, target_currency_id, target_amount\]
+		WeightedPoolSwap(AccountId, PoolId, AssetId, Balance, AssetId, Balance),
+		/// Protocol fee updated. \[protocol_fee\]
+		ProtocolFeeUpdated(Option<Permill>),
+		/// Protocol fee minted as LP shares. \[currency_id_0, currency_id_1, share_increment\]
+		ProtocolFeeMinted(AssetId, AssetId, Balance),
+		/// LP shares of `ProtocolFeeAccount` redeemed. \[beneficiary, currency_id_0, amount_0, currency_id_1, amount_1, share_decrement\]
+		ProtocolFeeWithdrawn(AccountId, AssetId, Balance, AssetId, Balance, Balance),
+		/// Concentrated liquidity pool created. \[who, pool_id, currency_id_0, currency_id_1, fee, tick_spacing\]
+		ConcentratedPoolCreated(AccountId, PoolId, AssetId, AssetId, Permill, u32),
+		/// Concentrated liquidity position opened. \[who, position_id, pool_id, tick_lower, tick_upper, liquidity, amount_0, amount_1\]
+		PositionMinted(AccountId, PositionId, PoolId, i32, i32, u128, Balance, Balance),
+		/// Liquidity removed from a concentrated liquidity position. \[who, position_id, liquidity, amount_0, amount_1\]
+		PositionBurned(AccountId, PositionId, u128, Balance, Balance),
+		/// Fees of a concentrated liquidity position paid out. \[who, position_id, fee_0, fee_1\]
+		PositionFeesCollected(AccountId, PositionId, Balance, Balance),
+		/// Swap within a concentrated liquidity pool. \[who, pool_id, supply_currency_id, supply_amount, target_currency_id, target_amount\]
+		ConcentratedPoolSwap(AccountId, PoolId, AssetId, Balance, AssetId, Balance),
+		/// LP shares of a pool transferred. \[from, to, currency_id_0, currency_id_1, share_amount\]
+		LpSharesTransferred(AccountId, AccountId, AssetId, AssetId, Balance),
+		/// LP shares of a weighted pool transferred. \[from, to, pool_id, share_amount\]
+		WeightedPoolSharesTransferred(AccountId, AccountId, PoolId, Balance),
+		/// Concentrated liquidity position transferred. \[from, to, position_id\]
+		PositionTransferred(AccountId, AccountId, PositionId),
+		/// Flash swap repaid. \[who, currency_id_0, amount_0_out, amount_0_in, currency_id_1, amount_1_out, amount_1_in\]
+		FlashSwap(AccountId, AssetId, Balance, Balance, AssetId, Balance, Balance),
 	}
 );
 
@@ -94,6 +236,54 @@ decl_error! {
 		InsufficientBalance,
 		///LowShare
 		LowShare,
+		/// No price observation is available for the requested TWAP window
+		TwapWindowUnavailable,
+		/// The trading path visits the same asset more than once
+		InvalidTradingPath,
+		/// The trading pair is already registered
+		TradingPairAlreadyRegistered,
+		/// The pool is not a StableSwap pool
+		NotStableSwapPool,
+		/// The amplification coefficient is zero or above `MaxAmplification`
+		InvalidAmplification,
+		/// The amplification ramp is too fast or too short
+		InvalidAmplificationRamp,
+		/// Assets, weights, amounts or fee of a weighted pool are invalid
+		InvalidWeightedPool,
+		/// The weighted pool does not exist
+		WeightedPoolNotFound,
+		/// The asset is not a member of the weighted pool
+		AssetNotInPool,
+		/// The amount supplied is too large compared to the pool balance
+		ExceedMaxInRatio,
+		/// The amount requested is too large compared to the pool balance
+		ExceedMaxOutRatio,
+		/// Shares received are less than the minimum requested
+		InsufficientShareIncrement,
+		/// The pool has a flash swap in progress
+		PoolLocked,
+		/// The flash swap callback did not pay back enough to keep the pool invariant
+		FlashSwapNotRepaid,
+		/// The operation is only supported by constant product pools
+		NotConstantProductPool,
+		/// The amount withdrawn is less than the minimum requested
+		InsufficientWithdrawnAmount,
+		/// The deadline of the transaction has passed
+		DeadlineExpired,
+		/// The concentrated liquidity pool does not exist
+		ConcentratedPoolNotFound,
+		/// Fee or tick spacing of a concentrated liquidity pool are invalid
+		InvalidConcentratedPool,
+		/// The square root price is out of range or on the wrong side of the current price
+		InvalidSqrtPrice,
+		/// The ticks are out of range, unordered or not multiples of the tick spacing
+		InvalidTickRange,
+		/// The pool already has `MaxTicksPerPool` initialized ticks
+		TooManyTicks,
+		/// The position does not exist
+		PositionNotFound,
+		/// The position belongs to another account
+		NotPositionOwner,
 	}
 }
 
@@ -108,6 +298,21 @@ decl_module! {
         /// The limit for length of trading path
 		const TradingPathLimit: u32 = T::TradingPathLimit::get() as u32;
 
+		/// Number of price observations kept per pool
+		const TwapObservationPeriod: u32 = T::TwapObservationPeriod::get();
+
+		/// Maximum amplification coefficient of StableSwap pools
+		const MaxAmplification: u32 = T::MaxAmplification::get();
+
+		/// Minimum duration of an amplification ramp
+		const MinAmplificationRampDuration: T::BlockNumber = T::MinAmplificationRampDuration::get();
+
+		/// Maximum number of assets in a weighted pool
+		const MaxWeightedPoolAssets: u32 = T::MaxWeightedPoolAssets::get();
+
+		/// Maximum number of initialized ticks in a concentrated liquidity pool
+		const MaxTicksPerPool: u32 = T::MaxTicksPerPool::get();
+
         /// This method registers new Swap Pair and insert liquidity.
         /// # Arguments
         ///
@@ -121,15 +326,17 @@ decl_module! {
         ///
         /// * `currency_id_b_amount` - Balance provided by Trader for Base Asset.
         ///
+        /// * `pool_kind` - Pricing curve of the pool, constant product or StableSwap.
+        ///
         /// # Return
         ///
         ///  This function returns a status that, new Swap Pair is successfully registered or not.
 
         #[weight=10000]
-        pub fn register_swap_pair(origin, currency_id_a: T::Hash, currency_id_b: T::Hash, currency_id_a_amount: T::Balance,
-                                    currency_id_b_amount: T::Balance) -> dispatch::DispatchResult{
+        pub fn register_swap_pair(origin, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, currency_id_a_amount: T::Balance,
+                                    currency_id_b_amount: T::Balance, pool_kind: PoolKind) -> dispatch::DispatchResult{
              let who = ensure_signed(origin)?;
-             Self::do_register_swap_pair(&who,currency_id_a,currency_id_b,currency_id_a_amount,currency_id_b_amount)?;
+             Self::do_register_swap_pair(&who,currency_id_a,currency_id_b,currency_id_a_amount,currency_id_b_amount,pool_kind)?;
              Ok(())
         }
 
@@ -144,14 +351,17 @@ decl_module! {
         ///
         /// * `min_target_amount` - Acceptable minimum target amount.
         ///
+        /// * `max_price_impact` - Acceptable price impact of every hop against the pool's spot price.
+        ///
         /// # Return
         ///
         ///  This function returns a status that, new Swap successfully happened or not.
 
 		#[weight = 10000]
-		pub fn swap_with_exact_supply(origin, path: Vec<T::Hash>, #[compact] supply_amount: T::Balance, #[compact] min_target_amount: T::Balance) -> dispatch::DispatchResult{
+		pub fn swap_with_exact_supply(origin, path: Vec<T::CurrencyId>, #[compact] supply_amount: T::Balance, #[compact] min_target_amount: T::Balance,
+		                            max_price_impact: Option<Permill>) -> dispatch::DispatchResult{
 				let who = ensure_signed(origin)?;
-				Self::do_swap_with_exact_supply(&who, &path, supply_amount, min_target_amount,None)?;
+				Self::do_swap_with_exact_supply(&who, &path, supply_amount, min_target_amount, max_price_impact)?;
 				Ok(())
 		}
 
@@ -166,13 +376,16 @@ decl_module! {
         ///
         /// * `max_supply_amount` - Acceptable maximum supply amount.
         ///
+        /// * `max_price_impact` - Acceptable price impact of every hop against the pool's spot price.
+        ///
         /// # Return
         ///
         ///  This function returns a status that, new Swap successfully happened or not.
 		#[weight = 10000]
-		pub fn swap_with_exact_target(origin, path: Vec<T::Hash>, #[compact] target_amount: T::Balance, #[compact] max_supply_amount: T::Balance) -> dispatch::DispatchResult{
+		pub fn swap_with_exact_target(origin, path: Vec<T::CurrencyId>, #[compact] target_amount: T::Balance, #[compact] max_supply_amount: T::Balance,
+		                            max_price_impact: Option<Permill>) -> dispatch::DispatchResult{
 				let who = ensure_signed(origin)?;
-				Self::do_swap_with_exact_target(&who, &path, target_amount, max_supply_amount,None)?;
+				Self::do_swap_with_exact_target(&who, &path, target_amount, max_supply_amount, max_price_impact)?;
 				Ok(())
 		}
 
@@ -189,15 +402,21 @@ decl_module! {
         ///
         /// * `max_amount_b` - Maximum Counter Base Id's amount allowed to inject to liquidity pool.
         ///
+        /// * `min_share` - Minimum LP shares to receive.
+        ///
+        /// * `deadline` - Last block in which the transaction can be included.
+        ///
         /// # Return
         ///
         ///  This function returns a status that, Liquidity is successfully inserted or not.
 
 		#[weight = 10000]
-		pub fn add_liquidity(origin, currency_id_a: T::Hash, currency_id_b: T::Hash,
-		                    #[compact] max_amount_a: T::Balance, #[compact] max_amount_b: T::Balance) -> dispatch::DispatchResult {
+		pub fn add_liquidity(origin, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId,
+		                    #[compact] max_amount_a: T::Balance, #[compact] max_amount_b: T::Balance,
+		                    #[compact] min_share: T::Balance, deadline: Option<T::BlockNumber>) -> dispatch::DispatchResult {
 			let who = ensure_signed(origin)?;
-			Self::do_add_liquidity(&who, currency_id_a, currency_id_b, max_amount_a, max_amount_b)?;
+			Self::ensure_deadline(deadline)?;
+			Self::do_add_liquidity(&who, currency_id_a, currency_id_b, max_amount_a, max_amount_b, min_share)?;
 			Ok(())
 		}
 
@@ -211,14 +430,470 @@ decl_module! {
         ///
         /// * `remove_share` - Liquidity amount to remove.
         ///
+        /// * `min_amount_a` - Minimum Counter Asset amount to withdraw.
+        ///
+        /// * `min_amount_b` - Minimum Base Asset amount to withdraw.
+        ///
+        /// * `deadline` - Last block in which the transaction can be included.
+        ///
         /// # Return
         ///
         ///  This function returns a status that, Liquidity is successfully removed or not.
 
 		#[weight = 10000]
-		pub fn remove_liquidity(origin, currency_id_a: T::Hash, currency_id_b: T::Hash, #[compact] remove_share: T::Balance) -> dispatch::DispatchResult {
+		pub fn remove_liquidity(origin, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, #[compact] remove_share: T::Balance,
+		                        #[compact] min_amount_a: T::Balance, #[compact] min_amount_b: T::Balance, deadline: Option<T::BlockNumber>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_deadline(deadline)?;
+			Self::do_remove_liquidity(&who, currency_id_a, currency_id_b, remove_share, min_amount_a, min_amount_b)?;
+			Ok(())
+		}
+
+		/// Adds liquidity with a single asset, swapping the part of it needed to match the pool
+		/// ratio first. Only supported by constant product pools.
+        /// # Arguments
+        ///
+        /// * `origin` - This contains the detail of Origin from where Transaction originated.
+        ///
+        /// * `supply_currency_id` - Asset deposited.
+        ///
+        /// * `other_currency_id` - Other asset of the pool.
+        ///
+        /// * `supply_amount` - Amount deposited.
+        ///
+        /// * `min_share` - Minimum LP shares to receive.
+        ///
+        /// * `deadline` - Last block in which the transaction can be included.
+		#[weight = 10000]
+		pub fn add_liquidity_single_asset(origin, supply_currency_id: T::CurrencyId, other_currency_id: T::CurrencyId,
+		                                  #[compact] supply_amount: T::Balance, #[compact] min_share: T::Balance,
+		                                  deadline: Option<T::BlockNumber>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_deadline(deadline)?;
+			Self::do_add_liquidity_single_asset(&who, supply_currency_id, other_currency_id, supply_amount, min_share)?;
+			Ok(())
+		}
+
+		/// Removes liquidity and swaps the withdrawn `other_currency_id` into `target_currency_id`.
+        /// # Arguments
+        ///
+        /// * `target_currency_id` - Asset received.
+        ///
+        /// * `other_currency_id` - Other asset of the pool.
+        ///
+        /// * `remove_share` - LP shares to burn.
+        ///
+        /// * `min_target_amount` - Minimum amount of `target_currency_id` to receive.
+        ///
+        /// * `deadline` - Last block in which the transaction can be included.
+		#[weight = 10000]
+		pub fn remove_liquidity_single_asset(origin, target_currency_id: T::CurrencyId, other_currency_id: T::CurrencyId,
+		                                     #[compact] remove_share: T::Balance, #[compact] min_target_amount: T::Balance,
+		                                     deadline: Option<T::BlockNumber>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_deadline(deadline)?;
+			Self::do_remove_liquidity_single_asset(&who, target_currency_id, other_currency_id, remove_share, min_target_amount)?;
+			Ok(())
+		}
+
+		/// This method swaps along a route found off-chain by the Polkapool RPC router.
+        /// # Arguments
+        ///
+        /// * `origin` - This contains the detail of Origin from where Transaction originated.
+        ///
+        /// * `path` - Trading Path returned by the router.
+        ///
+        /// * `supply_amount` - Provided amount to Swap.
+        ///
+        /// * `min_target_amount` - Acceptable minimum target amount.
+        ///
+        /// * `max_price_impact` - Acceptable price impact of every hop against the pool's spot price.
+        ///
+        /// # Return
+        ///
+        ///  This function returns a status that, new Swap successfully happened or not.
+		#[weight = 10000]
+		pub fn swap_with_best_route(origin, path: Vec<T::CurrencyId>, #[compact] supply_amount: T::Balance, #[compact] min_target_amount: T::Balance,
+		                            max_price_impact: Option<Permill>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_valid_route(&path)?;
+			Self::do_swap_with_exact_supply(&who, &path, supply_amount, min_target_amount, max_price_impact)?;
+			Ok(())
+		}
+
+		/// Sets the maximum price impact allowed for a single hop of any swap.
+		/// `None` removes the limit.
+		#[weight = 10000]
+		pub fn set_max_price_impact(origin, max_price_impact: Option<Permill>) -> dispatch::DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			MaxPriceImpact::set(max_price_impact);
+			Self::deposit_event(RawEvent::MaxPriceImpactUpdated(max_price_impact));
+			Ok(())
+		}
+
+		/// Sets the fraction of swap fees minted to `ProtocolFeeAccount` on liquidity events.
+		/// `None` switches the protocol fee off.
+		#[weight = 10000]
+		pub fn set_protocol_fee(origin, protocol_fee: Option<Permill>) -> dispatch::DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			ProtocolFee::set(protocol_fee);
+			Self::deposit_event(RawEvent::ProtocolFeeUpdated(protocol_fee));
+			Ok(())
+		}
+
+		/// Redeems LP shares minted to `ProtocolFeeAccount` and pays the assets withdrawn to
+		/// `beneficiary`. The shares are only held in `LiquidityPoolHoldings`, out of reach of
+		/// the account owner, so this is the only way to spend them.
+        /// # Arguments
+        ///
+        /// * `origin` - Update origin, usually governance.
+        ///
+        /// * `currency_id_a` - Currency Id of Counter Asset.
+        ///
+        /// * `currency_id_b` - Currency Id of Base Asset.
+        ///
+        /// * `remove_share` - LP shares of `ProtocolFeeAccount` to burn.
+        ///
+        /// * `beneficiary` - Account receiving the assets withdrawn.
+		#[weight = 10000]
+		pub fn withdraw_protocol_fee(origin, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, #[compact] remove_share: T::Balance,
+		                             beneficiary: T::AccountId) -> dispatch::DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			Self::do_withdraw_protocol_fee(currency_id_a, currency_id_b, remove_share, &beneficiary)?;
+			Ok(())
+		}
+
+		/// Sets the maximum price impact allowed for a single hop through the pool of
+		/// `currency_id_a` and `currency_id_b`, overriding the global maximum.
+		/// `None` falls back to the global maximum.
+		#[weight = 10000]
+		pub fn set_pool_max_price_impact(origin, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, max_price_impact: Option<Permill>) -> dispatch::DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+			ensure!(LiquidityPool::<T>::contains_key(trading_pair), Error::<T>::TradingPairNotAllowed);
+			PoolMaxPriceImpact::<T>::set(trading_pair, max_price_impact);
+			Self::deposit_event(RawEvent::PoolMaxPriceImpactUpdated(trading_pair.0, trading_pair.1, max_price_impact));
+			Ok(())
+		}
+
+		/// Linearly ramps the amplification coefficient of a StableSwap pool from its current
+		/// value to `future_amplification` at block `future_block`.
+		#[weight = 10000]
+		pub fn ramp_amplification(origin, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, future_amplification: u32, future_block: T::BlockNumber) -> dispatch::DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+			let ramp = Self::amplification_ramp(trading_pair).ok_or(Error::<T>::NotStableSwapPool)?;
+			ensure!(future_amplification > 0 && future_amplification <= T::MaxAmplification::get(), Error::<T>::InvalidAmplification);
+
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(future_block >= now.saturating_add(T::MinAmplificationRampDuration::get()), Error::<T>::InvalidAmplificationRamp);
+			let initial_amplification = ramp.amplification_at(now);
+			ensure!(
+				future_amplification <= initial_amplification.saturating_mul(MAX_AMPLIFICATION_CHANGE) &&
+				initial_amplification <= future_amplification.saturating_mul(MAX_AMPLIFICATION_CHANGE),
+				Error::<T>::InvalidAmplificationRamp
+			);
+
+			StableSwapAmplification::<T>::insert(trading_pair, AmplificationRamp {
+				initial_amplification,
+				future_amplification,
+				initial_block: now,
+				future_block,
+			});
+			Self::deposit_event(RawEvent::AmplificationRampStarted(trading_pair.0, trading_pair.1, initial_amplification, future_amplification, future_block));
+			Ok(())
+		}
+
+		/// Freezes the amplification coefficient of a StableSwap pool at its current value.
+		#[weight = 10000]
+		pub fn stop_amplification_ramp(origin, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId) -> dispatch::DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+			let ramp = Self::amplification_ramp(trading_pair).ok_or(Error::<T>::NotStableSwapPool)?;
+			let now = <frame_system::Module<T>>::block_number();
+			let amplification = ramp.amplification_at(now);
+			StableSwapAmplification::<T>::insert(trading_pair, AmplificationRamp::fixed(amplification, now));
+			Self::deposit_event(RawEvent::AmplificationRampStopped(trading_pair.0, trading_pair.1, amplification));
+			Ok(())
+		}
+
+		/// Creates a weighted pool and provides its initial liquidity.
+        /// # Arguments
+        ///
+        /// * `origin` - This contains the detail of Origin from where Transaction originated.
+        ///
+        /// * `assets` - Member assets of the pool.
+        ///
+        /// * `weights` - Weight of each asset, must sum to 100%.
+        ///
+        /// * `amounts` - Initial balance of each asset.
+        ///
+        /// * `swap_fee` - Fee charged on swaps through the pool.
+		#[weight = 10000]
+		pub fn create_weighted_pool(origin, assets: Vec<T::CurrencyId>, weights: Vec<Permill>, amounts: Vec<T::Balance>, swap_fee: Permill) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_create_weighted_pool(&who, assets, weights, amounts, swap_fee)?;
+			Ok(())
+		}
+
+		/// Adds liquidity in all assets of a weighted pool, proportionally to its balances.
+        /// # Arguments
+        ///
+        /// * `pool_id` - Weighted pool.
+        ///
+        /// * `share_amount` - LP shares to receive.
+        ///
+        /// * `max_amounts_in` - Maximum amount of each asset to deposit.
+		#[weight = 10000]
+		pub fn join_weighted_pool(origin, pool_id: WeightedPoolId, #[compact] share_amount: T::Balance, max_amounts_in: Vec<T::Balance>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_join_weighted_pool(&who, pool_id, share_amount, max_amounts_in)?;
+			Ok(())
+		}
+
+		/// Removes liquidity in all assets of a weighted pool, proportionally to its balances.
+        /// # Arguments
+        ///
+        /// * `pool_id` - Weighted pool.
+        ///
+        /// * `share_amount` - LP shares to burn.
+        ///
+        /// * `min_amounts_out` - Minimum amount of each asset to withdraw.
+		#[weight = 10000]
+		pub fn exit_weighted_pool(origin, pool_id: WeightedPoolId, #[compact] share_amount: T::Balance, min_amounts_out: Vec<T::Balance>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_exit_weighted_pool(&who, pool_id, share_amount, min_amounts_out)?;
+			Ok(())
+		}
+
+		/// Adds liquidity to a weighted pool in a single asset.
+        /// # Arguments
+        ///
+        /// * `pool_id` - Weighted pool.
+        ///
+        /// * `currency_id` - Asset deposited.
+        ///
+        /// * `amount_in` - Amount deposited.
+        ///
+        /// * `min_share` - Minimum LP shares to receive.
+		#[weight = 10000]
+		pub fn join_weighted_pool_single_asset(origin, pool_id: WeightedPoolId, currency_id: T::CurrencyId, #[compact] amount_in: T::Balance, #[compact] min_share: T::Balance) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_join_weighted_pool_single_asset(&who, pool_id, currency_id, amount_in, min_share)?;
+			Ok(())
+		}
+
+		/// Removes liquidity from a weighted pool in a single asset.
+        /// # Arguments
+        ///
+        /// * `pool_id` - Weighted pool.
+        ///
+        /// * `currency_id` - Asset withdrawn.
+        ///
+        /// * `share_amount` - LP shares to burn.
+        ///
+        /// * `min_amount_out` - Minimum amount to withdraw.
+		#[weight = 10000]
+		pub fn exit_weighted_pool_single_asset(origin, pool_id: WeightedPoolId, currency_id: T::CurrencyId, #[compact] share_amount: T::Balance, #[compact] min_amount_out: T::Balance) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_exit_weighted_pool_single_asset(&who, pool_id, currency_id, share_amount, min_amount_out)?;
+			Ok(())
+		}
+
+		/// Lends pool assets to the caller and dispatches `call` on their behalf, then takes the
+		/// amounts paid back from the caller. They must be enough for the invariant to hold after
+		/// charging the swap fee on them, otherwise the whole extrinsic is reverted.
+        /// # Arguments
+        ///
+        /// * `origin` - This contains the detail of Origin from where Transaction originated.
+        ///
+        /// * `currency_id_a` - Currency Id of Counter Asset.
+        ///
+        /// * `currency_id_b` - Currency Id of Base Asset.
+        ///
+        /// * `amount_a_out` - Amount of Counter Asset lent.
+        ///
+        /// * `amount_b_out` - Amount of Base Asset lent.
+        ///
+        /// * `amount_a_in` - Amount of Counter Asset paid back once `call` returns.
+        ///
+        /// * `amount_b_in` - Amount of Base Asset paid back once `call` returns.
+        ///
+        /// * `call` - Callback dispatched with the origin of the caller.
+		#[weight = 10000u64.saturating_add(call.get_dispatch_info().weight)]
+		pub fn flash_swap(origin, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, #[compact] amount_a_out: T::Balance,
+		                  #[compact] amount_b_out: T::Balance, #[compact] amount_a_in: T::Balance, #[compact] amount_b_in: T::Balance,
+		                  call: Box<<T as Config>::Call>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_flash_swap(&who, currency_id_a, currency_id_b, amount_a_out, amount_b_out, amount_a_in, amount_b_in, |borrower| {
+				call.dispatch(frame_system::RawOrigin::Signed(borrower.clone()).into())
+					.map(|_| ())
+					.map_err(|e| e.error)
+			})
+		}
+
+		/// Swaps exactly `supply_amount` between two members of a weighted pool.
+        /// # Arguments
+        ///
+        /// * `pool_id` - Weighted pool.
+        ///
+        /// * `supply_currency_id` - Asset sold.
+        ///
+        /// * `target_currency_id` - Asset bought.
+        ///
+        /// * `supply_amount` - Amount sold.
+        ///
+        /// * `min_target_amount` - Acceptable minimum amount bought.
+		#[weight = 10000]
+		pub fn swap_weighted_pool(origin, pool_id: WeightedPoolId, supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId,
+		                          #[compact] supply_amount: T::Balance, #[compact] min_target_amount: T::Balance) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_swap_weighted_pool(&who, pool_id, supply_currency_id, target_currency_id, supply_amount, min_target_amount)?;
+			Ok(())
+		}
+
+		/// Transfers LP shares of the pool of `currency_id_a` and `currency_id_b` to another account.
+        /// # Arguments
+        ///
+        /// * `origin` - This contains the detail of Origin from where Transaction originated.
+        ///
+        /// * `dest` - Receiver of the shares.
+        ///
+        /// * `currency_id_a` - Currency Id of Counter Asset.
+        ///
+        /// * `currency_id_b` - Currency Id of Base Asset.
+        ///
+        /// * `share_amount` - LP shares transferred.
+		#[weight = 10000]
+		pub fn transfer_lp_shares(origin, dest: <T::Lookup as StaticLookup>::Source, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId,
+		                          #[compact] share_amount: T::Balance) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			let dest = T::Lookup::lookup(dest)?;
+			Self::do_transfer_lp_shares(&who, &dest, currency_id_a, currency_id_b, share_amount)?;
+			Ok(())
+		}
+
+		/// Transfers LP shares of a weighted pool to another account.
+        /// # Arguments
+        ///
+        /// * `dest` - Receiver of the shares.
+        ///
+        /// * `pool_id` - Weighted pool.
+        ///
+        /// * `share_amount` - LP shares transferred.
+		#[weight = 10000]
+		pub fn transfer_weighted_pool_shares(origin, dest: <T::Lookup as StaticLookup>::Source, pool_id: WeightedPoolId,
+		                                     #[compact] share_amount: T::Balance) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			let dest = T::Lookup::lookup(dest)?;
+			Self::do_transfer_weighted_pool_shares(&who, &dest, pool_id, share_amount)?;
+			Ok(())
+		}
+
+		/// Transfers a concentrated liquidity position, including its unpaid fees, to another account.
+        /// # Arguments
+        ///
+        /// * `dest` - New owner of the position.
+        ///
+        /// * `position_id` - Position owned by the caller.
+		#[weight = 10000]
+		pub fn transfer_position(origin, dest: <T::Lookup as StaticLookup>::Source, position_id: PositionId) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			let dest = T::Lookup::lookup(dest)?;
+			Self::do_transfer_position(&who, &dest, position_id)?;
+			Ok(())
+		}
+
+		/// Creates a concentrated liquidity pool without liquidity.
+        /// # Arguments
+        ///
+        /// * `origin` - This contains the detail of Origin from where Transaction originated.
+        ///
+        /// * `currency_id_a` - Currency Id of Counter Asset.
+        ///
+        /// * `currency_id_b` - Currency Id of Base Asset.
+        ///
+        /// * `fee` - Fee charged on swaps, earned by the liquidity in range.
+        ///
+        /// * `tick_spacing` - Bounds of positions must be multiples of it.
+        ///
+        /// * `sqrt_price` - Initial square root of the price of Counter Asset in Base Asset, in Q64.64.
+		#[weight = 10000]
+		pub fn create_concentrated_pool(origin, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, fee: Permill, tick_spacing: u32, sqrt_price: u128) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_create_concentrated_pool(&who, currency_id_a, currency_id_b, fee, tick_spacing, sqrt_price)?;
+			Ok(())
+		}
+
+		/// Provides liquidity to a concentrated liquidity pool in a price range.
+        /// # Arguments
+        ///
+        /// * `pool_id` - Concentrated liquidity pool.
+        ///
+        /// * `tick_lower` - Lower bound of the range.
+        ///
+        /// * `tick_upper` - Upper bound of the range.
+        ///
+        /// * `liquidity` - Liquidity provided.
+        ///
+        /// * `max_amount_0` - Maximum amount of the first asset of the pool to deposit.
+        ///
+        /// * `max_amount_1` - Maximum amount of the second asset of the pool to deposit.
+		#[weight = 10000]
+		pub fn mint_position(origin, pool_id: ConcentratedPoolId, tick_lower: i32, tick_upper: i32, #[compact] liquidity: u128,
+		                     #[compact] max_amount_0: T::Balance, #[compact] max_amount_1: T::Balance) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_mint_position(&who, pool_id, tick_lower, tick_upper, liquidity, max_amount_0, max_amount_1)?;
+			Ok(())
+		}
+
+		/// Removes liquidity from a position and pays out the fees it earned.
+        /// # Arguments
+        ///
+        /// * `position_id` - Position owned by the caller.
+        ///
+        /// * `liquidity` - Liquidity removed.
+        ///
+        /// * `min_amount_0` - Minimum amount of the first asset of the pool to withdraw, excluding fees.
+        ///
+        /// * `min_amount_1` - Minimum amount of the second asset of the pool to withdraw, excluding fees.
+		#[weight = 10000]
+		pub fn burn_position(origin, position_id: PositionId, #[compact] liquidity: u128,
+		                     #[compact] min_amount_0: T::Balance, #[compact] min_amount_1: T::Balance) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_burn_position(&who, position_id, liquidity, min_amount_0, min_amount_1)?;
+			Ok(())
+		}
+
+		/// Pays out the fees earned by a position.
+        /// # Arguments
+        ///
+        /// * `position_id` - Position owned by the caller.
+		#[weight = 10000]
+		pub fn collect_position_fees(origin, position_id: PositionId) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_collect_position_fees(&who, position_id)?;
+			Ok(())
+		}
+
+		/// Swaps exactly `supply_amount` in a concentrated liquidity pool, or less if the price
+		/// reaches `sqrt_price_limit`.
+        /// # Arguments
+        ///
+        /// * `pool_id` - Concentrated liquidity pool.
+        ///
+        /// * `supply_currency_id` - Asset sold.
+        ///
+        /// * `supply_amount` - Amount sold.
+        ///
+        /// * `min_target_amount` - Acceptable minimum amount bought.
+        ///
+        /// * `sqrt_price_limit` - Square root price at which the swap stops, in Q64.64.
+		#[weight = 10000]
+		pub fn swap_concentrated_pool(origin, pool_id: ConcentratedPoolId, supply_currency_id: T::CurrencyId, #[compact] supply_amount: T::Balance,
+		                              #[compact] min_target_amount: T::Balance, sqrt_price_limit: Option<u128>) -> dispatch::DispatchResult {
 			let who = ensure_signed(origin)?;
-			Self::do_remove_liquidity(&who, currency_id_a, currency_id_b, remove_share)?;
+			Self::do_swap_concentrated_pool(&who, pool_id, supply_currency_id, supply_amount, min_target_amount, sqrt_price_limit)?;
 			Ok(())
 		}
 	}
@@ -232,22 +907,38 @@ impl<T: Config> Module<T> {
     }
 
     /// Registers new Swap Pair and insert liquidity.
-    pub fn do_register_swap_pair(who: &T::AccountId, currency_id_a: T::Hash, currency_id_b: T::Hash, currency_id_a_amount: T::Balance, currency_id_b_amount: T::Balance) -> DispatchResult {
+    #[transactional]
+    pub fn do_register_swap_pair(who: &T::AccountId, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, currency_id_a_amount: T::Balance, currency_id_b_amount: T::Balance, pool_kind: PoolKind) -> DispatchResult {
+        ensure!(currency_id_a != currency_id_b, Error::<T>::InvalidCurrencyId);
+        let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+        ensure!(!LiquidityPool::<T>::contains_key(trading_pair), Error::<T>::TradingPairAlreadyRegistered);
+        if let PoolKind::StableSwap { amplification } = pool_kind {
+            ensure!(amplification > 0 && amplification <= T::MaxAmplification::get(), Error::<T>::InvalidAmplification);
+        }
 
+        // Set first so that `RootKLast` of StableSwap pools is `D` from the start
+        if let PoolKind::StableSwap { amplification } = pool_kind {
+            let now = <frame_system::Module<T>>::block_number();
+            StableSwapAmplification::<T>::insert(trading_pair, AmplificationRamp::fixed(amplification, now));
+        }
+
+        Self::do_add_liquidity(who, currency_id_a, currency_id_b, currency_id_a_amount, currency_id_b_amount, Zero::zero())?;
+        Ok(())
     }
 
     /// Swaps supply amount for amount less then Minimum target amount.
-    pub fn do_swap_with_exact_supply(who: &T::AccountId, path: &Vec<T::Hash>, supply_amount: T::Balance, min_target_amount: T::Balance, price_impact_limit: Option<T::Balance>) -> DispatchResult {
+    #[transactional]
+    pub fn do_swap_with_exact_supply(who: &T::AccountId, path: &Vec<T::CurrencyId>, supply_amount: T::Balance, min_target_amount: T::Balance, price_impact_limit: Option<Permill>) -> DispatchResult {
         let amounts = Self::get_target_amounts(&path, supply_amount, price_impact_limit)?;
         ensure!(amounts[amounts.len() - 1] >= min_target_amount, Error::<T>::InsufficientTargetAmount);
         let module_account_id = Self::get_wallet_account();
 
         let actual_target_amount = amounts[amounts.len() - 1];
+        ensure!(T::Currency::free_balance(path[0], who) >= supply_amount, Error::<T>::InsufficientBalance);
 
-        // TODO: @Krishna Please take care of results from the transfers, ensure it's not error
-        //polkadex_custom_assets::Module::<T>::transfer(who, &module_account_id, path[0], &supply_amount_converted, ExistenceRequirement::AllowDeath)?;
+        T::Currency::transfer(path[0], who, &module_account_id, supply_amount)?;
         Self::_swap_by_path(&path, &amounts);
-        //polkadex_custom_assets::Module::<T>::transfer(&module_account_id, who, path[path.len() - 1], &actual_target_amount_converted, ExistenceRequirement::AllowDeath)?;
+        T::Currency::transfer(path[path.len() - 1], &module_account_id, who, actual_target_amount)?;
 
         Self::deposit_event(RawEvent::Swap(who.clone(), path.to_vec(), supply_amount.clone(), actual_target_amount));
 
@@ -255,23 +946,24 @@ impl<T: Config> Module<T> {
     }
 
     /// Swaps with Exact target amount.
-    pub fn do_swap_with_exact_target(who: &T::AccountId, path: &Vec<T::Hash>, target_amount: T::Balance, max_supply_amount: T::Balance, price_impact_limit: Option<T::Balance>) -> DispatchResult {
+    #[transactional]
+    pub fn do_swap_with_exact_target(who: &T::AccountId, path: &Vec<T::CurrencyId>, target_amount: T::Balance, max_supply_amount: T::Balance, price_impact_limit: Option<Permill>) -> DispatchResult {
 
         let amounts = Self::get_supply_amounts(&path, target_amount, price_impact_limit)?;
         ensure!(amounts[0] <= max_supply_amount, Error::<T>::ExcessiveSupplyAmount);
         let module_account_id = Self::get_wallet_account();
         let actual_supply_amount = amounts[0];
+        ensure!(T::Currency::free_balance(path[0], who) >= actual_supply_amount, Error::<T>::InsufficientBalance);
 
-        // TODO: @Krishna Please take care of results from the transfers, ensure it's not error
-        //polkadex_custom_assets::Module::<T>::transfer(who, &module_account_id, path[0], &actual_supply_amount_converted, ExistenceRequirement::AllowDeath)?;
+        T::Currency::transfer(path[0], who, &module_account_id, actual_supply_amount)?;
         Self::_swap_by_path(&path, &amounts);
-        //polkadex_custom_assets::Module::<T>::transfer(&module_account_id, who, path[path.len() - 1], &target_amount_converted, ExistenceRequirement::AllowDeath)?;
+        T::Currency::transfer(path[path.len() - 1], &module_account_id, who, target_amount)?;
 
         Self::deposit_event(RawEvent::Swap(who.clone(), path.to_vec(), actual_supply_amount, target_amount.clone()));
         Ok(())
     }
 
-    pub fn get_liquidity(currency_id_a: T::Hash, currency_id_b: T::Hash) -> (T::Balance, T::Balance) {
+    pub fn get_liquidity(currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId) -> (T::Balance, T::Balance) {
         let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
         let (pool_0, pool_1, _) = Self::liquidity_pool(trading_pair);
         if currency_id_a == trading_pair.0 {
@@ -281,61 +973,178 @@ impl<T: Config> Module<T> {
         }
     }
 
-    /// Get how much target amount will be got for specific supply amount and price impact.
-    fn get_target_amount(supply_pool: T::Balance, target_pool: T::Balance, supply_amount: T::Balance) -> T::Balance {
-        if supply_amount ==  0.into() || supply_pool ==  0.into() || target_pool ==  0.into() {
-            0.into()
+    /// Current amplification coefficient of the pool, `None` for constant product pools.
+    fn current_amplification(trading_pair: (T::CurrencyId, T::CurrencyId)) -> Option<u128> {
+        Self::amplification_ramp(trading_pair)
+            .map(|ramp| ramp.amplification_at(<frame_system::Module<T>>::block_number()) as u128)
+    }
+
+    /// Get how much target amount will be got for specific supply amount and price impact.
+    fn get_target_amount(amplification: Option<u128>, supply_pool: T::Balance, target_pool: T::Balance, supply_amount: T::Balance) -> T::Balance {
+        if supply_amount.is_zero() || supply_pool.is_zero() || target_pool.is_zero() {
+            Zero::zero()
+        } else {
+            let fee_reduced_supply_amount = supply_amount.saturating_sub(Self::swap_fee().mul_ceil(supply_amount));
+
+            if let Some(amplification) = amplification {
+                return stable_swap::get_target_amount(Self::to_u128(supply_pool), Self::to_u128(target_pool), Self::to_u128(fee_reduced_supply_amount), amplification)
+                    .map(T::Balance::unique_saturated_from)
+                    .unwrap_or_else(Zero::zero);
+            }
+
+            let numerator = Self::to_u256(target_pool).saturating_mul(Self::to_u256(fee_reduced_supply_amount));
+            let denominator = Self::to_u256(supply_pool).saturating_add(Self::to_u256(fee_reduced_supply_amount));
+
+            numerator.checked_div(denominator)
+                .and_then(Self::from_u256)
+                .unwrap_or_else(Zero::zero)
+        }
+    }
+
+    /// Get supply amount paid for specific target amount.
+    fn get_supply_amount(amplification: Option<u128>, supply_pool: T::Balance, target_pool: T::Balance, target_amount: T::Balance) -> T::Balance {
+        if target_amount.is_zero() || supply_pool.is_zero() || target_amount >= target_pool {
+            Zero::zero()
         } else {
-            let swap_fee: T::Balance = SwappingFee::get();
-            let fee_term: T::Balance = 1.saturating_sub(swap_fee);
+            let fee_parts = U256::from(Self::swap_fee().deconstruct());
+            let accuracy = U256::from(Permill::ACCURACY);
+
+            // Amount that has to reach the pool once the fee is taken, rounded up
+            let fee_reduced_supply_amount = match amplification {
+                Some(amplification) => {
+                    match stable_swap::get_supply_amount(Self::to_u128(supply_pool), Self::to_u128(target_pool), Self::to_u128(target_amount), amplification) {
+                        Some(amount) => U256::from(amount),
+                        None => return Zero::zero(),
+                    }
+                }
+                None => {
+                    let numerator = Self::to_u256(supply_pool).saturating_mul(Self::to_u256(target_amount));
+                    let denominator = Self::to_u256(target_pool.saturating_sub(target_amount));
+                    numerator.saturating_add(denominator - 1) / denominator
+                }
+            };
 
-            let fee_reduced_supply_amount: T::Balance  = supply_amount.saturating_mul(fee_term);
+            // Gross up by the fee, rounded up
+            let numerator = fee_reduced_supply_amount.saturating_mul(accuracy);
+            let denominator = accuracy - fee_parts;
+            if denominator.is_zero() {
+                return Zero::zero();
+            }
+            Self::from_u256(numerator.saturating_add(denominator - 1) / denominator)
+                .unwrap_or_else(Zero::zero)
+        }
+    }
 
-            let numerator: T::Balance  = target_pool.saturating_mul(fee_reduced_supply_amount);  // product makes this value too low
+    /// Marginal price of the supply asset quoted in the target asset.
+    fn spot_price(amplification: Option<u128>, supply_pool: T::Balance, target_pool: T::Balance) -> sp_std::result::Result<FixedU128, Error<T>> {
+        match amplification {
+            Some(amplification) => stable_swap::spot_price(Self::to_u128(supply_pool), Self::to_u128(target_pool), amplification),
+            None => FixedU128::checked_from_rational(target_pool, supply_pool),
+        }.ok_or(Error::<T>::FixedU128ConversionFailed)
+    }
 
+    fn to_u128(amount: T::Balance) -> u128 {
+        amount.unique_saturated_into()
+    }
 
-            let denominator: T::Balance  = supply_pool.saturating_add(fee_reduced_supply_amount.clone());
+    fn to_u256(amount: T::Balance) -> U256 {
+        U256::from(Self::to_u128(amount))
+    }
 
-            let target_amount: T::Balance  = numerator.checked_div(&denominator)
-                .unwrap_or_else(0);
+    fn from_u256(amount: U256) -> Option<T::Balance> {
+        TryInto::<u128>::try_into(amount).ok().map(T::Balance::unique_saturated_from)
+    }
 
-            target_amount
+    /// Price impact of a single hop, i.e. how much worse the execution price is than the spot
+    /// price of the pool. The swap fee is not part of the price impact.
+    fn price_impact(spot_price: FixedU128, fee_reduced_supply_amount: T::Balance, target_amount: T::Balance) -> Permill {
+        let spot_target_amount = spot_price.saturating_mul_int(fee_reduced_supply_amount);
+        if spot_target_amount.is_zero() || target_amount >= spot_target_amount {
+            return Permill::zero();
         }
+        Permill::one().saturating_sub(Permill::from_rational_approximation(target_amount, spot_target_amount))
     }
 
-    /// Get supply amount paid for specific target amount.
-    fn get_supply_amount(supply_pool: T::Balance, target_pool: T::Balance, target_amount: T::Balance) -> T::Balance {
-        if supply_amount ==  0.into() || supply_pool ==  0.into() || target_pool ==  0.into() {
-            0.into()
-        } else {
-            let swap_fee: T::Balance = SwappingFee::get();
-            let numerator: T::Balance = target_amount.saturating_mul(supply_pool);
-            let fee_term: T::Balance = 1.saturating_sub(swap_fee);
-            let sub: T::Balance = target_pool.saturating_sub(target_amount);
-            let denominator: T::Balance = sub.saturating_mul(fee_term);
+    /// Tightest of the caller's limit, the pool's maximum and the global maximum price impact.
+    fn price_impact_limit(trading_pair: (T::CurrencyId, T::CurrencyId), price_impact_limit: Option<Permill>) -> Option<Permill> {
+        let pool_limit = Self::pool_max_price_impact(trading_pair).or_else(Self::max_price_impact);
+        match (price_impact_limit, pool_limit) {
+            (Some(user_limit), Some(pool_limit)) => Some(user_limit.min(pool_limit)),
+            (user_limit, pool_limit) => user_limit.or(pool_limit),
+        }
+    }
 
-            let supply_amount: T::Balance = numerator.checked_div(&denominator).unwrap_or_else(0);
-            supply_amount
+    /// Per-hop amounts, fees and price impacts of swapping along `path` given the `amounts`
+    /// computed by `get_target_amounts` or `get_supply_amounts`.
+    fn build_quote(path: &[T::CurrencyId], amounts: Vec<T::Balance>) -> SwapQuote<T::Balance> {
+        let swap_fee = Self::swap_fee();
+        let mut fees: Vec<T::Balance> = Vec::with_capacity(path.len() - 1);
+        let mut price_impacts: Vec<Permill> = Vec::with_capacity(path.len() - 1);
+        let mut i: usize = 0;
+        while i + 1 < path.len() {
+            let (supply_pool, target_pool) = Self::get_liquidity(path[i], path[i + 1]);
+            let spot_price = Self::spot_price(Self::current_amplification(Self::get_pair(path[i], path[i + 1])), supply_pool, target_pool).unwrap_or_default();
+            let fee = swap_fee.mul_ceil(amounts[i]);
+            price_impacts.push(Self::price_impact(spot_price, amounts[i].saturating_sub(fee), amounts[i + 1]));
+            fees.push(fee);
+            i += 1;
         }
+        SwapQuote { amounts, fees, price_impacts }
+    }
+
+    /// Quotes a swap of exactly `supply_amount` of `path[0]` along `path`.
+    pub fn quote_swap_with_exact_supply(path: &[T::CurrencyId], supply_amount: T::Balance) -> sp_std::result::Result<SwapQuote<T::Balance>, DispatchError> {
+        let amounts = Self::get_target_amounts(path, supply_amount, None)?;
+        Ok(Self::build_quote(path, amounts))
+    }
+
+    /// Quotes a swap receiving exactly `target_amount` of the last asset of `path`.
+    pub fn quote_swap_with_exact_target(path: &[T::CurrencyId], target_amount: T::Balance) -> sp_std::result::Result<SwapQuote<T::Balance>, DispatchError> {
+        let amounts = Self::get_supply_amounts(path, target_amount, None)?;
+        Ok(Self::build_quote(path, amounts))
+    }
+
+    /// Returns the reserves of `currency_id_a` and `currency_id_b` and the total LP shares of
+    /// their pool.
+    pub fn get_pool_reserves(currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId) -> (T::Balance, T::Balance, T::Balance) {
+        let (_, _, total_shares) = Self::liquidity_pool(Self::get_pair(currency_id_a, currency_id_b));
+        let (pool_a, pool_b) = Self::get_liquidity(currency_id_a, currency_id_b);
+        (pool_a, pool_b, total_shares)
+    }
+
+    /// Returns the LP shares held by `who` in the pool of `currency_id_a` and `currency_id_b`.
+    pub fn get_lp_holdings(who: &T::AccountId, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId) -> T::Balance {
+        Self::holdings((who, Self::get_pair(currency_id_a, currency_id_b)))
+    }
+
+    /// Returns the LP shares minted to `ProtocolFeeAccount` in the pool of `currency_id_a` and `currency_id_b`.
+    pub fn get_accrued_protocol_fee(currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId) -> T::Balance {
+        Self::accrued_protocol_fee(Self::get_pair(currency_id_a, currency_id_b))
     }
+
     /// Get vector of target amount for specific supply amount and price impact.
-    fn get_target_amounts(path: &[T::Hash], supply_amount: T::Balance, price_impact_limit: Option<T::Balance>) -> sp_std::result::Result<Vec<T::Balance>, Error<T>> {
+    fn get_target_amounts(path: &[T::CurrencyId], supply_amount: T::Balance, price_impact_limit: Option<Permill>) -> sp_std::result::Result<Vec<T::Balance>, Error<T>> {
         let path_length = path.len();
         ensure!(path_length >= 2 && path_length <= T::TradingPathLimit::get(), Error::<T>::InvalidTradingPathLength);
-        let mut target_amounts: Vec<FixedU128> = vec![0.into(); path_length];
+        let mut target_amounts: Vec<T::Balance> = vec![Zero::zero(); path_length];
         target_amounts[0] = supply_amount;
 
         let mut i: usize = 0;
         while i + 1 < path_length {
-            ensure!(LiquidityPool::<T>::contains_key(Self::get_pair(path[i],path[i+1])),Error::<T>::TradingPairNotAllowed);
+            let trading_pair = Self::get_pair(path[i], path[i + 1]);
+            ensure!(LiquidityPool::<T>::contains_key(trading_pair),Error::<T>::TradingPairNotAllowed);
+            ensure!(!Self::flash_swap_locked(trading_pair), Error::<T>::PoolLocked);
             let (supply_pool, target_pool) = Self::get_liquidity(path[i], path[i + 1]);
             ensure!(!supply_pool.is_zero() && !target_pool.is_zero(),Error::<T>::InsufficientLiquidity);
-            let target_amount = Self::get_target_amount(supply_pool, target_pool, target_amounts[i]);
+            let amplification = Self::current_amplification(trading_pair);
+            let target_amount = Self::get_target_amount(amplification, supply_pool, target_pool, target_amounts[i]);
             ensure!(!target_amount.is_zero(), Error::<T>::ZeroTargetAmount);
 
             // check price impact if limit exists
-            if let Some(limit) = price_impact_limit {
-                let price_impact = target_amount.checked_div(&target_pool).unwrap_or_else(0.into());
+            if let Some(limit) = Self::price_impact_limit(trading_pair, price_impact_limit) {
+                let fee_reduced_supply_amount = target_amounts[i].saturating_sub(Self::swap_fee().mul_ceil(target_amounts[i]));
+                let spot_price = Self::spot_price(amplification, supply_pool, target_pool)?;
+                let price_impact = Self::price_impact(spot_price, fee_reduced_supply_amount, target_amount);
                 ensure!(price_impact <= limit, Error::<T>::ExceedPriceImpactLimit);
             }
 
@@ -346,25 +1155,30 @@ impl<T: Config> Module<T> {
         Ok(target_amounts)
     }
     /// Get vector of supply amount for specific target amount and price impact.
-    fn get_supply_amounts(path: &[T::Hash], target_amount: T::Balance, price_impact_limit: Option<T::Balance>) -> sp_std::result::Result<Vec<T::Balance>, Error<T>> {
+    fn get_supply_amounts(path: &[T::CurrencyId], target_amount: T::Balance, price_impact_limit: Option<Permill>) -> sp_std::result::Result<Vec<T::Balance>, Error<T>> {
         let path_length = path.len();
         ensure!(path_length >= 2 && path_length <= T::TradingPathLimit::get(), Error::<T>::InvalidTradingPathLength);
 
-        let mut supply_amounts: Vec<FixedU128> = vec![0; path_length];
+        let mut supply_amounts: Vec<T::Balance> = vec![Zero::zero(); path_length];
         supply_amounts[path_length - 1] = target_amount;
 
         let mut i: usize = path_length - 1;
         while i > 0 {
-            ensure!(LiquidityPool::<T>::contains_key(Self::get_pair(path[i-1],path[i])), Error::<T>::TradingPairNotAllowed);
+            let trading_pair = Self::get_pair(path[i - 1], path[i]);
+            ensure!(LiquidityPool::<T>::contains_key(trading_pair), Error::<T>::TradingPairNotAllowed);
+            ensure!(!Self::flash_swap_locked(trading_pair), Error::<T>::PoolLocked);
             let (supply_pool, target_pool) = Self::get_liquidity(path[i - 1], path[i]);
             ensure!(!supply_pool.is_zero() && !target_pool.is_zero(),Error::<T>::InsufficientLiquidity);
 
-            let supply_amount = Self::get_supply_amount(supply_pool, target_pool, supply_amounts[i]);
+            let amplification = Self::current_amplification(trading_pair);
+            let supply_amount = Self::get_supply_amount(amplification, supply_pool, target_pool, supply_amounts[i]);
             ensure!(!supply_amount.is_zero(), Error::<T>::ZeroSupplyAmount);
 
             // check price impact if limit exists
-            if let Some(limit) = price_impact_limit {
-                let price_impact = supply_amounts[i].checked_div(&target_pool).unwrap_or_else(0.into());
+            if let Some(limit) = Self::price_impact_limit(trading_pair, price_impact_limit) {
+                let fee_reduced_supply_amount = supply_amount.saturating_sub(Self::swap_fee().mul_ceil(supply_amount));
+                let spot_price = Self::spot_price(amplification, supply_pool, target_pool)?;
+                let price_impact = Self::price_impact(spot_price, fee_reduced_supply_amount, supply_amounts[i]);
                 ensure!(price_impact <= limit, Error::<T>::ExceedPriceImpactLimit);
             };
 
@@ -375,8 +1189,9 @@ impl<T: Config> Module<T> {
         Ok(supply_amounts)
     }
 
-    fn _swap(supply_currency_id: T::Hash, target_currency_id: T::Hash, supply_increment: T::Balance, target_decrement: T::Balance) {
+    fn _swap(supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId, supply_increment: T::Balance, target_decrement: T::Balance) {
         let trading_pair = Self::get_pair(supply_currency_id, target_currency_id);
+        Self::update_price_accumulator(trading_pair, <frame_system::Module<T>>::block_number());
         LiquidityPool::<T>::mutate(trading_pair, |(pool_0, pool_1, _pool_shares): &mut (T::Balance, T::Balance, T::Balance)| {
             if supply_currency_id == trading_pair.0 {
                 *pool_0 = pool_0.saturating_add(supply_increment);
@@ -386,9 +1201,11 @@ impl<T: Config> Module<T> {
                 *pool_1 = pool_1.saturating_add(supply_increment);
             }
         });
+        let fee = Self::swap_fee().mul_ceil(supply_increment);
+        Self::deposit_event(RawEvent::PoolSwap(supply_currency_id, supply_increment, target_currency_id, target_decrement, fee));
     }
 
-    fn _swap_by_path(path: &[T::Hash], amounts: &[T::Balance]) {
+    fn _swap_by_path(path: &[T::CurrencyId], amounts: &[T::Balance]) {
         let mut i: usize = 0;
         while i + 1 < path.len() {
             let (supply_currency_id, target_currency_id) = (path[i], path[i + 1]);
@@ -402,13 +1219,17 @@ impl<T: Config> Module<T> {
             i += 1;
         }
     }
-    /// Adds Liquidity for specific swapping pair.
-    pub fn do_add_liquidity(who: &T::AccountId, currency_id_a: T::Hash, currency_id_b: T::Hash, max_amount_a: T::Balance, max_amount_b: T::Balance) -> dispatch::DispatchResult {
-        ensure!(!max_amount_a == 0.into() && !max_amount_b == 0.into(), Error::<T>::ProvidedAmountIsZero);
+    /// Adds Liquidity for specific swapping pair, returns the LP shares minted.
+    #[transactional]
+    pub fn do_add_liquidity(who: &T::AccountId, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, max_amount_a: T::Balance, max_amount_b: T::Balance, min_share: T::Balance) -> sp_std::result::Result<T::Balance, DispatchError> {
+        ensure!(!max_amount_a.is_zero() && !max_amount_b.is_zero(), Error::<T>::ProvidedAmountIsZero);
 
         let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+        ensure!(!Self::flash_swap_locked(trading_pair), Error::<T>::PoolLocked);
+        Self::update_price_accumulator(trading_pair, <frame_system::Module<T>>::block_number());
 
-        <LiquidityPool<T>>::try_mutate(trading_pair, |(pool_0, pool_1, pool_shares)| -> dispatch::DispatchResult {
+        <LiquidityPool<T>>::try_mutate(trading_pair, |(pool_0, pool_1, pool_shares)| -> sp_std::result::Result<T::Balance, DispatchError> {
+            let protocol_fee_on = Self::mint_protocol_fee(trading_pair, *pool_0, *pool_1, pool_shares);
             let (max_amount_0, max_amount_1) = if currency_id_a == trading_pair.0 {
                 (max_amount_a, max_amount_b)
             } else {
@@ -416,86 +1237,96 @@ impl<T: Config> Module<T> {
             };
 
             let (pool_0_increment, pool_1_increment, share_increment): (T::Balance, T::Balance, T::Balance) =
-                if pool_shares == 0.into() {
+                if pool_shares.is_zero() {
                     // initialize this liquidity pool, the initial share is equal to the max value
                     // between base currency amount and other currency amount
                     let initial_share = sp_std::cmp::max(max_amount_0, max_amount_1);
-                    (max_amount_0.clone(), max_amount_1.clone(), initial_share)
+                    (max_amount_0, max_amount_1, initial_share)
                 } else {
-                    let price_0_1 = pool_1.checked_div(&pool_0).unwrap_or(0.into());
-                    let input_price_0_1 = max_amount_1.checked_div(&max_amount_0).unwrap_or(0.into());
-
-                    if input_price_0_1 <= price_0_1 {
-                        // max_amount_0 may be too much, calculate the actual amount_0
-                        let price_1_0: FixedU128 = pool_0.checked_div(pool_1).unwrap_or(0.into());
-                        let amount_0 = price_1_0.checked_mul(&max_amount_1).unwrap_or(0.into());
-                        let share_increment = amount_0.checked_div(pool_0).unwrap_or(0.into())
-                            .checked_mul(pool_shares).unwrap_or(0.into());
-                        (amount_0, max_amount_1, share_increment)
+                    let amount_1 = Self::mul_div(max_amount_0, *pool_1, *pool_0).ok_or(Error::<T>::InvalidLiquidityIncrement)?;
+                    let (amount_0, amount_1) = if amount_1 <= max_amount_1 {
+                        // max_amount_1 may be too much, only deposit the amount matching max_amount_0
+                        (max_amount_0, amount_1)
                     } else {
-                        // max_amount_1 is too much, calculate the actual amount_1
-                        let amount_1 = price_0_1.checked_mul(&max_amount_0).unwrap_or(0.into());
-                        let share_increment = amount_1.checked_div(pool_1).unwrap_or(0.into())
-                            .checked_mul(pool_shares)
-                            .unwrap_or(0.into());
-                        (max_amount_0, amount_1, share_increment)
-                    }
+                        // max_amount_0 is too much, calculate the actual amount_0
+                        let amount_0 = Self::mul_div(max_amount_1, *pool_0, *pool_1).ok_or(Error::<T>::InvalidLiquidityIncrement)?;
+                        (amount_0, max_amount_1)
+                    };
+                    let share_increment = sp_std::cmp::min(
+                        Self::mul_div(amount_0, *pool_shares, *pool_0).ok_or(Error::<T>::InvalidLiquidityIncrement)?,
+                        Self::mul_div(amount_1, *pool_shares, *pool_1).ok_or(Error::<T>::InvalidLiquidityIncrement)?,
+                    );
+                    (amount_0, amount_1, share_increment)
                 };
-            ensure!(!share_increment==0.into() && !pool_0_increment==0.into() && !pool_1_increment==0.into(), Error::<T>::InvalidLiquidityIncrement);
+            ensure!(!share_increment.is_zero() && !pool_0_increment.is_zero() && !pool_1_increment.is_zero(), Error::<T>::InvalidLiquidityIncrement);
+            ensure!(share_increment >= min_share, Error::<T>::InsufficientShareIncrement);
             let swap_wallet_account = Self::get_wallet_account();
 
-            //polkadex_custom_assets::Module::<T>::transfer(who, &swap_wallet_account, trading_pair.0, &pool_0_increment_converted, ExistenceRequirement::AllowDeath)?;
-            //polkadex_custom_assets::Module::<T>::transfer(who, &swap_wallet_account, trading_pair.1, &pool_1_increment_converted, ExistenceRequirement::AllowDeath)?;
+            T::Currency::transfer(trading_pair.0, who, &swap_wallet_account, pool_0_increment)?;
+            T::Currency::transfer(trading_pair.1, who, &swap_wallet_account, pool_1_increment)?;
 
-            <LiquidityPoolHoldings<T>>::try_mutate((who, trading_pair), |lp_shares| -> dispatch::DispatchResult {
+            <LiquidityPoolHoldings<T>>::mutate((who, trading_pair), |lp_shares| {
                 *lp_shares = lp_shares.saturating_add(share_increment);
-                Ok(())
-            })?;
+            });
 
             *pool_0 = pool_0.saturating_add(pool_0_increment);
             *pool_1 = pool_1.saturating_add(pool_1_increment);
-            *pool_shares = pool_shares.saturating_add(share_increment.clone()); // TODO ask @gautham about this
+            *pool_shares = pool_shares.saturating_add(share_increment);
+            if protocol_fee_on {
+                Self::snapshot_root_k(trading_pair, *pool_0, *pool_1);
+            }
 
             Self::deposit_event(RawEvent::AddLiquidity(
                 who.clone(),
                 trading_pair.0,
-                pool_0_increment.clone(),
+                pool_0_increment,
                 trading_pair.1,
-                pool_1_increment.clone(),
-                share_increment.clone(),
+                pool_1_increment,
+                share_increment,
             ));
-            Ok(())
+            Ok(share_increment)
         })
     }
-    /// Removes liquidity for specific trading pair.
-    pub fn do_remove_liquidity(who: &T::AccountId, currency_id_a: T::Hash, currency_id_b: T::Hash, remove_share: T::Balance) -> DispatchResult {
+    /// Removes liquidity for specific trading pair, returns the amounts of `currency_id_a` and
+    /// `currency_id_b` withdrawn.
+    #[transactional]
+    pub fn do_remove_liquidity(who: &T::AccountId, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, remove_share: T::Balance, min_amount_a: T::Balance, min_amount_b: T::Balance) -> sp_std::result::Result<(T::Balance, T::Balance), DispatchError> {
         if remove_share.is_zero() {
-            return Ok(());
+            return Ok((Zero::zero(), Zero::zero()));
         }
-        let remove_share: FixedU128 = Self::convert_balance_to_fixedU128(remove_share).ok_or(Error::<T>::FixedU128ConversionFailed)?;
 
         let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
         ensure!(<LiquidityPool<T>>::contains_key(&trading_pair), Error::<T>::TradingPairNotAllowed);
         let original_share = <LiquidityPoolHoldings<T>>::get((who, trading_pair));
         ensure!(remove_share <= original_share, Error::<T>::LowShare);
-
-        <LiquidityPool<T>>::try_mutate(trading_pair, |(pool_0, pool_1, pool_shares)| -> dispatch::DispatchResult {
-            let proportion = remove_share.checked_div(pool_shares).unwrap_or(0);
-            let pool_0_decrement = proportion.saturating_mul(*pool_0);
-            let pool_1_decrement = proportion.saturating_mul(*pool_1);
+        ensure!(!Self::flash_swap_locked(trading_pair), Error::<T>::PoolLocked);
+        Self::update_price_accumulator(trading_pair, <frame_system::Module<T>>::block_number());
+
+        let (pool_0_decrement, pool_1_decrement) = <LiquidityPool<T>>::try_mutate(trading_pair, |(pool_0, pool_1, pool_shares)| -> sp_std::result::Result<(T::Balance, T::Balance), DispatchError> {
+            let protocol_fee_on = Self::mint_protocol_fee(trading_pair, *pool_0, *pool_1, pool_shares);
+            let pool_0_decrement = Self::mul_div(remove_share, *pool_0, *pool_shares).ok_or(Error::<T>::InsufficientLiquidity)?;
+            let pool_1_decrement = Self::mul_div(remove_share, *pool_1, *pool_shares).ok_or(Error::<T>::InsufficientLiquidity)?;
+            let (min_amount_0, min_amount_1) = if currency_id_a == trading_pair.0 {
+                (min_amount_a, min_amount_b)
+            } else {
+                (min_amount_b, min_amount_a)
+            };
+            ensure!(pool_0_decrement >= min_amount_0 && pool_1_decrement >= min_amount_1, Error::<T>::InsufficientWithdrawnAmount);
             let swap_wallet_account = Self::get_wallet_account();
 
-            //polkadex_custom_assets::Module::<T>::transfer(&swap_wallet_account, &who, trading_pair.0, &pool_0_decrement_converted, ExistenceRequirement::KeepAlive)?;
-            //polkadex_custom_assets::Module::<T>::transfer(&swap_wallet_account, &who, trading_pair.1, &pool_1_decrement_converted, ExistenceRequirement::KeepAlive)?;
+            T::Currency::transfer(trading_pair.0, &swap_wallet_account, who, pool_0_decrement)?;
+            T::Currency::transfer(trading_pair.1, &swap_wallet_account, who, pool_1_decrement)?;
 
             *pool_0 = pool_0.saturating_sub(pool_0_decrement);
             *pool_1 = pool_1.saturating_sub(pool_1_decrement);
+            *pool_shares = pool_shares.saturating_sub(remove_share);
+            if protocol_fee_on {
+                Self::snapshot_root_k(trading_pair, *pool_0, *pool_1);
+            }
 
-            <LiquidityPoolHoldings<T>>::try_mutate((who, trading_pair), |lp_shares| -> dispatch::DispatchResult {
+            <LiquidityPoolHoldings<T>>::mutate((who, trading_pair), |lp_shares| {
                 *lp_shares = lp_shares.saturating_sub(remove_share);
-                Ok(())
-            })?;
-
+            });
 
             Self::deposit_event(RawEvent::RemoveLiquidity(
                 who.clone(),
@@ -505,12 +1336,1033 @@ impl<T: Config> Module<T> {
                 pool_1_decrement,
                 remove_share,
             ));
+            Ok((pool_0_decrement, pool_1_decrement))
+        })?;
+
+        if currency_id_a == trading_pair.0 {
+            Ok((pool_0_decrement, pool_1_decrement))
+        } else {
+            Ok((pool_1_decrement, pool_0_decrement))
+        }
+    }
+
+    /// Redeems LP shares of `ProtocolFeeAccount` and pays the assets withdrawn to `beneficiary`.
+    #[transactional]
+    pub fn do_withdraw_protocol_fee(currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, remove_share: T::Balance, beneficiary: &T::AccountId) -> DispatchResult {
+        let protocol_fee_account = T::ProtocolFeeAccount::get();
+        let (amount_a, amount_b) = Self::do_remove_liquidity(&protocol_fee_account, currency_id_a, currency_id_b, remove_share, Zero::zero(), Zero::zero())?;
+        T::Currency::transfer(currency_id_a, &protocol_fee_account, beneficiary, amount_a)?;
+        T::Currency::transfer(currency_id_b, &protocol_fee_account, beneficiary, amount_b)?;
+
+        let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+        let (amount_0, amount_1) = if currency_id_a == trading_pair.0 { (amount_a, amount_b) } else { (amount_b, amount_a) };
+        Self::deposit_event(RawEvent::ProtocolFeeWithdrawn(beneficiary.clone(), trading_pair.0, amount_0, trading_pair.1, amount_1, remove_share));
+        Ok(())
+    }
+
+    /// Swaps the optimal part of `supply_amount` for `other_currency_id` and adds both as
+    /// liquidity, returns the LP shares minted.
+    #[transactional]
+    pub fn do_add_liquidity_single_asset(who: &T::AccountId, supply_currency_id: T::CurrencyId, other_currency_id: T::CurrencyId, supply_amount: T::Balance, min_share: T::Balance) -> sp_std::result::Result<T::Balance, DispatchError> {
+        ensure!(!supply_amount.is_zero(), Error::<T>::ProvidedAmountIsZero);
+        ensure!(supply_currency_id != other_currency_id, Error::<T>::InvalidCurrencyId);
+        let trading_pair = Self::get_pair(supply_currency_id, other_currency_id);
+        ensure!(LiquidityPool::<T>::contains_key(trading_pair), Error::<T>::TradingPairNotAllowed);
+        ensure!(Self::amplification_ramp(trading_pair).is_none(), Error::<T>::NotConstantProductPool);
+        let (supply_pool, _) = Self::get_liquidity(supply_currency_id, other_currency_id);
+        ensure!(!supply_pool.is_zero(), Error::<T>::InsufficientLiquidity);
+
+        let swap_amount = Self::optimal_single_asset_swap_amount(supply_pool, supply_amount).ok_or(Error::<T>::InvalidLiquidityIncrement)?;
+        ensure!(!swap_amount.is_zero() && swap_amount < supply_amount, Error::<T>::InvalidLiquidityIncrement);
+        let path = vec![supply_currency_id, other_currency_id];
+        let amounts = Self::get_target_amounts(&path, swap_amount, None)?;
+        Self::do_swap_with_exact_supply(who, &path, swap_amount, amounts[1], None)?;
+
+        Self::do_add_liquidity(who, supply_currency_id, other_currency_id, supply_amount.saturating_sub(swap_amount), amounts[1], min_share)
+    }
+
+    /// Removes liquidity and swaps the withdrawn `other_currency_id` into `target_currency_id`,
+    /// returns the total amount of `target_currency_id` received.
+    #[transactional]
+    pub fn do_remove_liquidity_single_asset(who: &T::AccountId, target_currency_id: T::CurrencyId, other_currency_id: T::CurrencyId, remove_share: T::Balance, min_target_amount: T::Balance) -> sp_std::result::Result<T::Balance, DispatchError> {
+        ensure!(!remove_share.is_zero(), Error::<T>::ProvidedAmountIsZero);
+        ensure!(target_currency_id != other_currency_id, Error::<T>::InvalidCurrencyId);
+        let (target_amount, other_amount) = Self::do_remove_liquidity(who, target_currency_id, other_currency_id, remove_share, Zero::zero(), Zero::zero())?;
+
+        let swapped_amount = if other_amount.is_zero() {
+            Zero::zero()
+        } else {
+            let path = vec![other_currency_id, target_currency_id];
+            let amounts = Self::get_target_amounts(&path, other_amount, None)?;
+            Self::do_swap_with_exact_supply(who, &path, other_amount, amounts[1], None)?;
+            amounts[1]
+        };
+        let target_amount = target_amount.saturating_add(swapped_amount);
+        ensure!(target_amount >= min_target_amount, Error::<T>::InsufficientTargetAmount);
+        Ok(target_amount)
+    }
+
+    /// Part of `supply_amount` to swap so that the remainder and the swap output match the
+    /// ratio of the pool after the swap, for a constant product pool with fee `f`:
+    /// `s = (√(((2 - f) r)² + 4 (1 - f) r x) - (2 - f) r) / (2 (1 - f))`
+    fn optimal_single_asset_swap_amount(supply_pool: T::Balance, supply_amount: T::Balance) -> Option<T::Balance> {
+        let accuracy = U512::from(Permill::ACCURACY);
+        let fee_complement = U512::from(Permill::ACCURACY - Self::swap_fee().deconstruct());
+        let supply_pool = U512::from(Self::to_u128(supply_pool));
+        let supply_amount = U512::from(Self::to_u128(supply_amount));
+
+        let b = (accuracy + fee_complement).checked_mul(supply_pool)?;
+        let discriminant = b.checked_mul(b)?
+            .checked_add(U512::from(4u8).checked_mul(fee_complement)?.checked_mul(accuracy)?.checked_mul(supply_pool)?.checked_mul(supply_amount)?)?;
+        let swap_amount = discriminant.integer_sqrt().checked_sub(b)?.checked_div(fee_complement.checked_mul(U512::from(2u8))?)?;
+        if swap_amount > U512::from(u128::max_value()) {
+            return None;
+        }
+        Some(T::Balance::unique_saturated_from(swap_amount.low_u128()))
+    }
+
+    /// Fails once the block number is past `deadline`.
+    fn ensure_deadline(deadline: Option<T::BlockNumber>) -> DispatchResult {
+        if let Some(deadline) = deadline {
+            ensure!(<frame_system::Module<T>>::block_number() <= deadline, Error::<T>::DeadlineExpired);
+        }
+        Ok(())
+    }
+
+    /// `a * b / c` without intermediate overflow, rounded down.
+    fn mul_div(a: T::Balance, b: T::Balance, c: T::Balance) -> Option<T::Balance> {
+        Self::to_u256(a).saturating_mul(Self::to_u256(b))
+            .checked_div(Self::to_u256(c))
+            .and_then(Self::from_u256)
+    }
+
+    /// Square root of `pool_0 * pool_1`, or `D` for StableSwap pools. Both grow linearly with
+    /// the liquidity of the pool, and only grow otherwise through fees.
+    fn root_k(amplification: Option<u128>, pool_0: T::Balance, pool_1: T::Balance) -> U256 {
+        match amplification {
+            Some(amplification) => stable_swap::get_d(Self::to_u128(pool_0), Self::to_u128(pool_1), amplification).unwrap_or_default(),
+            None => Self::to_u256(pool_0).saturating_mul(Self::to_u256(pool_1)).integer_sqrt(),
+        }
+    }
+
+    /// Mints the protocol's share of the fees accrued since the last liquidity event as LP
+    /// shares of `ProtocolFeeAccount`. Must be called before the reserves and shares of the pool
+    /// change, returns whether the protocol fee is on.
+    ///
+    /// With `φ` the protocol fee and `S` the total shares, the protocol receives
+    /// `S * φ * (√k - √k_last) / (√k - φ * (√k - √k_last))` shares, i.e. a fraction `φ` of the
+    /// growth of the pool since `k_last`.
+    fn mint_protocol_fee(trading_pair: (T::CurrencyId, T::CurrencyId), pool_0: T::Balance, pool_1: T::Balance, pool_shares: &mut T::Balance) -> bool {
+        let protocol_fee = match Self::protocol_fee() {
+            Some(protocol_fee) if !protocol_fee.is_zero() => protocol_fee,
+            _ => {
+                RootKLast::<T>::remove(trading_pair);
+                RootKLastAmplification::<T>::remove(trading_pair);
+                return false;
+            }
+        };
+        let root_k_last = Self::root_k_last(trading_pair);
+        // `D` only measures fee growth at a fixed amplification
+        let amplification = Self::root_k_last_amplification(trading_pair)
+            .or_else(|| Self::current_amplification(trading_pair));
+        let root_k = Self::root_k(amplification, pool_0, pool_1);
+        if root_k_last.is_zero() || root_k <= root_k_last {
+            return true;
+        }
+
+        let fee_growth = (root_k - root_k_last)
+            .saturating_mul(U256::from(protocol_fee.deconstruct()))
+            / U256::from(Permill::ACCURACY);
+        let share_increment = Self::to_u256(*pool_shares).checked_mul(fee_growth)
+            .and_then(|numerator| numerator.checked_div(root_k.saturating_sub(fee_growth)))
+            .and_then(Self::from_u256)
+            .unwrap_or_else(Zero::zero);
+        if !share_increment.is_zero() {
+            LiquidityPoolHoldings::<T>::mutate((T::ProtocolFeeAccount::get(), trading_pair), |lp_shares| {
+                *lp_shares = lp_shares.saturating_add(share_increment)
+            });
+            AccruedProtocolFee::<T>::mutate(trading_pair, |accrued| *accrued = accrued.saturating_add(share_increment));
+            *pool_shares = pool_shares.saturating_add(share_increment);
+            Self::deposit_event(RawEvent::ProtocolFeeMinted(trading_pair.0, trading_pair.1, share_increment));
+        }
+        true
+    }
+
+    /// Records `RootKLast` after a liquidity event, at the current amplification of StableSwap pools.
+    fn snapshot_root_k(trading_pair: (T::CurrencyId, T::CurrencyId), pool_0: T::Balance, pool_1: T::Balance) {
+        let amplification = Self::current_amplification(trading_pair);
+        RootKLast::<T>::insert(trading_pair, Self::root_k(amplification, pool_0, pool_1));
+        RootKLastAmplification::<T>::set(trading_pair, amplification);
+    }
+
+    /// Accumulates the current spot prices of the pool over the blocks elapsed since the last
+    /// update and records an observation at the first update of a block. Must be called before
+    /// the reserves of the pool change.
+    fn update_price_accumulator(trading_pair: (T::CurrencyId, T::CurrencyId), now: T::BlockNumber) {
+        let (pool_0, pool_1, _) = Self::liquidity_pool(trading_pair);
+        let amplification = Self::current_amplification(trading_pair);
+        let accumulator = PriceAccumulators::<T>::mutate(trading_pair, |accumulator| {
+            *accumulator = Self::accumulate(accumulator, amplification, pool_0, pool_1, now);
+            *accumulator
+        });
+        Self::record_price_observation(trading_pair, accumulator);
+    }
+
+    /// Appends `accumulator` to the observations of the pool, overwriting the oldest one once
+    /// `TwapObservationPeriod` are kept. Only the first snapshot of a block is kept, as prices
+    /// accumulated by later events of the block are weighted by zero blocks.
+    fn record_price_observation(trading_pair: (T::CurrencyId, T::CurrencyId), accumulator: PriceAccumulator<T::BlockNumber>) {
+        let index = Self::latest_price_observation(trading_pair);
+        match Self::price_observation(trading_pair, index) {
+            Some(latest) if latest.last_update >= accumulator.last_update => {}
+            Some(_) => {
+                let next = (index + 1) % T::TwapObservationPeriod::get().max(1);
+                LatestPriceObservation::<T>::insert(trading_pair, next);
+                PriceObservations::<T>::insert(trading_pair, next, accumulator);
+            }
+            None => PriceObservations::<T>::insert(trading_pair, index, accumulator),
+        }
+    }
+
+    /// Returns `accumulator` advanced to block `now` using the given reserves as spot price.
+    fn accumulate(accumulator: &PriceAccumulator<T::BlockNumber>, amplification: Option<u128>, pool_0: T::Balance, pool_1: T::Balance, now: T::BlockNumber) -> PriceAccumulator<T::BlockNumber> {
+        let mut updated = *accumulator;
+        if now > accumulator.last_update && !pool_0.is_zero() && !pool_1.is_zero() {
+            let elapsed: u128 = now.saturating_sub(accumulator.last_update).unique_saturated_into();
+            let elapsed = FixedU128::saturating_from_integer(elapsed);
+            let price_0 = Self::spot_price(amplification, pool_0, pool_1).unwrap_or_default();
+            let price_1 = Self::spot_price(amplification, pool_1, pool_0).unwrap_or_default();
+            updated.price_0_cumulative = updated.price_0_cumulative.saturating_add(price_0.saturating_mul(elapsed));
+            updated.price_1_cumulative = updated.price_1_cumulative.saturating_add(price_1.saturating_mul(elapsed));
+        }
+        updated.last_update = now;
+        updated
+    }
+
+    /// Cumulative prices of the pool at block `at`, `None` if `at` is older than the oldest
+    /// observation kept.
+    ///
+    /// Prices only change with the reserves, so cumulative prices grow linearly between two
+    /// observations and after the newest one.
+    fn cumulative_prices_at(trading_pair: (T::CurrencyId, T::CurrencyId), at: T::BlockNumber) -> Option<(FixedU128, FixedU128)> {
+        let period = T::TwapObservationPeriod::get().max(1);
+        let latest = Self::latest_price_observation(trading_pair);
+        let (oldest, count) = if Self::price_observation(trading_pair, (latest + 1) % period).is_some() {
+            ((latest + 1) % period, period)
+        } else {
+            (0, latest + 1)
+        };
+        let observation = |position: u32| Self::price_observation(trading_pair, (oldest + position) % period);
+
+        // Binary search of the newest observation at or before `at`
+        if observation(0)?.last_update > at {
+            return None;
+        }
+        let (mut low, mut high) = (0, count - 1);
+        while low < high {
+            let middle = (low + high + 1) / 2;
+            if observation(middle)?.last_update <= at {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        let before = observation(low)?;
+
+        if low == count - 1 {
+            let (pool_0, pool_1, _) = Self::liquidity_pool(trading_pair);
+            let accumulator = Self::accumulate(&Self::price_accumulator(trading_pair), Self::current_amplification(trading_pair), pool_0, pool_1, at);
+            return Some((accumulator.price_0_cumulative, accumulator.price_1_cumulative));
+        }
+        let after = observation(low + 1)?;
+        let span: u128 = after.last_update.saturating_sub(before.last_update).unique_saturated_into();
+        let elapsed: u128 = at.saturating_sub(before.last_update).unique_saturated_into();
+        let interpolate = |before: FixedU128, after: FixedU128| {
+            after.saturating_sub(before)
+                .checked_div(&FixedU128::saturating_from_integer(span))
+                .unwrap_or_default()
+                .saturating_mul(FixedU128::saturating_from_integer(elapsed))
+                .saturating_add(before)
+        };
+        Some((
+            interpolate(before.price_0_cumulative, after.price_0_cumulative),
+            interpolate(before.price_1_cumulative, after.price_1_cumulative),
+        ))
+    }
+
+    /// Returns the time-weighted average price of `currency_id_a` quoted in `currency_id_b`
+    /// over the last `window` blocks.
+    ///
+    /// `window` must be non zero and the oldest observation kept must be at least `window`
+    /// blocks old.
+    pub fn get_twap(currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, window: T::BlockNumber) -> sp_std::result::Result<FixedU128, Error<T>> {
+        let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+        ensure!(LiquidityPool::<T>::contains_key(trading_pair), Error::<T>::TradingPairNotAllowed);
+        let window_length: u128 = window.unique_saturated_into();
+        ensure!(window_length > 0, Error::<T>::TwapWindowUnavailable);
+
+        let now = <frame_system::Module<T>>::block_number();
+        ensure!(now >= window, Error::<T>::TwapWindowUnavailable);
+        let (observed_0_cumulative, observed_1_cumulative) = Self::cumulative_prices_at(trading_pair, now - window)
+            .ok_or(Error::<T>::TwapWindowUnavailable)?;
+
+        let (pool_0, pool_1, _) = Self::liquidity_pool(trading_pair);
+        let current = Self::accumulate(&Self::price_accumulator(trading_pair), Self::current_amplification(trading_pair), pool_0, pool_1, now);
+        let window_length = FixedU128::saturating_from_integer(window_length);
+        let (price_cumulative, observed_cumulative) = if currency_id_a == trading_pair.0 {
+            (current.price_0_cumulative, observed_0_cumulative)
+        } else {
+            (current.price_1_cumulative, observed_1_cumulative)
+        };
+        Ok(price_cumulative.saturating_sub(observed_cumulative)
+            .checked_div(&window_length)
+            .unwrap_or_default())
+    }
+
+    /// Ensures a route submitted by a client is still tradable: it must fit in the trading path
+    /// limit, never visit an asset twice and every hop must have liquidity.
+    /// Whether the route is still the best one is left to the caller's `min_target_amount`.
+    fn ensure_valid_route(path: &[T::CurrencyId]) -> DispatchResult {
+        ensure!(path.len() >= 2 && path.len() <= T::TradingPathLimit::get(), Error::<T>::InvalidTradingPathLength);
+        for (i, currency_id) in path.iter().enumerate() {
+            ensure!(!path[i + 1..].contains(currency_id), Error::<T>::InvalidTradingPath);
+        }
+        for hop in path.windows(2) {
+            let (pool_0, pool_1, _) = Self::liquidity_pool(Self::get_pair(hop[0], hop[1]));
+            ensure!(!pool_0.is_zero() && !pool_1.is_zero(), Error::<T>::InsufficientLiquidity);
+        }
+        Ok(())
+    }
+
+    /// Returns every trading pair that currently has liquidity.
+    pub fn get_trading_pairs() -> Vec<(T::CurrencyId, T::CurrencyId)> {
+        LiquidityPool::<T>::iter()
+            .filter(|(_, (pool_0, pool_1, _))| !pool_0.is_zero() && !pool_1.is_zero())
+            .map(|(trading_pair, _)| trading_pair)
+            .collect()
+    }
+
+    /// Enumerates all acyclic paths from `supply_currency_id` to `target_currency_id` that fit in
+    /// `TradingPathLimit`.
+    ///
+    /// This walks every pool and is only meant to be called off-chain through the runtime API.
+    fn candidate_paths(supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId) -> Vec<Vec<T::CurrencyId>> {
+        let mut adjacency: BTreeMap<T::CurrencyId, Vec<T::CurrencyId>> = BTreeMap::new();
+        for (currency_id_0, currency_id_1) in Self::get_trading_pairs() {
+            adjacency.entry(currency_id_0).or_insert_with(Vec::new).push(currency_id_1);
+            adjacency.entry(currency_id_1).or_insert_with(Vec::new).push(currency_id_0);
+        }
+
+        let mut paths = Vec::new();
+        let mut path = vec![supply_currency_id];
+        if supply_currency_id != target_currency_id {
+            Self::extend_paths(&adjacency, target_currency_id, &mut path, &mut paths);
+        }
+        paths
+    }
+
+    fn extend_paths(adjacency: &BTreeMap<T::CurrencyId, Vec<T::CurrencyId>>, target_currency_id: T::CurrencyId, path: &mut Vec<T::CurrencyId>, paths: &mut Vec<Vec<T::CurrencyId>>) {
+        let last = path[path.len() - 1];
+        if last == target_currency_id {
+            paths.push(path.clone());
+            return;
+        }
+        if path.len() >= T::TradingPathLimit::get() {
+            return;
+        }
+        if let Some(neighbours) = adjacency.get(&last) {
+            for next in neighbours {
+                if !path.contains(next) {
+                    path.push(*next);
+                    Self::extend_paths(adjacency, target_currency_id, path, paths);
+                    path.pop();
+                }
+            }
+        }
+    }
+
+    /// Finds the route giving the most `target_currency_id` for exactly `supply_amount` of
+    /// `supply_currency_id`.
+    pub fn find_best_route_with_exact_supply(supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId, supply_amount: T::Balance) -> Option<SwapRoute<T::CurrencyId, T::Balance>> {
+        Self::candidate_paths(supply_currency_id, target_currency_id)
+            .into_iter()
+            .filter_map(|path| {
+                let amounts = Self::get_target_amounts(&path, supply_amount, None).ok()?;
+                Some((path, amounts))
+            })
+            .max_by_key(|(_, amounts)| amounts[amounts.len() - 1])
+            .map(|(path, amounts)| {
+                let quote = Self::build_quote(&path, amounts);
+                SwapRoute { path, quote }
+            })
+    }
+
+    /// Finds the route requiring the least `supply_currency_id` to receive exactly
+    /// `target_amount` of `target_currency_id`.
+    pub fn find_best_route_with_exact_target(supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId, target_amount: T::Balance) -> Option<SwapRoute<T::CurrencyId, T::Balance>> {
+        Self::candidate_paths(supply_currency_id, target_currency_id)
+            .into_iter()
+            .filter_map(|path| {
+                let amounts = Self::get_supply_amounts(&path, target_amount, None).ok()?;
+                Some((path, amounts))
+            })
+            .min_by_key(|(_, amounts)| amounts[0])
+            .map(|(path, amounts)| {
+                let quote = Self::build_quote(&path, amounts);
+                SwapRoute { path, quote }
+            })
+    }
+
+    /// Creates a weighted pool owned by nobody, the creator receives its initial shares.
+    #[transactional]
+    pub fn do_create_weighted_pool(who: &T::AccountId, assets: Vec<T::CurrencyId>, weights: Vec<Permill>, amounts: Vec<T::Balance>, swap_fee: Permill) -> DispatchResult {
+        let asset_count = assets.len();
+        ensure!(asset_count >= 2 && asset_count <= T::MaxWeightedPoolAssets::get() as usize, Error::<T>::InvalidWeightedPool);
+        ensure!(weights.len() == asset_count && amounts.len() == asset_count, Error::<T>::InvalidWeightedPool);
+        for (i, currency_id) in assets.iter().enumerate() {
+            ensure!(!assets[i + 1..].contains(currency_id), Error::<T>::InvalidWeightedPool);
+        }
+        ensure!(weights.iter().all(|weight| *weight >= MIN_WEIGHT), Error::<T>::InvalidWeightedPool);
+        let total_weight: u32 = weights.iter().map(|weight| weight.deconstruct()).sum();
+        ensure!(total_weight == Permill::ACCURACY, Error::<T>::InvalidWeightedPool);
+        ensure!(amounts.iter().all(|amount| !amount.is_zero()), Error::<T>::ProvidedAmountIsZero);
+        ensure!(swap_fee <= MAX_WEIGHTED_POOL_SWAP_FEE, Error::<T>::InvalidWeightedPool);
+
+        // Same convention as constant product pools: the initial share is the largest deposit
+        let initial_share = amounts.iter().copied().max().unwrap_or_else(Zero::zero);
+        let pool_id = Self::next_weighted_pool_id();
+        NextWeightedPoolId::put(pool_id.saturating_add(1));
+
+        let swap_wallet_account = Self::get_wallet_account();
+        for (currency_id, amount) in assets.iter().zip(amounts.iter()) {
+            T::Currency::transfer(*currency_id, who, &swap_wallet_account, *amount)?;
+        }
+
+        WeightedPools::<T>::insert(pool_id, WeightedPool {
+            assets: assets.clone(),
+            weights,
+            balances: amounts.clone(),
+            total_shares: initial_share,
+            swap_fee,
+        });
+        WeightedPoolHoldings::<T>::insert((who, pool_id), initial_share);
+
+        Self::deposit_event(RawEvent::WeightedPoolCreated(who.clone(), pool_id, assets));
+        Self::deposit_event(RawEvent::WeightedPoolJoined(who.clone(), pool_id, amounts, initial_share));
+        Ok(())
+    }
+
+    /// Adds liquidity in all assets, proportionally to the pool balances.
+    #[transactional]
+    pub fn do_join_weighted_pool(who: &T::AccountId, pool_id: WeightedPoolId, share_amount: T::Balance, max_amounts_in: Vec<T::Balance>) -> DispatchResult {
+        ensure!(!share_amount.is_zero(), Error::<T>::ProvidedAmountIsZero);
+        WeightedPools::<T>::try_mutate(pool_id, |maybe_pool| -> DispatchResult {
+            let pool = maybe_pool.as_mut().ok_or(Error::<T>::WeightedPoolNotFound)?;
+            ensure!(max_amounts_in.len() == pool.assets.len(), Error::<T>::InvalidWeightedPool);
+            let ratio = FixedU128::checked_from_rational(share_amount, pool.total_shares).ok_or(Error::<T>::InsufficientLiquidity)?;
+            let swap_wallet_account = Self::get_wallet_account();
+
+            let mut amounts_in: Vec<T::Balance> = Vec::with_capacity(pool.assets.len());
+            for (i, balance) in pool.balances.iter_mut().enumerate() {
+                // Rounded up in favour of the pool
+                let amount_in = ratio.saturating_mul_int(*balance).saturating_add(One::one());
+                ensure!(amount_in <= max_amounts_in[i], Error::<T>::ExcessiveSupplyAmount);
+                T::Currency::transfer(pool.assets[i], who, &swap_wallet_account, amount_in)?;
+                *balance = balance.saturating_add(amount_in);
+                amounts_in.push(amount_in);
+            }
+            pool.total_shares = pool.total_shares.saturating_add(share_amount);
+            WeightedPoolHoldings::<T>::mutate((who, pool_id), |shares| *shares = shares.saturating_add(share_amount));
+
+            Self::deposit_event(RawEvent::WeightedPoolJoined(who.clone(), pool_id, amounts_in, share_amount));
+            Ok(())
+        })
+    }
+
+    /// Removes liquidity in all assets, proportionally to the pool balances.
+    #[transactional]
+    pub fn do_exit_weighted_pool(who: &T::AccountId, pool_id: WeightedPoolId, share_amount: T::Balance, min_amounts_out: Vec<T::Balance>) -> DispatchResult {
+        ensure!(!share_amount.is_zero(), Error::<T>::ProvidedAmountIsZero);
+        ensure!(share_amount <= Self::weighted_pool_holdings((who, pool_id)), Error::<T>::LowShare);
+        WeightedPools::<T>::try_mutate(pool_id, |maybe_pool| -> DispatchResult {
+            let pool = maybe_pool.as_mut().ok_or(Error::<T>::WeightedPoolNotFound)?;
+            ensure!(min_amounts_out.len() == pool.assets.len(), Error::<T>::InvalidWeightedPool);
+            let ratio = FixedU128::checked_from_rational(share_amount, pool.total_shares).ok_or(Error::<T>::InsufficientLiquidity)?;
+            let swap_wallet_account = Self::get_wallet_account();
+
+            let mut amounts_out: Vec<T::Balance> = Vec::with_capacity(pool.assets.len());
+            for (i, balance) in pool.balances.iter_mut().enumerate() {
+                let amount_out = ratio.saturating_mul_int(*balance);
+                ensure!(amount_out >= min_amounts_out[i], Error::<T>::InsufficientTargetAmount);
+                T::Currency::transfer(pool.assets[i], &swap_wallet_account, who, amount_out)?;
+                *balance = balance.saturating_sub(amount_out);
+                amounts_out.push(amount_out);
+            }
+            pool.total_shares = pool.total_shares.saturating_sub(share_amount);
+            WeightedPoolHoldings::<T>::mutate((who, pool_id), |shares| *shares = shares.saturating_sub(share_amount));
+
+            Self::deposit_event(RawEvent::WeightedPoolExited(who.clone(), pool_id, amounts_out, share_amount));
+            Ok(())
+        })
+    }
+
+    /// Adds liquidity in a single asset, the fee applies to the part that is implicitly swapped.
+    #[transactional]
+    pub fn do_join_weighted_pool_single_asset(who: &T::AccountId, pool_id: WeightedPoolId, currency_id: T::CurrencyId, amount_in: T::Balance, min_share: T::Balance) -> DispatchResult {
+        ensure!(!amount_in.is_zero(), Error::<T>::ProvidedAmountIsZero);
+        WeightedPools::<T>::try_mutate(pool_id, |maybe_pool| -> DispatchResult {
+            let pool = maybe_pool.as_mut().ok_or(Error::<T>::WeightedPoolNotFound)?;
+            let i = pool.index_of(&currency_id).ok_or(Error::<T>::AssetNotInPool)?;
+            // An emptied pool cannot be joined with a single asset, nor can its in ratio be checked
+            ensure!(!pool.balances[i].is_zero() && !pool.total_shares.is_zero(), Error::<T>::InsufficientLiquidity);
+            ensure!(FixedU128::checked_from_rational(amount_in, pool.balances[i]).ok_or(Error::<T>::FixedU128ConversionFailed)? <= weighted_math::max_in_ratio(), Error::<T>::ExceedMaxInRatio);
+
+            let share_increment = weighted_math::calc_shares_out_given_single_in(
+                Self::to_u128(pool.balances[i]), pool.weights[i], Self::to_u128(pool.total_shares), Self::to_u128(amount_in), pool.swap_fee,
+            ).map(T::Balance::unique_saturated_from).ok_or(Error::<T>::InvalidLiquidityIncrement)?;
+            ensure!(!share_increment.is_zero(), Error::<T>::InvalidLiquidityIncrement);
+            ensure!(share_increment >= min_share, Error::<T>::InsufficientShareIncrement);
+
+            T::Currency::transfer(currency_id, who, &Self::get_wallet_account(), amount_in)?;
+            pool.balances[i] = pool.balances[i].saturating_add(amount_in);
+            pool.total_shares = pool.total_shares.saturating_add(share_increment);
+            WeightedPoolHoldings::<T>::mutate((who, pool_id), |shares| *shares = shares.saturating_add(share_increment));
+
+            let mut amounts_in = vec![Zero::zero(); pool.assets.len()];
+            amounts_in[i] = amount_in;
+            Self::deposit_event(RawEvent::WeightedPoolJoined(who.clone(), pool_id, amounts_in, share_increment));
             Ok(())
         })
     }
 
+    /// Removes liquidity in a single asset, the fee applies to the part that is implicitly swapped.
+    #[transactional]
+    pub fn do_exit_weighted_pool_single_asset(who: &T::AccountId, pool_id: WeightedPoolId, currency_id: T::CurrencyId, share_amount: T::Balance, min_amount_out: T::Balance) -> DispatchResult {
+        ensure!(!share_amount.is_zero(), Error::<T>::ProvidedAmountIsZero);
+        ensure!(share_amount <= Self::weighted_pool_holdings((who, pool_id)), Error::<T>::LowShare);
+        WeightedPools::<T>::try_mutate(pool_id, |maybe_pool| -> DispatchResult {
+            let pool = maybe_pool.as_mut().ok_or(Error::<T>::WeightedPoolNotFound)?;
+            let i = pool.index_of(&currency_id).ok_or(Error::<T>::AssetNotInPool)?;
+
+            let amount_out = weighted_math::calc_single_out_given_shares_in(
+                Self::to_u128(pool.balances[i]), pool.weights[i], Self::to_u128(pool.total_shares), Self::to_u128(share_amount), pool.swap_fee,
+            ).map(T::Balance::unique_saturated_from).ok_or(Error::<T>::InsufficientLiquidity)?;
+            ensure!(FixedU128::checked_from_rational(amount_out, pool.balances[i]).ok_or(Error::<T>::FixedU128ConversionFailed)? <= weighted_math::max_out_ratio(), Error::<T>::ExceedMaxOutRatio);
+            ensure!(!amount_out.is_zero(), Error::<T>::ZeroTargetAmount);
+            ensure!(amount_out >= min_amount_out, Error::<T>::InsufficientTargetAmount);
+
+            T::Currency::transfer(currency_id, &Self::get_wallet_account(), who, amount_out)?;
+            pool.balances[i] = pool.balances[i].saturating_sub(amount_out);
+            pool.total_shares = pool.total_shares.saturating_sub(share_amount);
+            WeightedPoolHoldings::<T>::mutate((who, pool_id), |shares| *shares = shares.saturating_sub(share_amount));
+
+            let mut amounts_out = vec![Zero::zero(); pool.assets.len()];
+            amounts_out[i] = amount_out;
+            Self::deposit_event(RawEvent::WeightedPoolExited(who.clone(), pool_id, amounts_out, share_amount));
+            Ok(())
+        })
+    }
+
+    /// Amount of `target_currency_id` received for exactly `supply_amount` of `supply_currency_id`.
+    pub fn get_weighted_pool_target_amount(pool_id: WeightedPoolId, supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId, supply_amount: T::Balance) -> sp_std::result::Result<T::Balance, Error<T>> {
+        let pool = Self::weighted_pool(pool_id).ok_or(Error::<T>::WeightedPoolNotFound)?;
+        let i = pool.index_of(&supply_currency_id).ok_or(Error::<T>::AssetNotInPool)?;
+        let o = pool.index_of(&target_currency_id).ok_or(Error::<T>::AssetNotInPool)?;
+        ensure!(i != o, Error::<T>::InvalidCurrencyId);
+        ensure!(FixedU128::checked_from_rational(supply_amount, pool.balances[i]).ok_or(Error::<T>::FixedU128ConversionFailed)? <= weighted_math::max_in_ratio(), Error::<T>::ExceedMaxInRatio);
+
+        let target_amount = weighted_math::calc_out_given_in(
+            Self::to_u128(pool.balances[i]), pool.weights[i], Self::to_u128(pool.balances[o]), pool.weights[o], Self::to_u128(supply_amount), pool.swap_fee,
+        ).map(T::Balance::unique_saturated_from).ok_or(Error::<T>::InsufficientLiquidity)?;
+        ensure!(!target_amount.is_zero(), Error::<T>::ZeroTargetAmount);
+        ensure!(FixedU128::checked_from_rational(target_amount, pool.balances[o]).ok_or(Error::<T>::FixedU128ConversionFailed)? <= weighted_math::max_out_ratio(), Error::<T>::ExceedMaxOutRatio);
+        Ok(target_amount)
+    }
+
+    /// Swaps exactly `supply_amount` between two members of a weighted pool.
+    #[transactional]
+    pub fn do_swap_weighted_pool(who: &T::AccountId, pool_id: WeightedPoolId, supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId, supply_amount: T::Balance, min_target_amount: T::Balance) -> DispatchResult {
+        let target_amount = Self::get_weighted_pool_target_amount(pool_id, supply_currency_id, target_currency_id, supply_amount)?;
+        ensure!(target_amount >= min_target_amount, Error::<T>::InsufficientTargetAmount);
+
+        let swap_wallet_account = Self::get_wallet_account();
+        T::Currency::transfer(supply_currency_id, who, &swap_wallet_account, supply_amount)?;
+        WeightedPools::<T>::try_mutate(pool_id, |maybe_pool| -> DispatchResult {
+            let pool = maybe_pool.as_mut().ok_or(Error::<T>::WeightedPoolNotFound)?;
+            let i = pool.index_of(&supply_currency_id).ok_or(Error::<T>::AssetNotInPool)?;
+            let o = pool.index_of(&target_currency_id).ok_or(Error::<T>::AssetNotInPool)?;
+            pool.balances[i] = pool.balances[i].saturating_add(supply_amount);
+            pool.balances[o] = pool.balances[o].saturating_sub(target_amount);
+            Ok(())
+        })?;
+        T::Currency::transfer(target_currency_id, &swap_wallet_account, who, target_amount)?;
+
+        Self::deposit_event(RawEvent::WeightedPoolSwap(who.clone(), pool_id, supply_currency_id, supply_amount, target_currency_id, target_amount));
+        Ok(())
+    }
+
+    /// Moves `share_amount` LP shares of the pool of `currency_id_a` and `currency_id_b` from `from` to `to`.
+    pub fn do_transfer_lp_shares(from: &T::AccountId, to: &T::AccountId, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, share_amount: T::Balance) -> DispatchResult {
+        ensure!(!share_amount.is_zero(), Error::<T>::ProvidedAmountIsZero);
+        let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+        ensure!(LiquidityPool::<T>::contains_key(trading_pair), Error::<T>::TradingPairNotAllowed);
+        // Shares cannot change hands while a flash swap borrows the reserves backing them
+        ensure!(!Self::flash_swap_locked(trading_pair), Error::<T>::PoolLocked);
+        if from == to {
+            return Ok(());
+        }
+        LiquidityPoolHoldings::<T>::try_mutate((from, trading_pair), |shares| -> DispatchResult {
+            *shares = shares.checked_sub(&share_amount).ok_or(Error::<T>::LowShare)?;
+            Ok(())
+        })?;
+        LiquidityPoolHoldings::<T>::mutate((to, trading_pair), |shares| *shares = shares.saturating_add(share_amount));
+
+        Self::deposit_event(RawEvent::LpSharesTransferred(from.clone(), to.clone(), trading_pair.0, trading_pair.1, share_amount));
+        Ok(())
+    }
+
+    /// Moves `share_amount` LP shares of a weighted pool from `from` to `to`.
+    pub fn do_transfer_weighted_pool_shares(from: &T::AccountId, to: &T::AccountId, pool_id: WeightedPoolId, share_amount: T::Balance) -> DispatchResult {
+        ensure!(!share_amount.is_zero(), Error::<T>::ProvidedAmountIsZero);
+        ensure!(WeightedPools::<T>::contains_key(pool_id), Error::<T>::WeightedPoolNotFound);
+        if from == to {
+            return Ok(());
+        }
+        WeightedPoolHoldings::<T>::try_mutate((from, pool_id), |shares| -> DispatchResult {
+            *shares = shares.checked_sub(&share_amount).ok_or(Error::<T>::LowShare)?;
+            Ok(())
+        })?;
+        WeightedPoolHoldings::<T>::mutate((to, pool_id), |shares| *shares = shares.saturating_add(share_amount));
+
+        Self::deposit_event(RawEvent::WeightedPoolSharesTransferred(from.clone(), to.clone(), pool_id, share_amount));
+        Ok(())
+    }
+
+    /// Makes `to` the owner of a concentrated liquidity position owned by `from`.
+    pub fn do_transfer_position(from: &T::AccountId, to: &T::AccountId, position_id: PositionId) -> DispatchResult {
+        Positions::<T>::try_mutate(position_id, |maybe_position| -> DispatchResult {
+            let position = maybe_position.as_mut().ok_or(Error::<T>::PositionNotFound)?;
+            ensure!(position.owner == *from, Error::<T>::NotPositionOwner);
+            position.owner = to.clone();
+            Ok(())
+        })?;
+
+        Self::deposit_event(RawEvent::PositionTransferred(from.clone(), to.clone(), position_id));
+        Ok(())
+    }
+
+    /// Creates a concentrated liquidity pool, `sqrt_price` is the square root of the price of
+    /// `currency_id_a` in `currency_id_b`.
+    pub fn do_create_concentrated_pool(who: &T::AccountId, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, fee: Permill, tick_spacing: u32, sqrt_price: u128) -> DispatchResult {
+        ensure!(currency_id_a != currency_id_b, Error::<T>::InvalidCurrencyId);
+        ensure!(fee <= MAX_CONCENTRATED_POOL_FEE, Error::<T>::InvalidConcentratedPool);
+        ensure!(tick_spacing > 0 && tick_spacing <= MAX_TICK_SPACING, Error::<T>::InvalidConcentratedPool);
+        let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+        let sqrt_price = if currency_id_a == trading_pair.0 {
+            sqrt_price
+        } else {
+            ensure!(sqrt_price != 0, Error::<T>::InvalidSqrtPrice);
+            ((U256::one() << 128) / U256::from(sqrt_price)).try_into().map_err(|_| Error::<T>::InvalidSqrtPrice)?
+        };
+        ensure!(sqrt_price >= MIN_SQRT_PRICE && sqrt_price < MAX_SQRT_PRICE, Error::<T>::InvalidSqrtPrice);
+        let tick = concentrated_math::tick_at_sqrt_price(sqrt_price).ok_or(Error::<T>::InvalidSqrtPrice)?;
+
+        let pool_id = Self::next_concentrated_pool_id();
+        NextConcentratedPoolId::put(pool_id.saturating_add(1));
+        ConcentratedPools::<T>::insert(pool_id, ConcentratedPool {
+            currency_id_0: trading_pair.0,
+            currency_id_1: trading_pair.1,
+            fee,
+            tick_spacing,
+            sqrt_price,
+            tick,
+            liquidity: 0,
+            fee_growth_global_0: 0,
+            fee_growth_global_1: 0,
+        });
+
+        Self::deposit_event(RawEvent::ConcentratedPoolCreated(who.clone(), pool_id, trading_pair.0, trading_pair.1, fee, tick_spacing));
+        Ok(())
+    }
+
+    /// Opens a position providing `liquidity` between `tick_lower` and `tick_upper`.
+    #[transactional]
+    pub fn do_mint_position(who: &T::AccountId, pool_id: ConcentratedPoolId, tick_lower: i32, tick_upper: i32, liquidity: u128, max_amount_0: T::Balance, max_amount_1: T::Balance) -> DispatchResult {
+        ensure!(liquidity > 0, Error::<T>::InvalidLiquidityIncrement);
+        let liquidity_delta: i128 = liquidity.try_into().map_err(|_| Error::<T>::InvalidLiquidityIncrement)?;
+        let mut pool = Self::concentrated_pool(pool_id).ok_or(Error::<T>::ConcentratedPoolNotFound)?;
+        ensure!(tick_lower < tick_upper && tick_lower >= MIN_TICK && tick_upper <= MAX_TICK, Error::<T>::InvalidTickRange);
+        let tick_spacing = pool.tick_spacing as i32;
+        ensure!(tick_lower % tick_spacing == 0 && tick_upper % tick_spacing == 0, Error::<T>::InvalidTickRange);
+
+        let mut position = Position {
+            owner: who.clone(),
+            pool_id,
+            tick_lower,
+            tick_upper,
+            liquidity: 0,
+            fee_growth_inside_0_last: 0,
+            fee_growth_inside_1_last: 0,
+        };
+        let (_, (amount_0, amount_1)) = Self::update_position(&mut pool, &mut position, liquidity_delta)?;
+        let amount_0 = T::Balance::unique_saturated_from(amount_0);
+        let amount_1 = T::Balance::unique_saturated_from(amount_1);
+        ensure!(amount_0 <= max_amount_0 && amount_1 <= max_amount_1, Error::<T>::ExcessiveSupplyAmount);
+
+        let swap_wallet_account = Self::get_wallet_account();
+        T::Currency::transfer(pool.currency_id_0, who, &swap_wallet_account, amount_0)?;
+        T::Currency::transfer(pool.currency_id_1, who, &swap_wallet_account, amount_1)?;
+
+        let position_id = Self::next_position_id();
+        NextPositionId::put(position_id.saturating_add(1));
+        Positions::<T>::insert(position_id, position);
+        ConcentratedPools::<T>::insert(pool_id, pool);
+
+        Self::deposit_event(RawEvent::PositionMinted(who.clone(), position_id, pool_id, tick_lower, tick_upper, liquidity, amount_0, amount_1));
+        Ok(())
+    }
+
+    /// Removes `liquidity` from a position and pays out the fees it earned, the position is
+    /// deleted once empty.
+    #[transactional]
+    pub fn do_burn_position(who: &T::AccountId, position_id: PositionId, liquidity: u128, min_amount_0: T::Balance, min_amount_1: T::Balance) -> DispatchResult {
+        let mut position = Self::position(position_id).ok_or(Error::<T>::PositionNotFound)?;
+        ensure!(position.owner == *who, Error::<T>::NotPositionOwner);
+        ensure!(liquidity > 0 && liquidity <= position.liquidity, Error::<T>::LowShare);
+        let liquidity_delta: i128 = liquidity.try_into().map_err(|_| Error::<T>::LowShare)?;
+        let mut pool = Self::concentrated_pool(position.pool_id).ok_or(Error::<T>::ConcentratedPoolNotFound)?;
+
+        let ((fee_0, fee_1), (amount_0, amount_1)) = Self::update_position(&mut pool, &mut position, -liquidity_delta)?;
+        let amount_0 = T::Balance::unique_saturated_from(amount_0);
+        let amount_1 = T::Balance::unique_saturated_from(amount_1);
+        ensure!(amount_0 >= min_amount_0 && amount_1 >= min_amount_1, Error::<T>::InsufficientTargetAmount);
+        let fee_0 = T::Balance::unique_saturated_from(fee_0);
+        let fee_1 = T::Balance::unique_saturated_from(fee_1);
+
+        let swap_wallet_account = Self::get_wallet_account();
+        T::Currency::transfer(pool.currency_id_0, &swap_wallet_account, who, amount_0.saturating_add(fee_0))?;
+        T::Currency::transfer(pool.currency_id_1, &swap_wallet_account, who, amount_1.saturating_add(fee_1))?;
+
+        ConcentratedPools::<T>::insert(position.pool_id, pool);
+        if position.liquidity == 0 {
+            Positions::<T>::remove(position_id);
+        } else {
+            Positions::<T>::insert(position_id, position);
+        }
+
+        Self::deposit_event(RawEvent::PositionBurned(who.clone(), position_id, liquidity, amount_0, amount_1));
+        if !fee_0.is_zero() || !fee_1.is_zero() {
+            Self::deposit_event(RawEvent::PositionFeesCollected(who.clone(), position_id, fee_0, fee_1));
+        }
+        Ok(())
+    }
+
+    /// Pays out the fees earned by a position since they were last paid out.
+    #[transactional]
+    pub fn do_collect_position_fees(who: &T::AccountId, position_id: PositionId) -> DispatchResult {
+        let mut position = Self::position(position_id).ok_or(Error::<T>::PositionNotFound)?;
+        ensure!(position.owner == *who, Error::<T>::NotPositionOwner);
+        let mut pool = Self::concentrated_pool(position.pool_id).ok_or(Error::<T>::ConcentratedPoolNotFound)?;
+
+        let ((fee_0, fee_1), _) = Self::update_position(&mut pool, &mut position, 0)?;
+        let fee_0 = T::Balance::unique_saturated_from(fee_0);
+        let fee_1 = T::Balance::unique_saturated_from(fee_1);
+
+        let swap_wallet_account = Self::get_wallet_account();
+        T::Currency::transfer(pool.currency_id_0, &swap_wallet_account, who, fee_0)?;
+        T::Currency::transfer(pool.currency_id_1, &swap_wallet_account, who, fee_1)?;
+        Positions::<T>::insert(position_id, position);
+
+        Self::deposit_event(RawEvent::PositionFeesCollected(who.clone(), position_id, fee_0, fee_1));
+        Ok(())
+    }
+
+    /// Applies `liquidity_delta` to a position and its bounds, returning the fees it earned
+    /// since the last update and the amounts of both assets backing `liquidity_delta`, rounded
+    /// in favour of the pool.
+    fn update_position(pool: &mut ConcentratedPool<T::CurrencyId>, position: &mut Position<T::AccountId, ConcentratedPoolId>, liquidity_delta: i128)
+        -> sp_std::result::Result<((u128, u128), (u128, u128)), DispatchError>
+    {
+        let pool_id = position.pool_id;
+        let (clear_lower, clear_upper) = if liquidity_delta != 0 {
+            (
+                Self::update_tick(pool_id, pool, position.tick_lower, liquidity_delta, false)?,
+                Self::update_tick(pool_id, pool, position.tick_upper, liquidity_delta, true)?,
+            )
+        } else {
+            (false, false)
+        };
+
+        let (fee_growth_inside_0, fee_growth_inside_1) = Self::fee_growth_inside(pool_id, pool, position.tick_lower, position.tick_upper);
+        let fee_0 = concentrated_math::fees_owed(fee_growth_inside_0.wrapping_sub(position.fee_growth_inside_0_last), position.liquidity);
+        let fee_1 = concentrated_math::fees_owed(fee_growth_inside_1.wrapping_sub(position.fee_growth_inside_1_last), position.liquidity);
+        position.fee_growth_inside_0_last = fee_growth_inside_0;
+        position.fee_growth_inside_1_last = fee_growth_inside_1;
+
+        if clear_lower {
+            Self::clear_tick(pool_id, position.tick_lower);
+        }
+        if clear_upper {
+            Self::clear_tick(pool_id, position.tick_upper);
+        }
+
+        position.liquidity = Self::apply_liquidity_delta(position.liquidity, liquidity_delta)?;
+        if pool.tick >= position.tick_lower && pool.tick < position.tick_upper {
+            pool.liquidity = Self::apply_liquidity_delta(pool.liquidity, liquidity_delta)?;
+        }
+
+        let sqrt_price_lower = concentrated_math::sqrt_price_at_tick(position.tick_lower).ok_or(Error::<T>::InvalidTickRange)?;
+        let sqrt_price_upper = concentrated_math::sqrt_price_at_tick(position.tick_upper).ok_or(Error::<T>::InvalidTickRange)?;
+        let liquidity = liquidity_delta.checked_abs().ok_or(Error::<T>::InvalidLiquidityIncrement)? as u128;
+        let amounts = concentrated_math::amounts_for_liquidity(pool.sqrt_price, sqrt_price_lower, sqrt_price_upper, liquidity, liquidity_delta > 0)
+            .ok_or(Error::<T>::InsufficientLiquidity)?;
+        Ok(((fee_0, fee_1), amounts))
+    }
+
+    /// Adds `liquidity_delta` to the liquidity referencing `tick`, initializing it if needed.
+    /// Returns whether no position references the tick anymore.
+    fn update_tick(pool_id: ConcentratedPoolId, pool: &ConcentratedPool<T::CurrencyId>, tick: i32, liquidity_delta: i128, upper: bool) -> sp_std::result::Result<bool, DispatchError> {
+        let mut info = Self::tick(pool_id, tick).unwrap_or_default();
+        if info.liquidity_gross == 0 {
+            // By convention all fees so far were earned below the tick
+            if tick <= pool.tick {
+                info.fee_growth_outside_0 = pool.fee_growth_global_0;
+                info.fee_growth_outside_1 = pool.fee_growth_global_1;
+            }
+            InitializedTicks::try_mutate(pool_id, |ticks| -> DispatchResult {
+                ensure!(ticks.len() < T::MaxTicksPerPool::get() as usize, Error::<T>::TooManyTicks);
+                if let Err(index) = ticks.binary_search(&tick) {
+                    ticks.insert(index, tick);
+                }
+                Ok(())
+            })?;
+        }
+        info.liquidity_gross = Self::apply_liquidity_delta(info.liquidity_gross, liquidity_delta)?;
+        info.liquidity_net = if upper {
+            info.liquidity_net.checked_sub(liquidity_delta)
+        } else {
+            info.liquidity_net.checked_add(liquidity_delta)
+        }.ok_or(Error::<T>::InvalidLiquidityIncrement)?;
+        Ticks::insert(pool_id, tick, info);
+        Ok(info.liquidity_gross == 0)
+    }
+
+    fn clear_tick(pool_id: ConcentratedPoolId, tick: i32) {
+        Ticks::remove(pool_id, tick);
+        InitializedTicks::mutate(pool_id, |ticks| {
+            if let Ok(index) = ticks.binary_search(&tick) {
+                ticks.remove(index);
+            }
+        });
+    }
+
+    /// Fee growth per unit of liquidity between `tick_lower` and `tick_upper`.
+    fn fee_growth_inside(pool_id: ConcentratedPoolId, pool: &ConcentratedPool<T::CurrencyId>, tick_lower: i32, tick_upper: i32) -> (u128, u128) {
+        let lower = Self::tick(pool_id, tick_lower).unwrap_or_default();
+        let upper = Self::tick(pool_id, tick_upper).unwrap_or_default();
+        let (global_0, global_1) = (pool.fee_growth_global_0, pool.fee_growth_global_1);
+        let (below_0, below_1) = if pool.tick >= tick_lower {
+            (lower.fee_growth_outside_0, lower.fee_growth_outside_1)
+        } else {
+            (global_0.wrapping_sub(lower.fee_growth_outside_0), global_1.wrapping_sub(lower.fee_growth_outside_1))
+        };
+        let (above_0, above_1) = if pool.tick < tick_upper {
+            (upper.fee_growth_outside_0, upper.fee_growth_outside_1)
+        } else {
+            (global_0.wrapping_sub(upper.fee_growth_outside_0), global_1.wrapping_sub(upper.fee_growth_outside_1))
+        };
+        (
+            global_0.wrapping_sub(below_0).wrapping_sub(above_0),
+            global_1.wrapping_sub(below_1).wrapping_sub(above_1),
+        )
+    }
+
+    fn apply_liquidity_delta(liquidity: u128, liquidity_delta: i128) -> sp_std::result::Result<u128, Error<T>> {
+        if liquidity_delta >= 0 {
+            liquidity.checked_add(liquidity_delta as u128)
+        } else {
+            liquidity_delta.checked_abs().and_then(|delta| liquidity.checked_sub(delta as u128))
+        }.ok_or(Error::<T>::InsufficientLiquidity)
+    }
+
+    /// Swaps `supply_amount` in a concentrated liquidity pool, crossing initialized ticks until
+    /// the whole amount is swapped or the price reaches `sqrt_price_limit`. Only the amount
+    /// actually swapped is taken from `who`.
+    #[transactional]
+    pub fn do_swap_concentrated_pool(who: &T::AccountId, pool_id: ConcentratedPoolId, supply_currency_id: T::CurrencyId, supply_amount: T::Balance, min_target_amount: T::Balance, sqrt_price_limit: Option<u128>) -> DispatchResult {
+        ensure!(!supply_amount.is_zero(), Error::<T>::ZeroSupplyAmount);
+        let mut pool = Self::concentrated_pool(pool_id).ok_or(Error::<T>::ConcentratedPoolNotFound)?;
+        let zero_for_one = if supply_currency_id == pool.currency_id_0 {
+            true
+        } else {
+            ensure!(supply_currency_id == pool.currency_id_1, Error::<T>::AssetNotInPool);
+            false
+        };
+        let sqrt_price_limit = if zero_for_one {
+            let limit = sqrt_price_limit.unwrap_or(MIN_SQRT_PRICE + 1);
+            ensure!(limit < pool.sqrt_price && limit > MIN_SQRT_PRICE, Error::<T>::InvalidSqrtPrice);
+            limit
+        } else {
+            let limit = sqrt_price_limit.unwrap_or(MAX_SQRT_PRICE - 1);
+            ensure!(limit > pool.sqrt_price && limit < MAX_SQRT_PRICE, Error::<T>::InvalidSqrtPrice);
+            limit
+        };
+
+        let initialized_ticks = Self::initialized_ticks(pool_id);
+        let mut amount_remaining = Self::to_u128(supply_amount);
+        let mut amount_out: u128 = 0;
+        while amount_remaining > 0 && pool.sqrt_price != sqrt_price_limit {
+            let next_tick = if zero_for_one {
+                // greatest initialized tick at or below the current tick
+                match initialized_ticks.binary_search(&pool.tick) {
+                    Ok(index) => Some(initialized_ticks[index]),
+                    Err(0) => None,
+                    Err(index) => Some(initialized_ticks[index - 1]),
+                }.unwrap_or(MIN_TICK)
+            } else {
+                // smallest initialized tick above the current tick
+                let index = match initialized_ticks.binary_search(&pool.tick) {
+                    Ok(index) => index + 1,
+                    Err(index) => index,
+                };
+                initialized_ticks.get(index).copied().unwrap_or(MAX_TICK)
+            };
+            let sqrt_price_next_tick = concentrated_math::sqrt_price_at_tick(next_tick).ok_or(Error::<T>::InvalidTickRange)?;
+            let sqrt_price_target = if zero_for_one {
+                sp_std::cmp::max(sqrt_price_next_tick, sqrt_price_limit)
+            } else {
+                sp_std::cmp::min(sqrt_price_next_tick, sqrt_price_limit)
+            };
+
+            let step = concentrated_math::compute_swap_step(pool.sqrt_price, sqrt_price_target, pool.liquidity, amount_remaining, pool.fee)
+                .ok_or(Error::<T>::InsufficientLiquidity)?;
+            amount_remaining = amount_remaining.saturating_sub(step.amount_in.saturating_add(step.fee_amount));
+            amount_out = amount_out.saturating_add(step.amount_out);
+            let fee_growth = concentrated_math::fee_growth(step.fee_amount, pool.liquidity);
+            if zero_for_one {
+                pool.fee_growth_global_0 = pool.fee_growth_global_0.wrapping_add(fee_growth);
+            } else {
+                pool.fee_growth_global_1 = pool.fee_growth_global_1.wrapping_add(fee_growth);
+            }
+            pool.sqrt_price = step.sqrt_price_next;
+
+            if step.sqrt_price_next == sqrt_price_next_tick {
+                if let Some(mut info) = Self::tick(pool_id, next_tick) {
+                    info.fee_growth_outside_0 = pool.fee_growth_global_0.wrapping_sub(info.fee_growth_outside_0);
+                    info.fee_growth_outside_1 = pool.fee_growth_global_1.wrapping_sub(info.fee_growth_outside_1);
+                    Ticks::insert(pool_id, next_tick, info);
+                    let liquidity_delta = if zero_for_one {
+                        info.liquidity_net.checked_neg().ok_or(Error::<T>::InsufficientLiquidity)?
+                    } else {
+                        info.liquidity_net
+                    };
+                    pool.liquidity = Self::apply_liquidity_delta(pool.liquidity, liquidity_delta)?;
+                }
+                pool.tick = if zero_for_one { next_tick - 1 } else { next_tick };
+            } else {
+                pool.tick = concentrated_math::tick_at_sqrt_price(pool.sqrt_price).ok_or(Error::<T>::InvalidSqrtPrice)?;
+            }
+        }
+
+        let supply_amount = supply_amount.saturating_sub(T::Balance::unique_saturated_from(amount_remaining));
+        let target_amount = T::Balance::unique_saturated_from(amount_out);
+        ensure!(!target_amount.is_zero(), Error::<T>::ZeroTargetAmount);
+        ensure!(target_amount >= min_target_amount, Error::<T>::InsufficientTargetAmount);
+        let target_currency_id = if zero_for_one { pool.currency_id_1 } else { pool.currency_id_0 };
+
+        let swap_wallet_account = Self::get_wallet_account();
+        T::Currency::transfer(supply_currency_id, who, &swap_wallet_account, supply_amount)?;
+        T::Currency::transfer(target_currency_id, &swap_wallet_account, who, target_amount)?;
+        ConcentratedPools::<T>::insert(pool_id, pool);
+
+        Self::deposit_event(RawEvent::ConcentratedPoolSwap(who.clone(), pool_id, supply_currency_id, supply_amount, target_currency_id, target_amount));
+        Ok(())
+    }
+
+    /// Lends `amount_a_out` and `amount_b_out` to `who`, runs `callback` and takes `amount_a_in`
+    /// and `amount_b_in` back from `who`, which must be enough for the invariant to hold after
+    /// charging the swap fee on them. The pool cannot be traded against while the callback runs.
+    ///
+    /// The amounts paid back are explicit rather than measured on the pool account, which holds
+    /// the assets of every pool.
+    #[transactional]
+    pub fn do_flash_swap<F>(who: &T::AccountId, currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId, amount_a_out: T::Balance, amount_b_out: T::Balance,
+                            amount_a_in: T::Balance, amount_b_in: T::Balance, callback: F) -> DispatchResult
+        where F: FnOnce(&T::AccountId) -> DispatchResult
+    {
+        ensure!(currency_id_a != currency_id_b, Error::<T>::InvalidCurrencyId);
+        ensure!(!amount_a_out.is_zero() || !amount_b_out.is_zero(), Error::<T>::ProvidedAmountIsZero);
+        let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+        ensure!(LiquidityPool::<T>::contains_key(trading_pair), Error::<T>::TradingPairNotAllowed);
+        ensure!(!Self::flash_swap_locked(trading_pair), Error::<T>::PoolLocked);
+
+        let (amount_0_out, amount_1_out, amount_0_in, amount_1_in) = if currency_id_a == trading_pair.0 {
+            (amount_a_out, amount_b_out, amount_a_in, amount_b_in)
+        } else {
+            (amount_b_out, amount_a_out, amount_b_in, amount_a_in)
+        };
+        let (pool_0, pool_1, _) = Self::liquidity_pool(trading_pair);
+        ensure!(amount_0_out < pool_0 && amount_1_out < pool_1, Error::<T>::InsufficientLiquidity);
+        let new_pool_0 = pool_0.saturating_sub(amount_0_out).saturating_add(amount_0_in);
+        let new_pool_1 = pool_1.saturating_sub(amount_1_out).saturating_add(amount_1_in);
+        ensure!(
+            Self::is_flash_swap_repaid(trading_pair, (pool_0, pool_1), (new_pool_0, new_pool_1), (amount_0_in, amount_1_in)),
+            Error::<T>::FlashSwapNotRepaid
+        );
+        Self::update_price_accumulator(trading_pair, <frame_system::Module<T>>::block_number());
+
+        let swap_wallet_account = Self::get_wallet_account();
+        if !amount_0_out.is_zero() {
+            T::Currency::transfer(trading_pair.0, &swap_wallet_account, who, amount_0_out)?;
+        }
+        if !amount_1_out.is_zero() {
+            T::Currency::transfer(trading_pair.1, &swap_wallet_account, who, amount_1_out)?;
+        }
+
+        FlashSwapLocks::<T>::insert(trading_pair, true);
+        callback(who)?;
+        FlashSwapLocks::<T>::remove(trading_pair);
+
+        if !amount_0_in.is_zero() {
+            T::Currency::transfer(trading_pair.0, who, &swap_wallet_account, amount_0_in)?;
+        }
+        if !amount_1_in.is_zero() {
+            T::Currency::transfer(trading_pair.1, who, &swap_wallet_account, amount_1_in)?;
+        }
+
+        LiquidityPool::<T>::mutate(trading_pair, |(pool_0, pool_1, _pool_shares)| {
+            *pool_0 = new_pool_0;
+            *pool_1 = new_pool_1;
+        });
+
+        Self::deposit_event(RawEvent::FlashSwap(who.clone(), trading_pair.0, amount_0_out, amount_0_in, trading_pair.1, amount_1_out, amount_1_in));
+        Ok(())
+    }
+
+    /// Whether the pool invariant, evaluated on the new reserves minus the swap fee on the amounts
+    /// paid back, is at least the invariant before the flash swap.
+    fn is_flash_swap_repaid(trading_pair: (T::CurrencyId, T::CurrencyId), pools: (T::Balance, T::Balance), new_pools: (T::Balance, T::Balance), amounts_in: (T::Balance, T::Balance)) -> bool {
+        let fee = Self::swap_fee();
+        let adjusted_pool_0 = new_pools.0.saturating_sub(fee.mul_ceil(amounts_in.0));
+        let adjusted_pool_1 = new_pools.1.saturating_sub(fee.mul_ceil(amounts_in.1));
+        match Self::current_amplification(trading_pair) {
+            Some(amplification) => {
+                let d = stable_swap::get_d(Self::to_u128(pools.0), Self::to_u128(pools.1), amplification);
+                let new_d = stable_swap::get_d(Self::to_u128(adjusted_pool_0), Self::to_u128(adjusted_pool_1), amplification);
+                match (d, new_d) {
+                    (Some(d), Some(new_d)) => new_d >= d,
+                    _ => false,
+                }
+            }
+            None => {
+                Self::to_u256(adjusted_pool_0).saturating_mul(Self::to_u256(adjusted_pool_1))
+                    >= Self::to_u256(pools.0).saturating_mul(Self::to_u256(pools.1))
+            }
+        }
+    }
+
     // TODO: Define this for AssetID
-    fn get_pair(currency_id_a: T::Hash, currency_id_b: T::Hash) -> (T::Hash, T::Hash) {
+    fn get_pair(currency_id_a: T::CurrencyId, currency_id_b: T::CurrencyId) -> (T::CurrencyId, T::CurrencyId) {
         if currency_id_a > currency_id_b {
             (currency_id_a, currency_id_b)
         } else {
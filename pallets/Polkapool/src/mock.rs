@@ -0,0 +1,176 @@
+use crate as polkapool;
+use frame_support::parameter_types;
+use frame_system::EnsureRoot;
+use orml_traits::parameter_type_with_key;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+pub type AccountId = u64;
+pub type Balance = u128;
+pub type Amount = i128;
+pub type CurrencyId = H256;
+pub type BlockNumber = u64;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const CHARLIE: AccountId = 3;
+/// Receiver of the protocol fee.
+pub const FEE_ACCOUNT: AccountId = 99;
+
+// Pairs are stored with the larger asset first, so with these assets `(BTC, DOT)`,
+// `(DOT, USDT)` and `(BTC, USDT)` are the stored orderings.
+pub const USDT: CurrencyId = H256([1u8; 32]);
+pub const DOT: CurrencyId = H256([2u8; 32]);
+pub const BTC: CurrencyId = H256([3u8; 32]);
+pub const ETH: CurrencyId = H256([4u8; 32]);
+
+/// Balance of each asset given to `ALICE` and `BOB` at genesis.
+pub const INITIAL_BALANCE: Balance = 1_000_000_000_000_000_000;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
+		Tokens: orml_tokens::{Module, Call, Storage, Event<T>, Config<T>},
+		Polkapool: polkapool::{Module, Call, Storage, Event<T>},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const SS58Prefix: u8 = 42;
+}
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<Balance>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = SS58Prefix;
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: Balance = 1;
+	pub const MaxLocks: u32 = 50;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = MaxLocks;
+	type Balance = Balance;
+	type Event = Event;
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+parameter_type_with_key! {
+	pub ExistentialDeposits: |_currency_id: CurrencyId| -> Balance {
+		0
+	};
+}
+
+impl orml_tokens::Config for Test {
+	type Event = Event;
+	type Balance = Balance;
+	type Amount = Amount;
+	type CurrencyId = CurrencyId;
+	type WeightInfo = ();
+	type ExistentialDeposits = ExistentialDeposits;
+	type OnDust = ();
+}
+
+parameter_types! {
+	pub const TradingPathLimit: usize = 3;
+	pub const TwapObservationPeriod: u32 = 10;
+	pub const MaxAmplification: u32 = 1_000_000;
+	pub const MinAmplificationRampDuration: BlockNumber = 10;
+	pub const MaxWeightedPoolAssets: u32 = 4;
+	pub const MaxTicksPerPool: u32 = 4;
+	pub const ProtocolFeeAccount: AccountId = FEE_ACCOUNT;
+}
+
+impl polkapool::Config for Test {
+	type Event = Event;
+	type TradingPathLimit = TradingPathLimit;
+	type Balance = Balance;
+	type TwapObservationPeriod = TwapObservationPeriod;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+	type MaxAmplification = MaxAmplification;
+	type MinAmplificationRampDuration = MinAmplificationRampDuration;
+	type MaxWeightedPoolAssets = MaxWeightedPoolAssets;
+	type MaxTicksPerPool = MaxTicksPerPool;
+	type CurrencyId = CurrencyId;
+	type Currency = Tokens;
+	type ProtocolFeeAccount = ProtocolFeeAccount;
+	type Call = Call;
+}
+
+pub struct ExtBuilder {
+	endowed_accounts: Vec<(AccountId, CurrencyId, Balance)>,
+}
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		let mut endowed_accounts = Vec::new();
+		for who in [ALICE, BOB].iter() {
+			for currency_id in [USDT, DOT, BTC, ETH].iter() {
+				endowed_accounts.push((*who, *currency_id, INITIAL_BALANCE));
+			}
+		}
+		ExtBuilder { endowed_accounts }
+	}
+}
+
+impl ExtBuilder {
+	// Build genesis storage according to the mock runtime.
+	pub fn build(self) -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+		pallet_balances::GenesisConfig::<Test> {
+			balances: vec![(ALICE, INITIAL_BALANCE), (BOB, INITIAL_BALANCE)],
+		}.assimilate_storage(&mut t).unwrap();
+
+		orml_tokens::GenesisConfig::<Test> {
+			endowed_accounts: self.endowed_accounts,
+		}.assimilate_storage(&mut t).unwrap();
+
+		let mut ext = sp_io::TestExternalities::new(t);
+		// Events are not recorded in the genesis block
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+}
+
+/// Moves to block `n`, the pallet has no hooks.
+pub fn run_to_block(n: BlockNumber) {
+	System::set_block_number(n);
+}
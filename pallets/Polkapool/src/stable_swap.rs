@@ -0,0 +1,112 @@
+//! StableSwap (Curve) invariant for two-asset pools.
+//!
+//! `A * n^n * (x + y) + D = A * D * n^n + D^(n + 1) / (n^n * x * y)` with `n = 2`.
+//! Both assets are expected to use the same precision.
+
+use frame_support::sp_std::convert::TryInto;
+use sp_arithmetic::{FixedPointNumber, FixedU128};
+use sp_arithmetic::traits::{CheckedAdd, CheckedDiv, CheckedMul};
+use sp_core::U256;
+
+/// Number of assets in a pool.
+const N_COINS: u128 = 2;
+/// Newton's method iterations before giving up.
+const MAX_ITERATIONS: usize = 255;
+
+fn has_converged(current: U256, previous: U256) -> bool {
+    if current > previous {
+        current - previous <= U256::one()
+    } else {
+        previous - current <= U256::one()
+    }
+}
+
+/// Computes the invariant `D` of a pool holding `x` and `y`.
+pub fn get_d(x: u128, y: u128, amplification: u128) -> Option<U256> {
+    let n = U256::from(N_COINS);
+    let (x, y) = (U256::from(x), U256::from(y));
+    let sum = x.checked_add(y)?;
+    if sum.is_zero() {
+        return Some(U256::zero());
+    }
+    if x.is_zero() || y.is_zero() {
+        return None;
+    }
+    let ann = U256::from(amplification).checked_mul(n)?;
+
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        let mut d_p = d;
+        d_p = d_p.checked_mul(d)?.checked_div(x.checked_mul(n)?)?;
+        d_p = d_p.checked_mul(d)?.checked_div(y.checked_mul(n)?)?;
+        let d_prev = d;
+        let numerator = ann.checked_mul(sum)?.checked_add(d_p.checked_mul(n)?)?.checked_mul(d)?;
+        let denominator = ann.checked_sub(U256::one())?.checked_mul(d)?
+            .checked_add(n.checked_add(U256::one())?.checked_mul(d_p)?)?;
+        d = numerator.checked_div(denominator)?;
+        if has_converged(d, d_prev) {
+            return Some(d);
+        }
+    }
+    None
+}
+
+/// Computes the balance of one asset such that the pool keeps invariant `d` when the other
+/// asset's balance is `x`.
+pub fn get_y(x: U256, d: U256, amplification: u128) -> Option<U256> {
+    let n = U256::from(N_COINS);
+    if x.is_zero() {
+        return None;
+    }
+    let ann = U256::from(amplification).checked_mul(n)?;
+
+    let mut c = d.checked_mul(d)?.checked_div(x.checked_mul(n)?)?;
+    c = c.checked_mul(d)?.checked_div(ann.checked_mul(n)?)?;
+    let b = x.checked_add(d.checked_div(ann)?)?;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        let numerator = y.checked_mul(y)?.checked_add(c)?;
+        let denominator = y.checked_mul(n)?.checked_add(b)?.checked_sub(d)?;
+        y = numerator.checked_div(denominator)?;
+        if has_converged(y, y_prev) {
+            return Some(y);
+        }
+    }
+    None
+}
+
+/// Amount of the target asset received for `supply_amount` (fee already deducted).
+pub fn get_target_amount(supply_pool: u128, target_pool: u128, supply_amount: u128, amplification: u128) -> Option<u128> {
+    let d = get_d(supply_pool, target_pool, amplification)?;
+    let new_supply_pool = U256::from(supply_pool).checked_add(U256::from(supply_amount))?;
+    let new_target_pool = get_y(new_supply_pool, d, amplification)?;
+    // Round against the trader
+    let target_amount = U256::from(target_pool).checked_sub(new_target_pool)?.checked_sub(U256::one())?;
+    target_amount.try_into().ok()
+}
+
+/// Amount of the supply asset (before fee) that has to reach the pool to receive `target_amount`.
+pub fn get_supply_amount(supply_pool: u128, target_pool: u128, target_amount: u128, amplification: u128) -> Option<u128> {
+    let d = get_d(supply_pool, target_pool, amplification)?;
+    let new_target_pool = U256::from(target_pool).checked_sub(U256::from(target_amount))?;
+    let new_supply_pool = get_y(new_target_pool, d, amplification)?;
+    // Round against the trader
+    let supply_amount = new_supply_pool.checked_sub(U256::from(supply_pool))?.checked_add(U256::one())?;
+    supply_amount.try_into().ok()
+}
+
+/// Marginal price of the supply asset quoted in the target asset.
+///
+/// `get_d` and `get_y` use `A * n` where the invariant above has `A * n^n`, so with
+/// `X = x / D` and `Y = y / D` the price is `(8A X²Y² + Y) / (8A X²Y² + X)`, which tends to
+/// `y / x` when `A` goes to zero and to `1` when `A` goes to infinity.
+pub fn spot_price(supply_pool: u128, target_pool: u128, amplification: u128) -> Option<FixedU128> {
+    let d: u128 = get_d(supply_pool, target_pool, amplification)?.try_into().ok()?;
+    let x = FixedU128::checked_from_rational(supply_pool, d)?;
+    let y = FixedU128::checked_from_rational(target_pool, d)?;
+    let a = FixedU128::saturating_from_integer(amplification.saturating_mul(8));
+    let term = a.checked_mul(&x)?.checked_mul(&x)?.checked_mul(&y)?.checked_mul(&y)?;
+    term.checked_add(&y)?.checked_div(&term.checked_add(&x)?)
+}
@@ -0,0 +1,1852 @@
+//! Unit tests of every extrinsic and error of the pallet, followed by property tests of the
+//! constant product pools.
+
+use crate::{Error, RawEvent, mock::*, stable_swap};
+use crate::types::{AmplificationRamp, PoolKind};
+use frame_support::{assert_noop, assert_ok};
+use orml_traits::MultiCurrency;
+use proptest::prelude::*;
+use sp_arithmetic::{FixedPointNumber, FixedU128};
+use sp_core::U256;
+use sp_runtime::{DispatchError, Permill};
+use sp_runtime::traits::BadOrigin;
+
+/// Initial reserves of the pools created by the tests.
+const POOL: Balance = 1_000_000_000_000;
+/// Liquidity of the concentrated liquidity position created by the tests.
+const LIQUIDITY: u128 = 1_000_000_000_000;
+/// Square root price of 1 in Q64.64.
+const Q64: u128 = 1 << 64;
+
+fn free_balance(currency_id: CurrencyId, who: AccountId) -> Balance {
+	<Tokens as MultiCurrency<AccountId>>::free_balance(currency_id, &who)
+}
+
+fn last_event() -> Event {
+	System::events().pop().expect("Event expected").event
+}
+
+fn has_event(event: Event) -> bool {
+	System::events().iter().any(|record| record.event == event)
+}
+
+fn register_pool(currency_id_a: CurrencyId, currency_id_b: CurrencyId, amount_a: Balance, amount_b: Balance) {
+	assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), currency_id_a, currency_id_b, amount_a, amount_b, PoolKind::ConstantProduct));
+}
+
+/// Output of a constant product pool for `supply_amount`, with the default 0.3% fee.
+fn constant_product_target(supply_pool: Balance, target_pool: Balance, supply_amount: Balance) -> Balance {
+	let fee = (supply_amount * 3 + 999) / 1000;
+	let supply_amount = supply_amount - fee;
+	target_pool * supply_amount / (supply_pool + supply_amount)
+}
+
+/// Input of a constant product pool needed for `target_amount`, with the default 0.3% fee.
+fn constant_product_supply(supply_pool: Balance, target_pool: Balance, target_amount: Balance) -> Balance {
+	let denominator = target_pool - target_amount;
+	let supply_amount = (supply_pool * target_amount + denominator - 1) / denominator;
+	(supply_amount * 1_000_000 + 996_999) / 997_000
+}
+
+fn pool_invariant(currency_id_a: CurrencyId, currency_id_b: CurrencyId) -> U256 {
+	let (pool_a, pool_b) = Polkapool::get_liquidity(currency_id_a, currency_id_b);
+	U256::from(pool_a) * U256::from(pool_b)
+}
+
+/// Creates weighted pool 0 of `BTC` and `DOT`, weighted 50/50, with `POOL` of each.
+fn create_weighted_pool() {
+	assert_ok!(Polkapool::create_weighted_pool(
+		Origin::signed(ALICE),
+		vec![BTC, DOT],
+		vec![Permill::from_percent(50), Permill::from_percent(50)],
+		vec![POOL, POOL],
+		Permill::from_perthousand(3),
+	));
+}
+
+/// Creates concentrated pool 0 of `BTC` and `DOT` at price 1, with position 0 of `ALICE`
+/// providing `LIQUIDITY` between ticks -600 and 600. Returns the amounts deposited.
+fn create_concentrated_position() -> (Balance, Balance) {
+	assert_ok!(Polkapool::create_concentrated_pool(Origin::signed(ALICE), BTC, DOT, Permill::from_perthousand(3), 60, Q64));
+	assert_ok!(Polkapool::mint_position(Origin::signed(ALICE), 0, -600, 600, LIQUIDITY, POOL, POOL));
+	match last_event() {
+		Event::polkapool(RawEvent::PositionMinted(ALICE, 0, 0, -600, 600, LIQUIDITY, amount_0, amount_1)) => (amount_0, amount_1),
+		event => panic!("Unexpected event {:?}", event),
+	}
+}
+
+#[test]
+fn register_swap_pair_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(DOT, BTC, POOL, 2 * POOL);
+
+		assert_eq!(Polkapool::liquidity_pool((BTC, DOT)), (2 * POOL, POOL, 2 * POOL));
+		assert_eq!(Polkapool::get_lp_holdings(&ALICE, DOT, BTC), 2 * POOL);
+		assert_eq!(Polkapool::amplification_ramp((BTC, DOT)), None);
+		assert_eq!(free_balance(DOT, ALICE), INITIAL_BALANCE - POOL);
+		assert_eq!(free_balance(BTC, ALICE), INITIAL_BALANCE - 2 * POOL);
+		assert_eq!(free_balance(BTC, Polkapool::get_wallet_account()), 2 * POOL);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::AddLiquidity(ALICE, BTC, 2 * POOL, DOT, POOL, 2 * POOL)));
+	});
+}
+
+#[test]
+fn register_stable_swap_pair_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, DOT, POOL, POOL, PoolKind::StableSwap { amplification: 100 }));
+		register_pool(ETH, USDT, POOL, POOL);
+		assert_eq!(Polkapool::amplification_ramp((BTC, DOT)), Some(AmplificationRamp::fixed(100, 1)));
+
+		// Around the peg a StableSwap pool gives more than a constant product pool of the same size
+		let stable_quote = Polkapool::quote_swap_with_exact_supply(&[BTC, DOT], POOL / 10).unwrap();
+		let constant_product_quote = Polkapool::quote_swap_with_exact_supply(&[ETH, USDT], POOL / 10).unwrap();
+		assert!(stable_quote.amounts[1] > constant_product_quote.amounts[1]);
+		assert!(stable_quote.amounts[1] < POOL / 10);
+	});
+}
+
+#[test]
+fn stable_swap_spot_price_matches_numeric_derivative() {
+	let cases: [(Balance, Balance, u128); 4] = [
+		(1_000_000_000_000_000_000, 3_000_000_000_000_000_000, 10),
+		(POOL, POOL, 100),
+		(POOL, 2 * POOL, 1),
+		(POOL / 2, 4 * POOL, 1_000),
+	];
+	for (x, y, amplification) in cases.iter().copied() {
+		// Output of a swap small enough for the curve to be linear
+		let h = x / 1_000_000;
+		let d = stable_swap::get_d(x, y, amplification).unwrap();
+		let new_y = stable_swap::get_y(U256::from(x + h), d, amplification).unwrap().low_u128();
+		let derivative = FixedU128::saturating_from_rational(y - new_y, h);
+
+		let spot_price = stable_swap::spot_price(x, y, amplification).unwrap();
+		let difference = if spot_price > derivative { spot_price - derivative } else { derivative - spot_price };
+		assert!(
+			difference < FixedU128::saturating_from_rational(1, 100_000),
+			"spot price {:?} differs from derivative {:?} for {:?}", spot_price, derivative, (x, y, amplification)
+		);
+	}
+
+	assert_eq!(stable_swap::spot_price(1_000_000_000_000_000_000, 3_000_000_000_000_000_000, 10).unwrap().saturating_mul_int(10_000u128), 11_568);
+	assert_eq!(stable_swap::spot_price(POOL, POOL, 100), Some(FixedU128::one()));
+}
+
+#[test]
+fn register_swap_pair_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, BTC, POOL, POOL, PoolKind::ConstantProduct),
+			Error::<Test>::InvalidCurrencyId
+		);
+		assert_noop!(
+			Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, DOT, POOL, POOL, PoolKind::StableSwap { amplification: 0 }),
+			Error::<Test>::InvalidAmplification
+		);
+		assert_noop!(
+			Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, DOT, POOL, POOL, PoolKind::StableSwap { amplification: MaxAmplification::get() + 1 }),
+			Error::<Test>::InvalidAmplification
+		);
+		assert_noop!(
+			Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, DOT, 0, POOL, PoolKind::ConstantProduct),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+
+		register_pool(BTC, DOT, POOL, POOL);
+		assert_noop!(
+			Polkapool::register_swap_pair(Origin::signed(BOB), DOT, BTC, POOL, POOL, PoolKind::ConstantProduct),
+			Error::<Test>::TradingPairAlreadyRegistered
+		);
+	});
+}
+
+#[test]
+fn swap_with_exact_supply_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		let supply_amount = 1_000_000_000;
+		let target_amount = constant_product_target(POOL, POOL, supply_amount);
+
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], supply_amount, target_amount, None));
+
+		assert_eq!(free_balance(BTC, BOB), INITIAL_BALANCE - supply_amount);
+		assert_eq!(free_balance(DOT, BOB), INITIAL_BALANCE + target_amount);
+		assert_eq!(Polkapool::get_liquidity(BTC, DOT), (POOL + supply_amount, POOL - target_amount));
+		assert!(has_event(Event::polkapool(RawEvent::PoolSwap(BTC, supply_amount, DOT, target_amount, 3_000_000))));
+		assert_eq!(last_event(), Event::polkapool(RawEvent::Swap(BOB, vec![BTC, DOT], supply_amount, target_amount)));
+	});
+}
+
+#[test]
+fn swap_with_exact_supply_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		register_pool(DOT, USDT, POOL, POOL);
+		let target_amount = constant_product_target(POOL, POOL, 1_000_000_000);
+
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC], 1_000_000_000, 0, None),
+			Error::<Test>::InvalidTradingPathLength
+		);
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT, USDT, ETH], 1_000_000_000, 0, None),
+			Error::<Test>::InvalidTradingPathLength
+		);
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, ETH], 1_000_000_000, 0, None),
+			Error::<Test>::TradingPairNotAllowed
+		);
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], 1_000_000_000, target_amount + 1, None),
+			Error::<Test>::InsufficientTargetAmount
+		);
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, 0, Some(Permill::from_percent(1))),
+			Error::<Test>::ExceedPriceImpactLimit
+		);
+		// The whole supply goes to the fee
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], 1, 0, None),
+			Error::<Test>::ZeroTargetAmount
+		);
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(CHARLIE), vec![BTC, DOT], 1_000_000_000, 0, None),
+			Error::<Test>::InsufficientBalance
+		);
+	});
+}
+
+#[test]
+fn swap_from_drained_pool_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		assert_ok!(Polkapool::remove_liquidity(Origin::signed(ALICE), BTC, DOT, POOL, 0, 0, None));
+		assert_eq!(Polkapool::liquidity_pool((BTC, DOT)), (0, 0, 0));
+
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], 1_000_000_000, 0, None),
+			Error::<Test>::InsufficientLiquidity
+		);
+		assert_noop!(
+			Polkapool::swap_with_exact_target(Origin::signed(BOB), vec![BTC, DOT], 1_000_000_000, POOL, None),
+			Error::<Test>::InsufficientLiquidity
+		);
+	});
+}
+
+#[test]
+fn swap_with_exact_target_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		let target_amount = 1_000_000_000;
+		let supply_amount = constant_product_supply(POOL, POOL, target_amount);
+
+		assert_ok!(Polkapool::swap_with_exact_target(Origin::signed(BOB), vec![BTC, DOT], target_amount, supply_amount, None));
+
+		assert_eq!(free_balance(BTC, BOB), INITIAL_BALANCE - supply_amount);
+		assert_eq!(free_balance(DOT, BOB), INITIAL_BALANCE + target_amount);
+		assert_eq!(Polkapool::get_liquidity(BTC, DOT), (POOL + supply_amount, POOL - target_amount));
+		assert_eq!(last_event(), Event::polkapool(RawEvent::Swap(BOB, vec![BTC, DOT], supply_amount, target_amount)));
+	});
+}
+
+#[test]
+fn swap_with_exact_target_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		let supply_amount = constant_product_supply(POOL, POOL, 1_000_000_000);
+
+		assert_noop!(
+			Polkapool::swap_with_exact_target(Origin::signed(BOB), vec![DOT], 1_000_000_000, POOL, None),
+			Error::<Test>::InvalidTradingPathLength
+		);
+		assert_noop!(
+			Polkapool::swap_with_exact_target(Origin::signed(BOB), vec![ETH, DOT], 1_000_000_000, POOL, None),
+			Error::<Test>::TradingPairNotAllowed
+		);
+		assert_noop!(
+			Polkapool::swap_with_exact_target(Origin::signed(BOB), vec![BTC, DOT], 1_000_000_000, supply_amount - 1, None),
+			Error::<Test>::ExcessiveSupplyAmount
+		);
+		assert_noop!(
+			Polkapool::swap_with_exact_target(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, POOL, Some(Permill::from_percent(1))),
+			Error::<Test>::ExceedPriceImpactLimit
+		);
+		// The pool cannot give out its whole reserve
+		assert_noop!(
+			Polkapool::swap_with_exact_target(Origin::signed(BOB), vec![BTC, DOT], POOL, INITIAL_BALANCE, None),
+			Error::<Test>::ZeroSupplyAmount
+		);
+		assert_noop!(
+			Polkapool::swap_with_exact_target(Origin::signed(CHARLIE), vec![BTC, DOT], 1_000_000_000, POOL, None),
+			Error::<Test>::InsufficientBalance
+		);
+	});
+}
+
+#[test]
+fn ratios_beyond_fixed_point_range_fail() {
+	ExtBuilder::default().build().execute_with(|| {
+		// Ratios above the largest `FixedU128`, about 3.4e20, cannot be compared to any limit
+		let amount = 1_000 * INITIAL_BALANCE;
+		assert_ok!(<Tokens as MultiCurrency<AccountId>>::deposit(BTC, &BOB, amount));
+		assert_ok!(<Tokens as MultiCurrency<AccountId>>::deposit(DOT, &ALICE, amount));
+
+		let half = Permill::from_percent(50);
+		assert_ok!(Polkapool::create_weighted_pool(Origin::signed(ALICE), vec![BTC, DOT], vec![half, half], vec![1, POOL], Permill::from_perthousand(3)));
+		assert_noop!(
+			Polkapool::swap_weighted_pool(Origin::signed(BOB), 0, BTC, DOT, amount, 0),
+			Error::<Test>::FixedU128ConversionFailed
+		);
+
+		// One BTC is worth 1e21 DOT, so the spot price checked against the price impact limit
+		// overflows
+		register_pool(BTC, DOT, 1, amount);
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], 1_000, 0, Some(Permill::one())),
+			Error::<Test>::FixedU128ConversionFailed
+		);
+	});
+}
+
+#[test]
+fn quote_swap_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		register_pool(DOT, USDT, POOL, POOL);
+		let amount = 1_000_000_000;
+		let dot_amount = constant_product_target(POOL, POOL, amount);
+		let usdt_amount = constant_product_target(POOL, POOL, dot_amount);
+
+		let quote = Polkapool::quote_swap_with_exact_supply(&[BTC, DOT, USDT], amount).unwrap();
+		assert_eq!(quote.amounts, vec![amount, dot_amount, usdt_amount]);
+		assert_eq!(quote.fees, vec![3_000_000, (dot_amount * 3 + 999) / 1000]);
+		assert_eq!(quote.price_impacts.len(), 2);
+
+		let quote = Polkapool::quote_swap_with_exact_target(&[BTC, DOT], amount).unwrap();
+		let supply_amount = constant_product_supply(POOL, POOL, amount);
+		assert_eq!(quote.amounts, vec![supply_amount, amount]);
+		assert_eq!(quote.fees, vec![(supply_amount * 3 + 999) / 1000]);
+
+		// Quoting does not swap
+		assert_eq!(Polkapool::get_liquidity(BTC, DOT), (POOL, POOL));
+		assert_eq!(Polkapool::get_pool_reserves(DOT, BTC), (POOL, POOL, POOL));
+		assert_eq!(
+			Polkapool::quote_swap_with_exact_supply(&[BTC, ETH], amount),
+			Err(Error::<Test>::TradingPairNotAllowed.into())
+		);
+		assert_eq!(
+			Polkapool::quote_swap_with_exact_target(&[BTC, DOT], POOL),
+			Err(Error::<Test>::ZeroSupplyAmount.into())
+		);
+	});
+}
+
+#[test]
+fn add_liquidity_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, 2 * POOL);
+
+		// Only half of the BTC offered matches the DOT offered
+		assert_ok!(Polkapool::add_liquidity(Origin::signed(BOB), DOT, BTC, POOL, POOL, POOL, Some(1)));
+
+		assert_eq!(Polkapool::liquidity_pool((BTC, DOT)), (3 * POOL / 2, 3 * POOL, 3 * POOL));
+		assert_eq!(Polkapool::get_lp_holdings(&BOB, BTC, DOT), POOL);
+		assert_eq!(free_balance(BTC, BOB), INITIAL_BALANCE - POOL / 2);
+		assert_eq!(free_balance(DOT, BOB), INITIAL_BALANCE - POOL);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::AddLiquidity(BOB, BTC, POOL / 2, DOT, POOL, POOL)));
+	});
+}
+
+#[test]
+fn add_liquidity_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL / 1_000);
+
+		assert_noop!(
+			Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, 0, POOL, 0, None),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+		// 1 BTC is worth less than the smallest unit of DOT
+		assert_noop!(
+			Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, 1, POOL, 0, None),
+			Error::<Test>::InvalidLiquidityIncrement
+		);
+		assert_noop!(
+			Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, POOL, POOL, POOL + 1, None),
+			Error::<Test>::InsufficientShareIncrement
+		);
+
+		run_to_block(5);
+		assert_noop!(
+			Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, POOL, POOL, 0, Some(4)),
+			Error::<Test>::DeadlineExpired
+		);
+	});
+}
+
+#[test]
+fn remove_liquidity_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, 2 * POOL);
+
+		assert_ok!(Polkapool::remove_liquidity(Origin::signed(ALICE), DOT, BTC, POOL / 2, POOL / 2, POOL / 4, Some(1)));
+
+		assert_eq!(Polkapool::liquidity_pool((BTC, DOT)), (3 * POOL / 4, 3 * POOL / 2, 3 * POOL / 2));
+		assert_eq!(Polkapool::get_lp_holdings(&ALICE, BTC, DOT), 3 * POOL / 2);
+		assert_eq!(free_balance(BTC, ALICE), INITIAL_BALANCE - POOL + POOL / 4);
+		assert_eq!(free_balance(DOT, ALICE), INITIAL_BALANCE - 2 * POOL + POOL / 2);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::RemoveLiquidity(ALICE, BTC, POOL / 4, DOT, POOL / 2, POOL / 2)));
+	});
+}
+
+#[test]
+fn remove_liquidity_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+
+		assert_noop!(
+			Polkapool::remove_liquidity(Origin::signed(ALICE), BTC, ETH, POOL / 2, 0, 0, None),
+			Error::<Test>::TradingPairNotAllowed
+		);
+		assert_noop!(
+			Polkapool::remove_liquidity(Origin::signed(BOB), BTC, DOT, 1, 0, 0, None),
+			Error::<Test>::LowShare
+		);
+		assert_noop!(
+			Polkapool::remove_liquidity(Origin::signed(ALICE), BTC, DOT, POOL / 2, POOL / 2 + 1, 0, None),
+			Error::<Test>::InsufficientWithdrawnAmount
+		);
+
+		run_to_block(5);
+		assert_noop!(
+			Polkapool::remove_liquidity(Origin::signed(ALICE), BTC, DOT, POOL / 2, 0, 0, Some(4)),
+			Error::<Test>::DeadlineExpired
+		);
+	});
+}
+
+#[test]
+fn liquidity_calls_expire_after_their_deadline() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		run_to_block(5);
+
+		assert_noop!(
+			Polkapool::add_liquidity_single_asset(Origin::signed(BOB), BTC, DOT, POOL / 100, 0, Some(4)),
+			Error::<Test>::DeadlineExpired
+		);
+		assert_noop!(
+			Polkapool::remove_liquidity_single_asset(Origin::signed(ALICE), BTC, DOT, POOL / 10, 0, Some(4)),
+			Error::<Test>::DeadlineExpired
+		);
+		// The deadline block itself is still valid
+		assert_ok!(Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, POOL / 100, POOL / 100, 0, Some(5)));
+		assert_ok!(Polkapool::add_liquidity_single_asset(Origin::signed(BOB), BTC, DOT, POOL / 100, 0, Some(5)));
+		assert_ok!(Polkapool::remove_liquidity_single_asset(Origin::signed(ALICE), BTC, DOT, POOL / 10, 0, Some(5)));
+		assert_ok!(Polkapool::remove_liquidity(Origin::signed(ALICE), BTC, DOT, POOL / 10, 0, 0, Some(5)));
+	});
+}
+
+#[test]
+fn remove_liquidity_checks_both_minimum_amounts() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, 2 * POOL);
+
+		// The minimums follow the order of the arguments, not of the pair
+		assert_noop!(
+			Polkapool::remove_liquidity(Origin::signed(ALICE), DOT, BTC, POOL / 2, POOL / 2 + 1, 0, None),
+			Error::<Test>::InsufficientWithdrawnAmount
+		);
+		assert_noop!(
+			Polkapool::remove_liquidity(Origin::signed(ALICE), DOT, BTC, POOL / 2, 0, POOL / 4 + 1, None),
+			Error::<Test>::InsufficientWithdrawnAmount
+		);
+		assert_ok!(Polkapool::remove_liquidity(Origin::signed(ALICE), DOT, BTC, POOL / 2, POOL / 2, POOL / 4, None));
+	});
+}
+
+#[test]
+fn add_liquidity_single_asset_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		let supply_amount = 10_000_000_000;
+
+		assert_ok!(Polkapool::add_liquidity_single_asset(Origin::signed(BOB), BTC, DOT, supply_amount, 1, None));
+
+		// The swapped DOT is deposited back, leaving at most rounding dust with the caller
+		let shares = Polkapool::get_lp_holdings(&BOB, BTC, DOT);
+		assert!(shares > 0 && shares < supply_amount / 2);
+		let btc_spent = INITIAL_BALANCE - free_balance(BTC, BOB);
+		assert!(btc_spent <= supply_amount && supply_amount - btc_spent < 1_000_000);
+		assert!(free_balance(DOT, BOB) - INITIAL_BALANCE < 1_000_000);
+	});
+}
+
+#[test]
+fn add_liquidity_single_asset_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), DOT, USDT, POOL, POOL, PoolKind::StableSwap { amplification: 100 }));
+
+		assert_noop!(
+			Polkapool::add_liquidity_single_asset(Origin::signed(BOB), BTC, DOT, 0, 0, None),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+		assert_noop!(
+			Polkapool::add_liquidity_single_asset(Origin::signed(BOB), BTC, BTC, POOL / 100, 0, None),
+			Error::<Test>::InvalidCurrencyId
+		);
+		assert_noop!(
+			Polkapool::add_liquidity_single_asset(Origin::signed(BOB), BTC, ETH, POOL / 100, 0, None),
+			Error::<Test>::TradingPairNotAllowed
+		);
+		assert_noop!(
+			Polkapool::add_liquidity_single_asset(Origin::signed(BOB), DOT, USDT, POOL / 100, 0, None),
+			Error::<Test>::NotConstantProductPool
+		);
+		assert_noop!(
+			Polkapool::add_liquidity_single_asset(Origin::signed(BOB), BTC, DOT, POOL / 100, POOL / 100, None),
+			Error::<Test>::InsufficientShareIncrement
+		);
+	});
+}
+
+#[test]
+fn remove_liquidity_single_asset_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+
+		assert_ok!(Polkapool::remove_liquidity_single_asset(Origin::signed(ALICE), BTC, DOT, POOL / 10, POOL / 10, None));
+
+		// The DOT withdrawn is sold for BTC in the same pool
+		let btc_received = free_balance(BTC, ALICE) - (INITIAL_BALANCE - POOL);
+		assert_eq!(free_balance(DOT, ALICE), INITIAL_BALANCE - POOL);
+		assert_eq!(btc_received, POOL / 10 + constant_product_target(9 * POOL / 10, 9 * POOL / 10, POOL / 10));
+		assert_eq!(Polkapool::get_lp_holdings(&ALICE, BTC, DOT), 9 * POOL / 10);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::Swap(ALICE, vec![DOT, BTC], POOL / 10, btc_received - POOL / 10)));
+	});
+}
+
+#[test]
+fn remove_liquidity_single_asset_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+
+		assert_noop!(
+			Polkapool::remove_liquidity_single_asset(Origin::signed(ALICE), BTC, DOT, 0, 0, None),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+		assert_noop!(
+			Polkapool::remove_liquidity_single_asset(Origin::signed(ALICE), BTC, BTC, POOL / 10, 0, None),
+			Error::<Test>::InvalidCurrencyId
+		);
+		assert_noop!(
+			Polkapool::remove_liquidity_single_asset(Origin::signed(BOB), BTC, DOT, POOL / 10, 0, None),
+			Error::<Test>::LowShare
+		);
+		assert_noop!(
+			Polkapool::remove_liquidity_single_asset(Origin::signed(ALICE), BTC, DOT, POOL / 10, POOL / 5, None),
+			Error::<Test>::InsufficientTargetAmount
+		);
+	});
+}
+
+#[test]
+fn single_asset_round_trip_pays_swap_fees() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		let supply_amount = 10_000_000_000;
+
+		assert_ok!(Polkapool::add_liquidity_single_asset(Origin::signed(BOB), BTC, DOT, supply_amount, 1, None));
+		let shares = Polkapool::get_lp_holdings(&BOB, BTC, DOT);
+		assert_ok!(Polkapool::remove_liquidity_single_asset(Origin::signed(BOB), BTC, DOT, shares, 1, None));
+
+		// Both swaps pay the fee on about half of the amount
+		assert_eq!(Polkapool::get_lp_holdings(&BOB, BTC, DOT), 0);
+		let btc_lost = INITIAL_BALANCE - free_balance(BTC, BOB);
+		assert!(btc_lost > 0 && btc_lost < supply_amount * 4 / 1000);
+		assert!(pool_invariant(BTC, DOT) > U256::from(POOL) * U256::from(POOL));
+	});
+}
+
+#[test]
+fn swap_with_best_route_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		register_pool(DOT, USDT, POOL, 2 * POOL);
+		let supply_amount = 1_000_000_000;
+
+		let route = Polkapool::find_best_route_with_exact_supply(BTC, USDT, supply_amount).unwrap();
+		assert_eq!(route.path, vec![BTC, DOT, USDT]);
+		let target_amount = route.quote.amounts[2];
+
+		assert_ok!(Polkapool::swap_with_best_route(Origin::signed(BOB), route.path, supply_amount, target_amount, None));
+		assert_eq!(free_balance(BTC, BOB), INITIAL_BALANCE - supply_amount);
+		assert_eq!(free_balance(USDT, BOB), INITIAL_BALANCE + target_amount);
+		assert_eq!(free_balance(DOT, BOB), INITIAL_BALANCE);
+	});
+}
+
+#[test]
+fn find_best_route_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		register_pool(DOT, USDT, POOL, POOL);
+		// The direct pool is shallower but charges the fee once
+		register_pool(BTC, USDT, POOL / 100, POOL / 100);
+		assert_eq!(Polkapool::get_trading_pairs().len(), 3);
+
+		let route = Polkapool::find_best_route_with_exact_supply(BTC, USDT, 1_000_000).unwrap();
+		assert_eq!(route.path, vec![BTC, USDT]);
+		assert_eq!(Ok(route.quote), Polkapool::quote_swap_with_exact_supply(&[BTC, USDT], 1_000_000));
+
+		let route = Polkapool::find_best_route_with_exact_supply(BTC, USDT, 1_000_000_000).unwrap();
+		assert_eq!(route.path, vec![BTC, DOT, USDT]);
+		assert_eq!(Ok(route.quote), Polkapool::quote_swap_with_exact_supply(&[BTC, DOT, USDT], 1_000_000_000));
+
+		let route = Polkapool::find_best_route_with_exact_target(BTC, USDT, 1_000_000_000).unwrap();
+		assert_eq!(route.path, vec![BTC, DOT, USDT]);
+		assert_eq!(Ok(route.quote), Polkapool::quote_swap_with_exact_target(&[BTC, DOT, USDT], 1_000_000_000));
+
+		assert_eq!(Polkapool::find_best_route_with_exact_supply(BTC, ETH, 1_000_000), None);
+		assert_eq!(Polkapool::find_best_route_with_exact_supply(BTC, BTC, 1_000_000), None);
+		// No route can give out more than the reserves
+		assert_eq!(Polkapool::find_best_route_with_exact_target(BTC, USDT, 2 * POOL), None);
+	});
+}
+
+#[test]
+fn swap_with_best_route_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		register_pool(DOT, USDT, POOL, POOL);
+
+		assert_noop!(
+			Polkapool::swap_with_best_route(Origin::signed(BOB), vec![BTC], 1_000_000_000, 0, None),
+			Error::<Test>::InvalidTradingPathLength
+		);
+		assert_noop!(
+			Polkapool::swap_with_best_route(Origin::signed(BOB), vec![BTC, DOT, BTC], 1_000_000_000, 0, None),
+			Error::<Test>::InvalidTradingPath
+		);
+		assert_noop!(
+			Polkapool::swap_with_best_route(Origin::signed(BOB), vec![BTC, USDT], 1_000_000_000, 0, None),
+			Error::<Test>::InsufficientLiquidity
+		);
+	});
+}
+
+#[test]
+fn price_impact_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+
+		// `s / (x + s)` for a constant product pool, with `s` the supply amount net of the fee
+		let quote = Polkapool::quote_swap_with_exact_supply(&[BTC, DOT], POOL / 10).unwrap();
+		let fee_reduced_supply_amount = POOL / 10 - quote.fees[0];
+		let expected = Permill::from_rational_approximation(fee_reduced_supply_amount, POOL + fee_reduced_supply_amount);
+		let price_impact = quote.price_impacts[0];
+		assert!(price_impact.deconstruct().max(expected.deconstruct()) - price_impact.deconstruct().min(expected.deconstruct()) <= 1);
+
+		// The fee is not part of the price impact
+		let small_quote = Polkapool::quote_swap_with_exact_supply(&[BTC, DOT], 1_000_000).unwrap();
+		assert!(small_quote.price_impacts[0] <= Permill::from_parts(1));
+		assert_eq!(small_quote.fees[0], 3_000);
+
+		// The limit is inclusive
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, 0, Some(Permill::from_parts(price_impact.deconstruct() - 1))),
+			Error::<Test>::ExceedPriceImpactLimit
+		);
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, 0, Some(price_impact)));
+	});
+}
+
+#[test]
+fn set_max_price_impact_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+
+		assert_noop!(Polkapool::set_max_price_impact(Origin::signed(ALICE), Some(Permill::from_percent(1))), BadOrigin);
+		assert_ok!(Polkapool::set_max_price_impact(Origin::root(), Some(Permill::from_percent(1))));
+		assert_eq!(Polkapool::max_price_impact(), Some(Permill::from_percent(1)));
+		assert_eq!(last_event(), Event::polkapool(RawEvent::MaxPriceImpactUpdated(Some(Permill::from_percent(1)))));
+
+		// The global limit applies even when the caller sets none
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, 0, None),
+			Error::<Test>::ExceedPriceImpactLimit
+		);
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 1_000, 0, None));
+	});
+}
+
+#[test]
+fn set_pool_max_price_impact_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		assert_ok!(Polkapool::set_max_price_impact(Origin::root(), Some(Permill::from_percent(1))));
+
+		assert_noop!(Polkapool::set_pool_max_price_impact(Origin::signed(ALICE), DOT, BTC, Some(Permill::from_percent(50))), BadOrigin);
+		assert_noop!(
+			Polkapool::set_pool_max_price_impact(Origin::root(), BTC, ETH, Some(Permill::from_percent(50))),
+			Error::<Test>::TradingPairNotAllowed
+		);
+		assert_ok!(Polkapool::set_pool_max_price_impact(Origin::root(), DOT, BTC, Some(Permill::from_percent(50))));
+		assert_eq!(Polkapool::pool_max_price_impact((BTC, DOT)), Some(Permill::from_percent(50)));
+		assert_eq!(last_event(), Event::polkapool(RawEvent::PoolMaxPriceImpactUpdated(BTC, DOT, Some(Permill::from_percent(50)))));
+
+		// The pool limit overrides the global one, the caller's limit still applies
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, 0, None));
+		assert_noop!(
+			Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, 0, Some(Permill::from_percent(1))),
+			Error::<Test>::ExceedPriceImpactLimit
+		);
+	});
+}
+
+#[test]
+fn set_protocol_fee_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(Polkapool::set_protocol_fee(Origin::signed(ALICE), Some(Permill::from_percent(50))), BadOrigin);
+		assert_ok!(Polkapool::set_protocol_fee(Origin::root(), Some(Permill::from_percent(50))));
+		assert_eq!(Polkapool::protocol_fee(), Some(Permill::from_percent(50)));
+		assert_eq!(last_event(), Event::polkapool(RawEvent::ProtocolFeeUpdated(Some(Permill::from_percent(50)))));
+
+		register_pool(BTC, DOT, POOL, POOL);
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, 0, None));
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![DOT, BTC], POOL / 10, 0, None));
+		assert_eq!(Polkapool::get_accrued_protocol_fee(BTC, DOT), 0);
+
+		// The fees are minted on the next liquidity event
+		assert_ok!(Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, POOL / 100, POOL / 100, 0, None));
+		let accrued = Polkapool::get_accrued_protocol_fee(BTC, DOT);
+		assert!(accrued > 0);
+		assert_eq!(Polkapool::get_lp_holdings(&FEE_ACCOUNT, BTC, DOT), accrued);
+		assert!(has_event(Event::polkapool(RawEvent::ProtocolFeeMinted(BTC, DOT, accrued))));
+	});
+}
+
+#[test]
+fn withdraw_protocol_fee_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Polkapool::set_protocol_fee(Origin::root(), Some(Permill::from_percent(50))));
+		register_pool(BTC, DOT, POOL, POOL);
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, 0, None));
+		assert_ok!(Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, POOL / 100, POOL / 100, 0, None));
+		let accrued = Polkapool::get_accrued_protocol_fee(BTC, DOT);
+		let (pool_0, pool_1, pool_shares) = Polkapool::liquidity_pool((BTC, DOT));
+		let amount_0 = pool_0 * accrued / pool_shares;
+		let amount_1 = pool_1 * accrued / pool_shares;
+
+		assert_noop!(Polkapool::withdraw_protocol_fee(Origin::signed(FEE_ACCOUNT), BTC, DOT, accrued, CHARLIE), BadOrigin);
+		assert_noop!(
+			Polkapool::withdraw_protocol_fee(Origin::root(), BTC, DOT, accrued + 1, CHARLIE),
+			Error::<Test>::LowShare
+		);
+		assert_ok!(Polkapool::withdraw_protocol_fee(Origin::root(), DOT, BTC, accrued, CHARLIE));
+
+		assert_eq!(Polkapool::get_lp_holdings(&FEE_ACCOUNT, BTC, DOT), 0);
+		assert_eq!(free_balance(BTC, CHARLIE), amount_0);
+		assert_eq!(free_balance(DOT, CHARLIE), amount_1);
+		assert_eq!(free_balance(BTC, FEE_ACCOUNT), 0);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::ProtocolFeeWithdrawn(CHARLIE, BTC, amount_0, DOT, amount_1, accrued)));
+	});
+}
+
+#[test]
+fn protocol_fee_is_not_minted_by_amplification_ramps() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Polkapool::set_protocol_fee(Origin::root(), Some(Permill::from_percent(50))));
+		// `D` of an imbalanced pool grows with the amplification
+		assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, DOT, POOL, POOL / 2, PoolKind::StableSwap { amplification: 100 }));
+		assert_eq!(Polkapool::root_k_last((BTC, DOT)), stable_swap::get_d(POOL, POOL / 2, 100).unwrap());
+		assert_eq!(Polkapool::root_k_last_amplification((BTC, DOT)), Some(100));
+
+		assert_ok!(Polkapool::ramp_amplification(Origin::root(), BTC, DOT, 200, 21));
+		run_to_block(11);
+		assert_ok!(Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, POOL / 100, POOL / 200, 0, None));
+		run_to_block(30);
+		assert_ok!(Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, POOL / 100, POOL / 200, 0, None));
+		assert_eq!(Polkapool::get_accrued_protocol_fee(BTC, DOT), 0);
+		assert_eq!(Polkapool::root_k_last_amplification((BTC, DOT)), Some(200));
+
+		// Fees are still minted
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, 0, None));
+		assert_ok!(Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, POOL / 100, POOL / 100, 0, None));
+		assert!(Polkapool::get_accrued_protocol_fee(BTC, DOT) > 0);
+	});
+}
+
+#[test]
+fn ramp_amplification_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, DOT, POOL, POOL, PoolKind::StableSwap { amplification: 100 }));
+
+		assert_noop!(Polkapool::ramp_amplification(Origin::signed(ALICE), BTC, DOT, 200, 21), BadOrigin);
+		assert_ok!(Polkapool::ramp_amplification(Origin::root(), BTC, DOT, 200, 21));
+		assert_eq!(last_event(), Event::polkapool(RawEvent::AmplificationRampStarted(BTC, DOT, 100, 200, 21)));
+
+		run_to_block(11);
+		assert_eq!(Polkapool::amplification_ramp((BTC, DOT)).unwrap().amplification_at(11), 150);
+		run_to_block(30);
+		assert_eq!(Polkapool::amplification_ramp((BTC, DOT)).unwrap().amplification_at(30), 200);
+	});
+}
+
+#[test]
+fn ramp_amplification_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, USDT, POOL, POOL);
+		assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, DOT, POOL, POOL, PoolKind::StableSwap { amplification: 100 }));
+
+		assert_noop!(
+			Polkapool::ramp_amplification(Origin::root(), BTC, USDT, 200, 21),
+			Error::<Test>::NotStableSwapPool
+		);
+		assert_noop!(
+			Polkapool::ramp_amplification(Origin::root(), BTC, DOT, 0, 21),
+			Error::<Test>::InvalidAmplification
+		);
+		assert_noop!(
+			Polkapool::ramp_amplification(Origin::root(), BTC, DOT, MaxAmplification::get() + 1, 21),
+			Error::<Test>::InvalidAmplification
+		);
+		// Shorter than `MinAmplificationRampDuration`
+		assert_noop!(
+			Polkapool::ramp_amplification(Origin::root(), BTC, DOT, 200, 10),
+			Error::<Test>::InvalidAmplificationRamp
+		);
+		// More than ten times the current amplification
+		assert_noop!(
+			Polkapool::ramp_amplification(Origin::root(), BTC, DOT, 1_001, 21),
+			Error::<Test>::InvalidAmplificationRamp
+		);
+	});
+}
+
+#[test]
+fn stop_amplification_ramp_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, USDT, POOL, POOL);
+		assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, DOT, POOL, POOL, PoolKind::StableSwap { amplification: 100 }));
+		assert_ok!(Polkapool::ramp_amplification(Origin::root(), BTC, DOT, 200, 21));
+		run_to_block(11);
+
+		assert_noop!(Polkapool::stop_amplification_ramp(Origin::signed(ALICE), BTC, DOT), BadOrigin);
+		assert_noop!(Polkapool::stop_amplification_ramp(Origin::root(), BTC, USDT), Error::<Test>::NotStableSwapPool);
+		assert_ok!(Polkapool::stop_amplification_ramp(Origin::root(), DOT, BTC));
+		assert_eq!(Polkapool::amplification_ramp((BTC, DOT)), Some(AmplificationRamp::fixed(150, 11)));
+		assert_eq!(last_event(), Event::polkapool(RawEvent::AmplificationRampStopped(BTC, DOT, 150)));
+	});
+}
+
+#[test]
+fn create_weighted_pool_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		let pool = Polkapool::weighted_pool(0).unwrap();
+		assert_eq!(pool.assets, vec![BTC, DOT]);
+		assert_eq!(pool.balances, vec![POOL, POOL]);
+		assert_eq!(pool.total_shares, POOL);
+		assert_eq!(Polkapool::next_weighted_pool_id(), 1);
+		assert_eq!(Polkapool::weighted_pool_holdings((ALICE, 0)), POOL);
+		assert_eq!(free_balance(BTC, ALICE), INITIAL_BALANCE - POOL);
+		assert!(has_event(Event::polkapool(RawEvent::WeightedPoolCreated(ALICE, 0, vec![BTC, DOT]))));
+		assert_eq!(last_event(), Event::polkapool(RawEvent::WeightedPoolJoined(ALICE, 0, vec![POOL, POOL], POOL)));
+	});
+}
+
+#[test]
+fn create_weighted_pool_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		let half = Permill::from_percent(50);
+		let fee = Permill::from_perthousand(3);
+
+		assert_noop!(
+			Polkapool::create_weighted_pool(Origin::signed(ALICE), vec![BTC], vec![Permill::one()], vec![POOL], fee),
+			Error::<Test>::InvalidWeightedPool
+		);
+		assert_noop!(
+			Polkapool::create_weighted_pool(Origin::signed(ALICE), vec![BTC, BTC], vec![half, half], vec![POOL, POOL], fee),
+			Error::<Test>::InvalidWeightedPool
+		);
+		assert_noop!(
+			Polkapool::create_weighted_pool(Origin::signed(ALICE), vec![BTC, DOT], vec![half, Permill::from_percent(60)], vec![POOL, POOL], fee),
+			Error::<Test>::InvalidWeightedPool
+		);
+		assert_noop!(
+			Polkapool::create_weighted_pool(Origin::signed(ALICE), vec![BTC, DOT], vec![half, half], vec![POOL], fee),
+			Error::<Test>::InvalidWeightedPool
+		);
+		assert_noop!(
+			Polkapool::create_weighted_pool(Origin::signed(ALICE), vec![BTC, DOT], vec![half, half], vec![POOL, POOL], Permill::from_percent(20)),
+			Error::<Test>::InvalidWeightedPool
+		);
+		assert_noop!(
+			Polkapool::create_weighted_pool(Origin::signed(ALICE), vec![BTC, DOT], vec![half, half], vec![POOL, 0], fee),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+	});
+}
+
+#[test]
+fn join_weighted_pool_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_ok!(Polkapool::join_weighted_pool(Origin::signed(BOB), 0, POOL / 10, vec![POOL, POOL]));
+
+		// Deposits are rounded up in favour of the pool
+		let amount_in = POOL / 10 + 1;
+		assert_eq!(Polkapool::weighted_pool(0).unwrap().balances, vec![POOL + amount_in, POOL + amount_in]);
+		assert_eq!(Polkapool::weighted_pool(0).unwrap().total_shares, POOL + POOL / 10);
+		assert_eq!(Polkapool::weighted_pool_holdings((BOB, 0)), POOL / 10);
+		assert_eq!(free_balance(DOT, BOB), INITIAL_BALANCE - amount_in);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::WeightedPoolJoined(BOB, 0, vec![amount_in, amount_in], POOL / 10)));
+	});
+}
+
+#[test]
+fn join_weighted_pool_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_noop!(
+			Polkapool::join_weighted_pool(Origin::signed(BOB), 0, 0, vec![POOL, POOL]),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+		assert_noop!(
+			Polkapool::join_weighted_pool(Origin::signed(BOB), 1, POOL / 10, vec![POOL, POOL]),
+			Error::<Test>::WeightedPoolNotFound
+		);
+		assert_noop!(
+			Polkapool::join_weighted_pool(Origin::signed(BOB), 0, POOL / 10, vec![POOL]),
+			Error::<Test>::InvalidWeightedPool
+		);
+		assert_noop!(
+			Polkapool::join_weighted_pool(Origin::signed(BOB), 0, POOL / 10, vec![POOL, POOL / 10]),
+			Error::<Test>::ExcessiveSupplyAmount
+		);
+	});
+}
+
+#[test]
+fn exit_weighted_pool_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_ok!(Polkapool::exit_weighted_pool(Origin::signed(ALICE), 0, POOL / 10, vec![POOL / 10, POOL / 10]));
+
+		assert_eq!(Polkapool::weighted_pool(0).unwrap().balances, vec![9 * POOL / 10, 9 * POOL / 10]);
+		assert_eq!(Polkapool::weighted_pool(0).unwrap().total_shares, 9 * POOL / 10);
+		assert_eq!(Polkapool::weighted_pool_holdings((ALICE, 0)), 9 * POOL / 10);
+		assert_eq!(free_balance(BTC, ALICE), INITIAL_BALANCE - 9 * POOL / 10);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::WeightedPoolExited(ALICE, 0, vec![POOL / 10, POOL / 10], POOL / 10)));
+	});
+}
+
+#[test]
+fn exit_weighted_pool_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_noop!(
+			Polkapool::exit_weighted_pool(Origin::signed(ALICE), 0, 0, vec![0, 0]),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+		assert_noop!(
+			Polkapool::exit_weighted_pool(Origin::signed(BOB), 0, POOL / 10, vec![0, 0]),
+			Error::<Test>::LowShare
+		);
+		assert_noop!(
+			Polkapool::exit_weighted_pool(Origin::signed(ALICE), 1, POOL / 10, vec![0, 0]),
+			Error::<Test>::WeightedPoolNotFound
+		);
+		assert_noop!(
+			Polkapool::exit_weighted_pool(Origin::signed(ALICE), 0, POOL / 10, vec![0, POOL / 10 + 1]),
+			Error::<Test>::InsufficientTargetAmount
+		);
+	});
+}
+
+#[test]
+fn join_weighted_pool_single_asset_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_ok!(Polkapool::join_weighted_pool_single_asset(Origin::signed(BOB), 0, BTC, POOL / 10, 1));
+
+		// Half of the deposit is implicitly swapped and pays the fee, so BOB gets less than a
+		// proportional join of both assets would give
+		let shares = Polkapool::weighted_pool_holdings((BOB, 0));
+		assert!(shares > 0 && shares < POOL / 20);
+		assert_eq!(Polkapool::weighted_pool(0).unwrap().balances, vec![POOL + POOL / 10, POOL]);
+		assert_eq!(free_balance(BTC, BOB), INITIAL_BALANCE - POOL / 10);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::WeightedPoolJoined(BOB, 0, vec![POOL / 10, 0], shares)));
+	});
+}
+
+#[test]
+fn join_weighted_pool_single_asset_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_noop!(
+			Polkapool::join_weighted_pool_single_asset(Origin::signed(BOB), 0, BTC, 0, 0),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+		assert_noop!(
+			Polkapool::join_weighted_pool_single_asset(Origin::signed(BOB), 1, BTC, POOL / 10, 0),
+			Error::<Test>::WeightedPoolNotFound
+		);
+		assert_noop!(
+			Polkapool::join_weighted_pool_single_asset(Origin::signed(BOB), 0, ETH, POOL / 10, 0),
+			Error::<Test>::AssetNotInPool
+		);
+		assert_noop!(
+			Polkapool::join_weighted_pool_single_asset(Origin::signed(BOB), 0, BTC, POOL / 2 + 1, 0),
+			Error::<Test>::ExceedMaxInRatio
+		);
+		assert_noop!(
+			Polkapool::join_weighted_pool_single_asset(Origin::signed(BOB), 0, BTC, POOL / 10, POOL / 20),
+			Error::<Test>::InsufficientShareIncrement
+		);
+
+		// Once emptied, any amount would pass the in ratio check against a zero balance
+		assert_ok!(Polkapool::exit_weighted_pool(Origin::signed(ALICE), 0, POOL, vec![0, 0]));
+		assert_eq!(Polkapool::weighted_pool(0).unwrap().balances, vec![0, 0]);
+		assert_noop!(
+			Polkapool::join_weighted_pool_single_asset(Origin::signed(BOB), 0, BTC, POOL / 10, 0),
+			Error::<Test>::InsufficientLiquidity
+		);
+	});
+}
+
+#[test]
+fn exit_weighted_pool_single_asset_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_ok!(Polkapool::exit_weighted_pool_single_asset(Origin::signed(ALICE), 0, DOT, POOL / 10, 1));
+
+		// Exiting a tenth of a 50/50 pool in one asset withdraws 1 - 0.9² of its balance,
+		// minus the fee on the implicitly swapped half
+		let amount_out = free_balance(DOT, ALICE) - (INITIAL_BALANCE - POOL);
+		assert!(amount_out > POOL / 10 && amount_out < 19 * POOL / 100);
+		assert_eq!(Polkapool::weighted_pool(0).unwrap().balances, vec![POOL, POOL - amount_out]);
+		assert_eq!(Polkapool::weighted_pool_holdings((ALICE, 0)), 9 * POOL / 10);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::WeightedPoolExited(ALICE, 0, vec![0, amount_out], POOL / 10)));
+	});
+}
+
+#[test]
+fn exit_weighted_pool_single_asset_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_noop!(
+			Polkapool::exit_weighted_pool_single_asset(Origin::signed(ALICE), 0, DOT, 0, 0),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+		assert_noop!(
+			Polkapool::exit_weighted_pool_single_asset(Origin::signed(BOB), 0, DOT, POOL / 10, 0),
+			Error::<Test>::LowShare
+		);
+		assert_noop!(
+			Polkapool::exit_weighted_pool_single_asset(Origin::signed(ALICE), 1, DOT, POOL / 10, 0),
+			Error::<Test>::WeightedPoolNotFound
+		);
+		assert_noop!(
+			Polkapool::exit_weighted_pool_single_asset(Origin::signed(ALICE), 0, ETH, POOL / 10, 0),
+			Error::<Test>::AssetNotInPool
+		);
+		assert_noop!(
+			Polkapool::exit_weighted_pool_single_asset(Origin::signed(ALICE), 0, DOT, 6 * POOL / 10, 0),
+			Error::<Test>::ExceedMaxOutRatio
+		);
+		assert_noop!(
+			Polkapool::exit_weighted_pool_single_asset(Origin::signed(ALICE), 0, DOT, POOL / 10, POOL / 5),
+			Error::<Test>::InsufficientTargetAmount
+		);
+	});
+}
+
+#[test]
+fn swap_weighted_pool_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+		let supply_amount = 1_000_000_000;
+		let target_amount = Polkapool::get_weighted_pool_target_amount(0, BTC, DOT, supply_amount).unwrap();
+		// A 50/50 pool prices like a constant product pool
+		let expected = constant_product_target(POOL, POOL, supply_amount);
+		assert!(target_amount.max(expected) - target_amount.min(expected) < 1_000);
+
+		assert_ok!(Polkapool::swap_weighted_pool(Origin::signed(BOB), 0, BTC, DOT, supply_amount, target_amount));
+
+		assert_eq!(Polkapool::weighted_pool(0).unwrap().balances, vec![POOL + supply_amount, POOL - target_amount]);
+		assert_eq!(free_balance(BTC, BOB), INITIAL_BALANCE - supply_amount);
+		assert_eq!(free_balance(DOT, BOB), INITIAL_BALANCE + target_amount);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::WeightedPoolSwap(BOB, 0, BTC, supply_amount, DOT, target_amount)));
+	});
+}
+
+#[test]
+fn swap_weighted_pool_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_noop!(
+			Polkapool::swap_weighted_pool(Origin::signed(BOB), 1, BTC, DOT, 1_000_000_000, 0),
+			Error::<Test>::WeightedPoolNotFound
+		);
+		assert_noop!(
+			Polkapool::swap_weighted_pool(Origin::signed(BOB), 0, ETH, DOT, 1_000_000_000, 0),
+			Error::<Test>::AssetNotInPool
+		);
+		assert_noop!(
+			Polkapool::swap_weighted_pool(Origin::signed(BOB), 0, BTC, BTC, 1_000_000_000, 0),
+			Error::<Test>::InvalidCurrencyId
+		);
+		assert_noop!(
+			Polkapool::swap_weighted_pool(Origin::signed(BOB), 0, BTC, DOT, POOL / 2 + 1, 0),
+			Error::<Test>::ExceedMaxInRatio
+		);
+		assert_noop!(
+			Polkapool::swap_weighted_pool(Origin::signed(BOB), 0, BTC, DOT, 1, 0),
+			Error::<Test>::ZeroTargetAmount
+		);
+		assert_noop!(
+			Polkapool::swap_weighted_pool(Origin::signed(BOB), 0, BTC, DOT, 1_000_000_000, 1_000_000_000),
+			Error::<Test>::InsufficientTargetAmount
+		);
+	});
+}
+
+/// Callback of flash swaps that does nothing with the assets lent.
+fn noop_call() -> Box<Call> {
+	Box::new(Call::System(frame_system::Call::remark(vec![])))
+}
+
+#[test]
+fn flash_swap_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		let amount_out = 1_000_000_000;
+		let amount_in = 1_004_000_000;
+
+		assert_ok!(Polkapool::flash_swap(Origin::signed(BOB), BTC, DOT, amount_out, 0, amount_in, 0, noop_call()));
+
+		assert_eq!(Polkapool::get_liquidity(BTC, DOT), (POOL - amount_out + amount_in, POOL));
+		assert_eq!(free_balance(BTC, BOB), INITIAL_BALANCE + amount_out - amount_in);
+		assert_eq!(free_balance(BTC, Polkapool::get_wallet_account()), POOL - amount_out + amount_in);
+		assert!(!Polkapool::flash_swap_locked((BTC, DOT)));
+		assert_eq!(last_event(), Event::polkapool(RawEvent::FlashSwap(BOB, BTC, amount_out, amount_in, DOT, 0, 0)));
+
+		// Paid back in the other asset
+		let dot_in = constant_product_supply(POOL + amount_in - amount_out, POOL, amount_out);
+		assert_ok!(Polkapool::flash_swap(Origin::signed(BOB), DOT, BTC, amount_out, 0, 0, dot_in, noop_call()));
+		assert_eq!(Polkapool::get_liquidity(BTC, DOT), (POOL - 2 * amount_out + amount_in + dot_in, POOL));
+	});
+}
+
+#[test]
+fn flash_swap_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		let amount_out = 1_000_000_000;
+		let amount_in = 1_004_000_000;
+
+		assert_noop!(
+			Polkapool::flash_swap(Origin::signed(BOB), BTC, BTC, amount_out, 0, amount_in, 0, noop_call()),
+			Error::<Test>::InvalidCurrencyId
+		);
+		assert_noop!(
+			Polkapool::flash_swap(Origin::signed(BOB), BTC, DOT, 0, 0, amount_in, 0, noop_call()),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+		assert_noop!(
+			Polkapool::flash_swap(Origin::signed(BOB), BTC, ETH, amount_out, 0, amount_in, 0, noop_call()),
+			Error::<Test>::TradingPairNotAllowed
+		);
+		assert_noop!(
+			Polkapool::flash_swap(Origin::signed(BOB), BTC, DOT, POOL, 0, 2 * POOL, 0, noop_call()),
+			Error::<Test>::InsufficientLiquidity
+		);
+		// Paying back the amount lent does not cover the fee
+		assert_noop!(
+			Polkapool::flash_swap(Origin::signed(BOB), BTC, DOT, amount_out, 0, amount_out, 0, noop_call()),
+			Error::<Test>::FlashSwapNotRepaid
+		);
+		// The borrower cannot pay back more than it holds
+		assert_noop!(
+			Polkapool::flash_swap(Origin::signed(CHARLIE), BTC, DOT, amount_out, 0, amount_in, 0, noop_call()),
+			orml_tokens::Error::<Test>::BalanceTooLow
+		);
+		// The callback fails
+		let call = Box::new(Call::Polkapool(crate::Call::swap_with_exact_supply(vec![BTC, ETH], amount_out, 0, None)));
+		assert_noop!(
+			Polkapool::flash_swap(Origin::signed(BOB), BTC, DOT, amount_out, 0, amount_in, 0, call),
+			Error::<Test>::TradingPairNotAllowed
+		);
+	});
+}
+
+#[test]
+fn flash_swap_is_not_repaid_by_deposits_to_other_pools() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		register_pool(BTC, ETH, POOL, POOL);
+		let amount_out = 1_000_000_000;
+		let amount_in = 1_004_000_000;
+		let wallet = Polkapool::get_wallet_account();
+
+		// Adding liquidity to another pool of the same asset moves BTC to the pool account, which
+		// used to count as paying back the flash swap
+		let deposit = Box::new(Call::Polkapool(crate::Call::add_liquidity(BTC, ETH, amount_in, amount_in, 0, None)));
+		assert_noop!(
+			Polkapool::flash_swap(Origin::signed(BOB), BTC, DOT, amount_out, 0, 0, 0, deposit.clone()),
+			Error::<Test>::FlashSwapNotRepaid
+		);
+		let swap = Box::new(Call::Polkapool(crate::Call::swap_with_exact_supply(vec![BTC, ETH], amount_in, 0, None)));
+		assert_noop!(
+			Polkapool::flash_swap(Origin::signed(BOB), BTC, DOT, amount_out, 0, 0, 0, swap),
+			Error::<Test>::FlashSwapNotRepaid
+		);
+
+		// Deposits to other pools stay with those pools
+		assert_ok!(Polkapool::flash_swap(Origin::signed(BOB), BTC, DOT, amount_out, 0, amount_in, 0, deposit));
+		let (btc_dot_pool, _) = Polkapool::get_liquidity(BTC, DOT);
+		let (btc_eth_pool, _) = Polkapool::get_liquidity(BTC, ETH);
+		assert_eq!(btc_dot_pool, POOL - amount_out + amount_in);
+		assert_eq!(btc_eth_pool, POOL + amount_in);
+		assert_eq!(free_balance(BTC, wallet), btc_dot_pool + btc_eth_pool);
+	});
+}
+
+#[test]
+fn pool_is_locked_during_flash_swap() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		let amount_out = 1_000_000_000;
+		let amount_in = 1_004_000_000;
+		let calls = vec![
+			crate::Call::swap_with_exact_supply(vec![BTC, DOT], amount_out, 0, None),
+			crate::Call::swap_with_exact_target(vec![DOT, BTC], amount_out, POOL, None),
+			crate::Call::add_liquidity(BTC, DOT, amount_out, amount_out, 0, None),
+			crate::Call::remove_liquidity(BTC, DOT, amount_out, 0, 0, None),
+			crate::Call::flash_swap(BTC, DOT, amount_out, 0, amount_in, 0, noop_call()),
+			crate::Call::transfer_lp_shares(BOB, BTC, DOT, amount_out),
+		];
+
+		for call in calls {
+			assert_noop!(
+				Polkapool::flash_swap(Origin::signed(ALICE), BTC, DOT, amount_out, 0, amount_in, 0, Box::new(Call::Polkapool(call))),
+				Error::<Test>::PoolLocked
+			);
+		}
+	});
+}
+
+#[test]
+fn transfer_lp_shares_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+
+		assert_ok!(Polkapool::transfer_lp_shares(Origin::signed(ALICE), BOB, DOT, BTC, POOL / 2));
+		assert_eq!(Polkapool::get_lp_holdings(&ALICE, BTC, DOT), POOL / 2);
+		assert_eq!(Polkapool::get_lp_holdings(&BOB, BTC, DOT), POOL / 2);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::LpSharesTransferred(ALICE, BOB, BTC, DOT, POOL / 2)));
+
+		assert_ok!(Polkapool::remove_liquidity(Origin::signed(BOB), BTC, DOT, POOL / 2, POOL / 2, POOL / 2, None));
+		assert_eq!(free_balance(BTC, BOB), INITIAL_BALANCE + POOL / 2);
+	});
+}
+
+#[test]
+fn transfer_lp_shares_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+
+		assert_noop!(
+			Polkapool::transfer_lp_shares(Origin::signed(ALICE), BOB, BTC, DOT, 0),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+		assert_noop!(
+			Polkapool::transfer_lp_shares(Origin::signed(ALICE), BOB, BTC, ETH, POOL / 2),
+			Error::<Test>::TradingPairNotAllowed
+		);
+		assert_noop!(
+			Polkapool::transfer_lp_shares(Origin::signed(ALICE), BOB, BTC, DOT, POOL + 1),
+			Error::<Test>::LowShare
+		);
+	});
+}
+
+#[test]
+fn transfer_weighted_pool_shares_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_ok!(Polkapool::transfer_weighted_pool_shares(Origin::signed(ALICE), BOB, 0, POOL / 2));
+		assert_eq!(Polkapool::weighted_pool_holdings((ALICE, 0)), POOL / 2);
+		assert_eq!(Polkapool::weighted_pool_holdings((BOB, 0)), POOL / 2);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::WeightedPoolSharesTransferred(ALICE, BOB, 0, POOL / 2)));
+	});
+}
+
+#[test]
+fn transfer_weighted_pool_shares_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_weighted_pool();
+
+		assert_noop!(
+			Polkapool::transfer_weighted_pool_shares(Origin::signed(ALICE), BOB, 0, 0),
+			Error::<Test>::ProvidedAmountIsZero
+		);
+		assert_noop!(
+			Polkapool::transfer_weighted_pool_shares(Origin::signed(ALICE), BOB, 1, POOL / 2),
+			Error::<Test>::WeightedPoolNotFound
+		);
+		assert_noop!(
+			Polkapool::transfer_weighted_pool_shares(Origin::signed(BOB), ALICE, 0, 1),
+			Error::<Test>::LowShare
+		);
+	});
+}
+
+#[test]
+fn create_concentrated_pool_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		// The price is given for the first asset passed, the pool stores it for the larger one
+		assert_ok!(Polkapool::create_concentrated_pool(Origin::signed(ALICE), DOT, BTC, Permill::from_perthousand(3), 60, Q64 / 2));
+
+		let pool = Polkapool::concentrated_pool(0).unwrap();
+		assert_eq!((pool.currency_id_0, pool.currency_id_1), (BTC, DOT));
+		assert_eq!(pool.sqrt_price, 2 * Q64);
+		assert_eq!(pool.liquidity, 0);
+		assert_eq!(Polkapool::next_concentrated_pool_id(), 1);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::ConcentratedPoolCreated(ALICE, 0, BTC, DOT, Permill::from_perthousand(3), 60)));
+	});
+}
+
+#[test]
+fn create_concentrated_pool_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		let fee = Permill::from_perthousand(3);
+
+		assert_noop!(
+			Polkapool::create_concentrated_pool(Origin::signed(ALICE), BTC, BTC, fee, 60, Q64),
+			Error::<Test>::InvalidCurrencyId
+		);
+		assert_noop!(
+			Polkapool::create_concentrated_pool(Origin::signed(ALICE), BTC, DOT, Permill::from_percent(20), 60, Q64),
+			Error::<Test>::InvalidConcentratedPool
+		);
+		assert_noop!(
+			Polkapool::create_concentrated_pool(Origin::signed(ALICE), BTC, DOT, fee, 0, Q64),
+			Error::<Test>::InvalidConcentratedPool
+		);
+		assert_noop!(
+			Polkapool::create_concentrated_pool(Origin::signed(ALICE), BTC, DOT, fee, 16_385, Q64),
+			Error::<Test>::InvalidConcentratedPool
+		);
+		assert_noop!(
+			Polkapool::create_concentrated_pool(Origin::signed(ALICE), BTC, DOT, fee, 60, 1),
+			Error::<Test>::InvalidSqrtPrice
+		);
+		assert_noop!(
+			Polkapool::create_concentrated_pool(Origin::signed(ALICE), DOT, BTC, fee, 60, 0),
+			Error::<Test>::InvalidSqrtPrice
+		);
+	});
+}
+
+#[test]
+fn mint_position_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		let (amount_0, amount_1) = create_concentrated_position();
+
+		// At price 1 a symmetric range needs about as much of both assets
+		assert!(amount_0 > 0 && amount_1 > 0);
+		assert!(amount_0.max(amount_1) - amount_0.min(amount_1) <= 2);
+		assert_eq!(free_balance(BTC, ALICE), INITIAL_BALANCE - amount_0);
+		assert_eq!(free_balance(DOT, ALICE), INITIAL_BALANCE - amount_1);
+
+		let position = Polkapool::position(0).unwrap();
+		assert_eq!((position.owner, position.pool_id, position.liquidity), (ALICE, 0, LIQUIDITY));
+		assert_eq!(Polkapool::concentrated_pool(0).unwrap().liquidity, LIQUIDITY);
+		assert_eq!(Polkapool::initialized_ticks(0), vec![-600, 600]);
+		assert_eq!(Polkapool::next_position_id(), 1);
+	});
+}
+
+#[test]
+fn mint_position_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_concentrated_position();
+
+		assert_noop!(
+			Polkapool::mint_position(Origin::signed(BOB), 0, -600, 600, 0, POOL, POOL),
+			Error::<Test>::InvalidLiquidityIncrement
+		);
+		assert_noop!(
+			Polkapool::mint_position(Origin::signed(BOB), 1, -600, 600, LIQUIDITY, POOL, POOL),
+			Error::<Test>::ConcentratedPoolNotFound
+		);
+		assert_noop!(
+			Polkapool::mint_position(Origin::signed(BOB), 0, 600, -600, LIQUIDITY, POOL, POOL),
+			Error::<Test>::InvalidTickRange
+		);
+		assert_noop!(
+			Polkapool::mint_position(Origin::signed(BOB), 0, -610, 600, LIQUIDITY, POOL, POOL),
+			Error::<Test>::InvalidTickRange
+		);
+		assert_noop!(
+			Polkapool::mint_position(Origin::signed(BOB), 0, -600, 600, LIQUIDITY, 1, POOL),
+			Error::<Test>::ExcessiveSupplyAmount
+		);
+
+		// `MaxTicksPerPool` is 4, two of them are used by the first position
+		assert_ok!(Polkapool::mint_position(Origin::signed(BOB), 0, -1200, 1200, LIQUIDITY, POOL, POOL));
+		assert_noop!(
+			Polkapool::mint_position(Origin::signed(BOB), 0, -1800, 1800, LIQUIDITY, POOL, POOL),
+			Error::<Test>::TooManyTicks
+		);
+		// Existing ticks can still be used
+		assert_ok!(Polkapool::mint_position(Origin::signed(BOB), 0, -600, 1200, LIQUIDITY, POOL, POOL));
+	});
+}
+
+#[test]
+fn burn_position_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_concentrated_position();
+
+		assert_ok!(Polkapool::burn_position(Origin::signed(ALICE), 0, LIQUIDITY / 2, 0, 0));
+		assert_eq!(Polkapool::position(0).unwrap().liquidity, LIQUIDITY / 2);
+		assert_eq!(Polkapool::concentrated_pool(0).unwrap().liquidity, LIQUIDITY / 2);
+
+		assert_ok!(Polkapool::burn_position(Origin::signed(ALICE), 0, LIQUIDITY / 2, 0, 0));
+		match last_event() {
+			Event::polkapool(RawEvent::PositionBurned(ALICE, 0, liquidity, _, _)) if liquidity == LIQUIDITY / 2 => {}
+			event => panic!("Unexpected event {:?}", event),
+		}
+		assert_eq!(Polkapool::position(0), None);
+		assert_eq!(Polkapool::initialized_ticks(0), Vec::<i32>::new());
+		assert_eq!(Polkapool::concentrated_pool(0).unwrap().liquidity, 0);
+
+		// Deposits are rounded up and withdrawals down, in favour of the pool
+		let (lost_0, lost_1) = (INITIAL_BALANCE - free_balance(BTC, ALICE), INITIAL_BALANCE - free_balance(DOT, ALICE));
+		assert!(lost_0 <= 3 && lost_1 <= 3);
+	});
+}
+
+#[test]
+fn burn_position_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_concentrated_position();
+
+		assert_noop!(
+			Polkapool::burn_position(Origin::signed(ALICE), 1, LIQUIDITY, 0, 0),
+			Error::<Test>::PositionNotFound
+		);
+		assert_noop!(
+			Polkapool::burn_position(Origin::signed(BOB), 0, LIQUIDITY, 0, 0),
+			Error::<Test>::NotPositionOwner
+		);
+		assert_noop!(
+			Polkapool::burn_position(Origin::signed(ALICE), 0, 0, 0, 0),
+			Error::<Test>::LowShare
+		);
+		assert_noop!(
+			Polkapool::burn_position(Origin::signed(ALICE), 0, LIQUIDITY + 1, 0, 0),
+			Error::<Test>::LowShare
+		);
+		assert_noop!(
+			Polkapool::burn_position(Origin::signed(ALICE), 0, LIQUIDITY, POOL, 0),
+			Error::<Test>::InsufficientTargetAmount
+		);
+	});
+}
+
+#[test]
+fn swap_concentrated_pool_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_concentrated_position();
+		let supply_amount = 1_000_000_000;
+
+		assert_ok!(Polkapool::swap_concentrated_pool(Origin::signed(BOB), 0, BTC, supply_amount, 1, None));
+
+		let target_amount = free_balance(DOT, BOB) - INITIAL_BALANCE;
+		assert!(target_amount > 0 && target_amount < supply_amount * 997 / 1000);
+		assert_eq!(free_balance(BTC, BOB), INITIAL_BALANCE - supply_amount);
+		assert!(Polkapool::concentrated_pool(0).unwrap().sqrt_price < Q64);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::ConcentratedPoolSwap(BOB, 0, BTC, supply_amount, DOT, target_amount)));
+
+		// And back, the price moves up again
+		assert_ok!(Polkapool::swap_concentrated_pool(Origin::signed(BOB), 0, DOT, target_amount, 1, None));
+		assert!(free_balance(BTC, BOB) < INITIAL_BALANCE);
+	});
+}
+
+#[test]
+fn swap_concentrated_pool_crosses_ticks() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_concentrated_position();
+		assert_ok!(Polkapool::mint_position(Origin::signed(BOB), 0, -120, 120, LIQUIDITY, POOL, POOL));
+		assert_eq!(Polkapool::concentrated_pool(0).unwrap().liquidity, 2 * LIQUIDITY);
+
+		// Out of the range of position 1, only position 0 provides liquidity
+		assert_ok!(Polkapool::swap_concentrated_pool(Origin::signed(BOB), 0, BTC, 20_000_000_000, 1, None));
+		let pool = Polkapool::concentrated_pool(0).unwrap();
+		assert!(pool.tick < -120 && pool.tick >= -600);
+		assert_eq!(pool.liquidity, LIQUIDITY);
+		// Fees earned above the tick are now outside of it
+		let fee_growth_outside_0 = Polkapool::tick(0, -120).unwrap().fee_growth_outside_0;
+		assert!(fee_growth_outside_0 > 0 && fee_growth_outside_0 < pool.fee_growth_global_0);
+
+		// And back in range
+		assert_ok!(Polkapool::swap_concentrated_pool(Origin::signed(BOB), 0, DOT, 30_000_000_000, 1, None));
+		let pool = Polkapool::concentrated_pool(0).unwrap();
+		assert!(pool.tick >= -120 && pool.tick < 120);
+		assert_eq!(pool.liquidity, 2 * LIQUIDITY);
+	});
+}
+
+#[test]
+fn swap_concentrated_pool_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_concentrated_position();
+
+		assert_noop!(
+			Polkapool::swap_concentrated_pool(Origin::signed(BOB), 0, BTC, 0, 0, None),
+			Error::<Test>::ZeroSupplyAmount
+		);
+		assert_noop!(
+			Polkapool::swap_concentrated_pool(Origin::signed(BOB), 1, BTC, 1_000_000_000, 0, None),
+			Error::<Test>::ConcentratedPoolNotFound
+		);
+		assert_noop!(
+			Polkapool::swap_concentrated_pool(Origin::signed(BOB), 0, ETH, 1_000_000_000, 0, None),
+			Error::<Test>::AssetNotInPool
+		);
+		// Selling the first asset moves the price down
+		assert_noop!(
+			Polkapool::swap_concentrated_pool(Origin::signed(BOB), 0, BTC, 1_000_000_000, 0, Some(Q64 + 1)),
+			Error::<Test>::InvalidSqrtPrice
+		);
+		assert_noop!(
+			Polkapool::swap_concentrated_pool(Origin::signed(BOB), 0, BTC, 1_000_000_000, 1_000_000_000, None),
+			Error::<Test>::InsufficientTargetAmount
+		);
+	});
+}
+
+#[test]
+fn collect_position_fees_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_concentrated_position();
+		assert_ok!(Polkapool::swap_concentrated_pool(Origin::signed(BOB), 0, BTC, 1_000_000_000, 1, None));
+		let btc_before = free_balance(BTC, ALICE);
+
+		assert_ok!(Polkapool::collect_position_fees(Origin::signed(ALICE), 0));
+
+		// The only position earns the whole 0.3% fee, paid in the asset sold
+		let fee_0 = free_balance(BTC, ALICE) - btc_before;
+		assert!(fee_0 > 2_990_000 && fee_0 <= 3_000_000);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::PositionFeesCollected(ALICE, 0, fee_0, 0)));
+
+		// Fees are only paid once
+		assert_ok!(Polkapool::collect_position_fees(Origin::signed(ALICE), 0));
+		assert_eq!(last_event(), Event::polkapool(RawEvent::PositionFeesCollected(ALICE, 0, 0, 0)));
+	});
+}
+
+#[test]
+fn collect_position_fees_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_concentrated_position();
+
+		assert_noop!(Polkapool::collect_position_fees(Origin::signed(ALICE), 1), Error::<Test>::PositionNotFound);
+		assert_noop!(Polkapool::collect_position_fees(Origin::signed(BOB), 0), Error::<Test>::NotPositionOwner);
+	});
+}
+
+#[test]
+fn transfer_position_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_concentrated_position();
+
+		assert_ok!(Polkapool::transfer_position(Origin::signed(ALICE), BOB, 0));
+		assert_eq!(Polkapool::position(0).unwrap().owner, BOB);
+		assert_eq!(last_event(), Event::polkapool(RawEvent::PositionTransferred(ALICE, BOB, 0)));
+
+		assert_noop!(Polkapool::burn_position(Origin::signed(ALICE), 0, LIQUIDITY, 0, 0), Error::<Test>::NotPositionOwner);
+		assert_ok!(Polkapool::burn_position(Origin::signed(BOB), 0, LIQUIDITY, 0, 0));
+		assert!(free_balance(BTC, BOB) > INITIAL_BALANCE);
+	});
+}
+
+#[test]
+fn transfer_position_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_concentrated_position();
+
+		assert_noop!(Polkapool::transfer_position(Origin::signed(ALICE), BOB, 1), Error::<Test>::PositionNotFound);
+		assert_noop!(Polkapool::transfer_position(Origin::signed(BOB), BOB, 0), Error::<Test>::NotPositionOwner);
+	});
+}
+
+#[test]
+fn get_twap_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, 2 * POOL);
+		run_to_block(5);
+
+		// One BTC is worth two DOT since the pool was registered
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 3).map_err(DispatchError::from), Ok(FixedU128::saturating_from_integer(2)));
+		assert_eq!(Polkapool::get_twap(DOT, BTC, 3).map_err(DispatchError::from), Ok(FixedU128::saturating_from_rational(1, 2)));
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 4).map_err(DispatchError::from), Ok(FixedU128::saturating_from_integer(2)));
+	});
+}
+
+#[test]
+fn get_twap_weights_prices_by_blocks() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, 2 * POOL);
+		run_to_block(5);
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], POOL / 10, 0, None));
+		let (pool_btc, pool_dot) = Polkapool::get_liquidity(BTC, DOT);
+		let new_price = FixedU128::checked_from_rational(pool_dot, pool_btc).unwrap();
+		let twap = |old_price_blocks: u128, new_price_blocks: u128| {
+			FixedU128::saturating_from_integer(2 * old_price_blocks)
+				.saturating_add(new_price.saturating_mul(FixedU128::saturating_from_integer(new_price_blocks)))
+				.checked_div(&FixedU128::saturating_from_integer(old_price_blocks + new_price_blocks))
+		};
+		run_to_block(9);
+
+		// Only the new price since the swap
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 3).ok(), Some(new_price));
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 4).ok(), twap(0, 4));
+		// Both prices, the window starting between the registration and the swap
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 6).ok(), twap(2, 4));
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 8).ok(), twap(4, 4));
+
+		// Skipped blocks without any swap keep accumulating the last price
+		run_to_block(100);
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 90).ok(), Some(new_price));
+	});
+}
+
+#[test]
+fn get_twap_of_stable_swap_pool_follows_the_curve() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, DOT, POOL, POOL / 2, PoolKind::StableSwap { amplification: 100 }));
+		run_to_block(5);
+
+		// The marginal price of the curve, much closer to 1 than the ratio of the reserves
+		let price = stable_swap::spot_price(POOL, POOL / 2, 100).unwrap();
+		assert!(price > FixedU128::saturating_from_rational(9, 10) && price < FixedU128::one());
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 3).ok(), Some(price));
+		assert_eq!(Polkapool::get_twap(DOT, BTC, 3).ok(), stable_swap::spot_price(POOL / 2, POOL, 100));
+
+		// Prices are weighted by blocks as in constant product pools
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![DOT, BTC], POOL / 10, 0, None));
+		let (pool_btc, pool_dot) = Polkapool::get_liquidity(BTC, DOT);
+		let new_price = stable_swap::spot_price(pool_btc, pool_dot, 100).unwrap();
+		run_to_block(9);
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 4).ok(), Some(new_price));
+		assert_eq!(
+			Polkapool::get_twap(BTC, DOT, 8).ok(),
+			price.saturating_add(new_price).checked_div(&FixedU128::saturating_from_integer(2)),
+		);
+	});
+}
+
+#[test]
+fn quote_stable_swap_price_impact_uses_the_curve_price() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Polkapool::register_swap_pair(Origin::signed(ALICE), BTC, DOT, POOL, POOL / 2, PoolKind::StableSwap { amplification: 100 }));
+
+		// Priced at the reserve ratio, a small swap of DOT would lose about half its value
+		let quote = Polkapool::quote_swap_with_exact_supply(&[DOT, BTC], 1_000_000).unwrap();
+		assert!(quote.price_impacts[0] < Permill::from_perthousand(1));
+		assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![DOT, BTC], 1_000_000, 0, Some(Permill::from_percent(1))));
+	});
+}
+
+#[test]
+fn get_twap_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 1).map_err(DispatchError::from), Err(Error::<Test>::TradingPairNotAllowed.into()));
+
+		register_pool(BTC, DOT, POOL, POOL);
+		run_to_block(5);
+		let unavailable: Result<FixedU128, DispatchError> = Err(Error::<Test>::TwapWindowUnavailable.into());
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 0).map_err(DispatchError::from), unavailable);
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 6).map_err(DispatchError::from), unavailable);
+		// Starts before the pool was registered at block 1
+		assert_eq!(Polkapool::get_twap(BTC, DOT, 5).map_err(DispatchError::from), unavailable);
+	});
+}
+
+#[test]
+fn get_twap_keeps_twap_observation_period_observations() {
+	ExtBuilder::default().build().execute_with(|| {
+		register_pool(BTC, DOT, POOL, POOL);
+		// One observation at registration and one per block with a swap
+		for block in 2..=TwapObservationPeriod::get() as BlockNumber + 1 {
+			run_to_block(block);
+			assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], 1_000_000, 0, None));
+			assert_ok!(Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![DOT, BTC], 1_000_000, 0, None));
+		}
+		run_to_block(20);
+
+		// The observation of the registration block has been overwritten
+		assert_eq!(Polkapool::price_observation((BTC, DOT), 0).map(|observation| observation.last_update), Some(11));
+		assert_eq!(Polkapool::latest_price_observation((BTC, DOT)), 0);
+		assert!(Polkapool::get_twap(BTC, DOT, 18).is_ok());
+		assert_eq!(
+			Polkapool::get_twap(BTC, DOT, 19).map_err(DispatchError::from),
+			Err(Error::<Test>::TwapWindowUnavailable.into())
+		);
+	});
+}
+
+proptest! {
+	#![proptest_config(ProptestConfig::with_cases(64))]
+
+	/// Swaps pay their fee to the pool, so `pool_0 * pool_1` never decreases.
+	#[test]
+	fn constant_product_invariant_never_decreases(
+		pool_0 in 1_000u128..1_000_000_000_000_000,
+		pool_1 in 1_000u128..1_000_000_000_000_000,
+		swaps in prop::collection::vec((any::<bool>(), any::<bool>(), 1u128..1_000_000_000_000_000), 1..10),
+	) {
+		ExtBuilder::default().build().execute_with(|| {
+			register_pool(BTC, DOT, pool_0, pool_1);
+			let mut invariant = pool_invariant(BTC, DOT);
+			for (exact_supply, sell_btc, amount) in swaps {
+				let path = if sell_btc { vec![BTC, DOT] } else { vec![DOT, BTC] };
+				// Rejected swaps leave the pool untouched
+				let _ = if exact_supply {
+					Polkapool::swap_with_exact_supply(Origin::signed(BOB), path, amount, 0, None)
+				} else {
+					Polkapool::swap_with_exact_target(Origin::signed(BOB), path, amount, INITIAL_BALANCE, None)
+				};
+				let new_invariant = pool_invariant(BTC, DOT);
+				assert!(new_invariant >= invariant, "invariant decreased from {} to {}", invariant, new_invariant);
+				invariant = new_invariant;
+			}
+		});
+	}
+
+	/// Adding liquidity and removing all of it again never returns more than was deposited, and
+	/// never takes anything from the other liquidity providers.
+	#[test]
+	fn add_remove_round_trip_creates_no_value(
+		pool_0 in 1_000u128..1_000_000_000_000_000,
+		pool_1 in 1_000u128..1_000_000_000_000_000,
+		amount_0 in 1u128..1_000_000_000_000_000,
+		amount_1 in 1u128..1_000_000_000_000_000,
+	) {
+		ExtBuilder::default().build().execute_with(|| {
+			register_pool(BTC, DOT, pool_0, pool_1);
+			if Polkapool::add_liquidity(Origin::signed(BOB), BTC, DOT, amount_0, amount_1, 0, None).is_err() {
+				return;
+			}
+			let shares = Polkapool::get_lp_holdings(&BOB, BTC, DOT);
+			assert_ok!(Polkapool::remove_liquidity(Origin::signed(BOB), BTC, DOT, shares, 0, 0, None));
+
+			assert!(free_balance(BTC, BOB) <= INITIAL_BALANCE);
+			assert!(free_balance(DOT, BOB) <= INITIAL_BALANCE);
+			let (new_pool_0, new_pool_1, total_shares) = Polkapool::get_pool_reserves(BTC, DOT);
+			assert_eq!(total_shares, Polkapool::get_lp_holdings(&ALICE, BTC, DOT));
+			assert!(new_pool_0 >= pool_0 && new_pool_1 >= pool_1);
+		});
+	}
+
+	/// The same holds for single asset liquidity, which swaps through the pool on the way in
+	/// and on the way out.
+	#[test]
+	fn single_asset_round_trip_creates_no_value(
+		pool_0 in 1_000_000u128..1_000_000_000_000_000,
+		pool_1 in 1_000_000u128..1_000_000_000_000_000,
+		supply_amount in 1u128..1_000_000_000_000_000,
+	) {
+		ExtBuilder::default().build().execute_with(|| {
+			register_pool(BTC, DOT, pool_0, pool_1);
+			if Polkapool::add_liquidity_single_asset(Origin::signed(BOB), BTC, DOT, supply_amount, 0, None).is_err() {
+				return;
+			}
+			let shares = Polkapool::get_lp_holdings(&BOB, BTC, DOT);
+			// Selling the DOT withdrawn may be rejected, withdrawing both assets never is
+			if Polkapool::remove_liquidity_single_asset(Origin::signed(BOB), BTC, DOT, shares, 0, None).is_err() {
+				assert_ok!(Polkapool::remove_liquidity(Origin::signed(BOB), BTC, DOT, shares, 0, 0, None));
+			}
+
+			// Rounding dust of DOT may be left with BOB, it is worth less than the BTC spent
+			let btc_spent = INITIAL_BALANCE - free_balance(BTC, BOB);
+			let dot_left = free_balance(DOT, BOB).saturating_sub(INITIAL_BALANCE);
+			let (new_pool_0, new_pool_1) = Polkapool::get_liquidity(BTC, DOT);
+			assert!(U256::from(dot_left) * U256::from(new_pool_0) <= U256::from(btc_spent) * U256::from(new_pool_1));
+			assert!(pool_invariant(BTC, DOT) >= U256::from(pool_0) * U256::from(pool_1));
+		});
+	}
+
+	/// Swapping along a path gives the same result as swapping hop by hop.
+	#[test]
+	fn path_swap_equals_chained_single_swaps(
+		pool_btc in 1_000u128..1_000_000_000_000_000,
+		pool_dot_0 in 1_000u128..1_000_000_000_000_000,
+		pool_dot_1 in 1_000u128..1_000_000_000_000_000,
+		pool_usdt in 1_000u128..1_000_000_000_000_000,
+		exact_supply in any::<bool>(),
+		amount in 1u128..1_000_000_000_000_000,
+	) {
+		let new_test_ext = || {
+			let mut ext = ExtBuilder::default().build();
+			ext.execute_with(|| {
+				register_pool(BTC, DOT, pool_btc, pool_dot_0);
+				register_pool(DOT, USDT, pool_dot_1, pool_usdt);
+			});
+			ext
+		};
+		let outcome = || (
+			free_balance(BTC, BOB),
+			free_balance(DOT, BOB),
+			free_balance(USDT, BOB),
+			Polkapool::get_liquidity(BTC, DOT),
+			Polkapool::get_liquidity(DOT, USDT),
+		);
+
+		let path = new_test_ext().execute_with(|| {
+			let result = if exact_supply {
+				Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT, USDT], amount, 0, None)
+			} else {
+				Polkapool::swap_with_exact_target(Origin::signed(BOB), vec![BTC, DOT, USDT], amount, INITIAL_BALANCE, None)
+			};
+			(result.is_ok(), outcome())
+		});
+		let chained = new_test_ext().execute_with(|| {
+			let result = if exact_supply {
+				let dot_before = free_balance(DOT, BOB);
+				Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![BTC, DOT], amount, 0, None)
+					.and_then(|_| Polkapool::swap_with_exact_supply(Origin::signed(BOB), vec![DOT, USDT], free_balance(DOT, BOB) - dot_before, 0, None))
+			} else {
+				Polkapool::quote_swap_with_exact_target(&[DOT, USDT], amount)
+					.and_then(|quote| Polkapool::swap_with_exact_target(Origin::signed(BOB), vec![BTC, DOT], quote.amounts[0], INITIAL_BALANCE, None))
+					.and_then(|_| Polkapool::swap_with_exact_target(Origin::signed(BOB), vec![DOT, USDT], amount, INITIAL_BALANCE, None))
+			};
+			(result.is_ok(), outcome())
+		});
+
+		prop_assert_eq!(path.0, chained.0);
+		if path.0 {
+			prop_assert_eq!(path.1, chained.1);
+		}
+	}
+}
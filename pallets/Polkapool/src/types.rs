@@ -0,0 +1,165 @@
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_arithmetic::FixedU128;
+use sp_arithmetic::traits::{AtLeast32BitUnsigned, UniqueSaturatedInto};
+use sp_runtime::{Permill, RuntimeDebug};
+use frame_support::sp_std::vec::Vec;
+
+/// Cumulative prices of a liquidity pool, in the style of Uniswap v2 price accumulators.
+///
+/// `price_0_cumulative` is the sum over blocks of the price of the first asset of the pair
+/// quoted in the second one (`pool_1 / pool_0`), `price_1_cumulative` is the inverse.
+/// The difference between two snapshots divided by the number of blocks between them is the
+/// time-weighted average price over that window.
+#[derive(Encode, Decode, Copy, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct PriceAccumulator<BlockNumber> {
+    pub price_0_cumulative: FixedU128,
+    pub price_1_cumulative: FixedU128,
+    pub last_update: BlockNumber,
+}
+
+/// Result of quoting a swap along a trading path.
+///
+/// `amounts` has one entry per asset of the path, `fees` and `price_impacts` have one entry per
+/// hop. Fees are charged in the supply asset of the hop.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct SwapQuote<Balance> {
+    pub amounts: Vec<Balance>,
+    pub fees: Vec<Balance>,
+    pub price_impacts: Vec<Permill>,
+}
+
+/// A trading path found by the router together with its quote.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct SwapRoute<AssetId, Balance> {
+    pub path: Vec<AssetId>,
+    pub quote: SwapQuote<Balance>,
+}
+
+/// Pricing curve of a liquidity pool, chosen at registration.
+#[derive(Encode, Decode, Copy, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum PoolKind {
+    /// Uniswap v2 style `x * y = k` pool.
+    ConstantProduct,
+    /// Curve style StableSwap pool with the given amplification coefficient.
+    StableSwap { amplification: u32 },
+}
+
+/// Amplification coefficient of a StableSwap pool, linearly ramped between two blocks.
+#[derive(Encode, Decode, Copy, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct AmplificationRamp<BlockNumber> {
+    pub initial_amplification: u32,
+    pub future_amplification: u32,
+    pub initial_block: BlockNumber,
+    pub future_block: BlockNumber,
+}
+
+impl<BlockNumber: AtLeast32BitUnsigned + Copy> AmplificationRamp<BlockNumber> {
+    /// An amplification coefficient that does not change.
+    pub fn fixed(amplification: u32, now: BlockNumber) -> Self {
+        AmplificationRamp {
+            initial_amplification: amplification,
+            future_amplification: amplification,
+            initial_block: now,
+            future_block: now,
+        }
+    }
+
+    /// Amplification coefficient in effect at block `now`.
+    pub fn amplification_at(&self, now: BlockNumber) -> u32 {
+        if now >= self.future_block || self.future_block <= self.initial_block {
+            return self.future_amplification;
+        }
+        let elapsed: u128 = now.saturating_sub(self.initial_block).unique_saturated_into();
+        let duration: u128 = (self.future_block - self.initial_block).unique_saturated_into();
+        let initial = self.initial_amplification as u128;
+        let future = self.future_amplification as u128;
+        let amplification = if future > initial {
+            initial + (future - initial) * elapsed / duration
+        } else {
+            initial - (initial - future) * elapsed / duration
+        };
+        amplification as u32
+    }
+}
+
+/// Balancer style pool of up to `MaxWeightedPoolAssets` assets with normalised weights.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct WeightedPool<AssetId, Balance> {
+    /// Member assets, without duplicates.
+    pub assets: Vec<AssetId>,
+    /// Weight of each asset, summing to one.
+    pub weights: Vec<Permill>,
+    /// Balance of each asset held by the pool.
+    pub balances: Vec<Balance>,
+    /// Total LP shares of the pool.
+    pub total_shares: Balance,
+    /// Fee charged on swaps and on the swapped part of single-asset joins and exits.
+    pub swap_fee: Permill,
+}
+
+impl<AssetId: PartialEq, Balance> WeightedPool<AssetId, Balance> {
+    /// Position of `asset` in the pool, if it is a member.
+    pub fn index_of(&self, asset: &AssetId) -> Option<usize> {
+        self.assets.iter().position(|member| member == asset)
+    }
+}
+
+/// Uniswap v3 style pool where liquidity is provided in price ranges.
+///
+/// Prices are those of `currency_id_0` quoted in `currency_id_1`, see `concentrated_math`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct ConcentratedPool<AssetId> {
+    pub currency_id_0: AssetId,
+    pub currency_id_1: AssetId,
+    /// Fee charged on the supply amount of swaps, earned by the liquidity in range.
+    pub fee: Permill,
+    /// Bounds of positions must be multiples of the tick spacing.
+    pub tick_spacing: u32,
+    /// Current square root price in Q64.64.
+    pub sqrt_price: u128,
+    /// Greatest tick whose square root price is at most `sqrt_price`.
+    pub tick: i32,
+    /// Liquidity of the positions whose range contains the current price.
+    pub liquidity: u128,
+    /// Fees of `currency_id_0` earned per unit of liquidity since creation, in Q64.64.
+    pub fee_growth_global_0: u128,
+    /// Fees of `currency_id_1` earned per unit of liquidity since creation, in Q64.64.
+    pub fee_growth_global_1: u128,
+}
+
+/// A tick of a concentrated liquidity pool used as a bound by at least one position.
+#[derive(Encode, Decode, Copy, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct TickInfo {
+    /// Liquidity of all positions using the tick as a bound.
+    pub liquidity_gross: u128,
+    /// Liquidity added when the price crosses the tick upwards, removed when it crosses downwards.
+    pub liquidity_net: i128,
+    /// Fee growth of `currency_id_0` on the other side of the tick from the current price.
+    pub fee_growth_outside_0: u128,
+    /// Fee growth of `currency_id_1` on the other side of the tick from the current price.
+    pub fee_growth_outside_1: u128,
+}
+
+/// Liquidity provided by an account in a price range of a concentrated liquidity pool.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct Position<AccountId, PoolId> {
+    pub owner: AccountId,
+    pub pool_id: PoolId,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    /// Fee growth inside the range when fees were last paid out to the owner.
+    pub fee_growth_inside_0_last: u128,
+    pub fee_growth_inside_1_last: u128,
+}
@@ -0,0 +1,123 @@
+//! Balancer style weighted pool math.
+//!
+//! Pools keep `Π balance_i ^ weight_i` constant, with weights normalised to sum to one.
+//! Balances never go through `FixedU128` themselves, only their ratios, so any `u128`
+//! balance is supported.
+
+use sp_arithmetic::{FixedPointNumber, FixedU128, Permill, PerThing};
+use sp_arithmetic::traits::{CheckedDiv, CheckedMul, Saturating, Zero};
+
+/// Maximum number of terms of the binomial series used for fractional powers.
+const MAX_POW_ITERATIONS: u32 = 100;
+/// Fractional powers are approximated until a term is smaller than this.
+const POW_PRECISION: u128 = 100_000_000;
+
+/// The input of a swap or join can be at most half the pool balance.
+pub fn max_in_ratio() -> FixedU128 {
+    FixedU128::saturating_from_rational(1u128, 2u128)
+}
+
+/// The output of a swap or exit can be at most a third of the pool balance.
+pub fn max_out_ratio() -> FixedU128 {
+    FixedU128::saturating_from_rational(1u128, 3u128)
+}
+
+fn weight_ratio(numerator: Permill, denominator: Permill) -> Option<FixedU128> {
+    FixedU128::checked_from_rational(numerator.deconstruct(), denominator.deconstruct())
+}
+
+fn weight_to_fixed(weight: Permill) -> FixedU128 {
+    FixedU128::saturating_from_rational(weight.deconstruct(), Permill::ACCURACY)
+}
+
+/// Returns `|a - b|` and whether `a < b`.
+fn sub_sign(a: FixedU128, b: FixedU128) -> (FixedU128, bool) {
+    if a >= b {
+        (a.saturating_sub(b), false)
+    } else {
+        (b.saturating_sub(a), true)
+    }
+}
+
+/// `base ^ exp` for a base in `(0, 2)`.
+pub fn pow(base: FixedU128, exp: FixedU128) -> Option<FixedU128> {
+    let whole = exp.trunc();
+    let remain = exp.saturating_sub(whole);
+    let whole_exp = (whole.into_inner() / FixedU128::accuracy()) as usize;
+    let whole_pow = base.saturating_pow(whole_exp);
+    if remain.is_zero() {
+        return Some(whole_pow);
+    }
+    let partial_result = pow_approx(base, remain)?;
+    whole_pow.checked_mul(&partial_result)
+}
+
+/// `base ^ exp` for an exponent in `[0, 1)` using the binomial series
+/// `(1 + x) ^ a = 1 + a x + a (a - 1) x² / 2! + ...`
+fn pow_approx(base: FixedU128, exp: FixedU128) -> Option<FixedU128> {
+    let one = FixedU128::one();
+    let precision = FixedU128::from_inner(POW_PRECISION);
+    let (x, x_negative) = sub_sign(base, one);
+    let mut term = one;
+    let mut sum = term;
+    let mut negative = false;
+
+    for i in 1..=MAX_POW_ITERATIONS {
+        let big_k = FixedU128::saturating_from_integer(i);
+        let (c, c_negative) = sub_sign(exp, big_k.saturating_sub(one));
+        term = term.checked_mul(&c.checked_mul(&x)?)?.checked_div(&big_k)?;
+        if term.is_zero() {
+            break;
+        }
+        if x_negative {
+            negative = !negative;
+        }
+        if c_negative {
+            negative = !negative;
+        }
+        if negative {
+            sum = sum.saturating_sub(term);
+        } else {
+            sum = sum.saturating_add(term);
+        }
+        if term < precision {
+            break;
+        }
+    }
+    Some(sum)
+}
+
+/// Amount of the output asset received for `amount_in`:
+/// `balance_out * (1 - (balance_in / (balance_in + amount_in * (1 - fee))) ^ (weight_in / weight_out))`
+pub fn calc_out_given_in(balance_in: u128, weight_in: Permill, balance_out: u128, weight_out: Permill,
+                         amount_in: u128, swap_fee: Permill) -> Option<u128> {
+    let amount_in = amount_in.saturating_sub(swap_fee.mul_ceil(amount_in));
+    let ratio = FixedU128::checked_from_rational(balance_in, balance_in.checked_add(amount_in)?)?;
+    let power = pow(ratio, weight_ratio(weight_in, weight_out)?)?;
+    Some(FixedU128::one().saturating_sub(power).saturating_mul_int(balance_out))
+}
+
+/// Shares minted for depositing `amount_in` of a single asset. Only the part of the deposit that
+/// would have to be swapped into the other assets pays the swap fee.
+pub fn calc_shares_out_given_single_in(balance_in: u128, weight_in: Permill, total_shares: u128,
+                                       amount_in: u128, swap_fee: Permill) -> Option<u128> {
+    let taxable = Permill::one().saturating_sub(weight_in);
+    let fee = swap_fee.saturating_mul(taxable);
+    let amount_in = amount_in.saturating_sub(fee.mul_ceil(amount_in));
+    let ratio = FixedU128::checked_from_rational(balance_in.checked_add(amount_in)?, balance_in)?;
+    let power = pow(ratio, weight_to_fixed(weight_in))?;
+    Some(power.saturating_sub(FixedU128::one()).saturating_mul_int(total_shares))
+}
+
+/// Amount of a single asset received for burning `shares_in`. Only the part of the withdrawal that
+/// would have to be swapped from the other assets pays the swap fee.
+pub fn calc_single_out_given_shares_in(balance_out: u128, weight_out: Permill, total_shares: u128,
+                                       shares_in: u128, swap_fee: Permill) -> Option<u128> {
+    let ratio = FixedU128::checked_from_rational(total_shares.checked_sub(shares_in)?, total_shares)?;
+    let exponent = FixedU128::one().checked_div(&weight_to_fixed(weight_out))?;
+    let power = pow(ratio, exponent)?;
+    let amount_out = FixedU128::one().saturating_sub(power).saturating_mul_int(balance_out);
+    let taxable = Permill::one().saturating_sub(weight_out);
+    let fee = swap_fee.saturating_mul(taxable);
+    Some(amount_out.saturating_sub(fee.mul_ceil(amount_out)))
+}
@@ -0,0 +1,346 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Routes swaps across the resting orders of the orderbook engine and the Polkapool AMM.
+//!
+//! Resting limit orders are reported and signed by a registered provider of the orderbook engine,
+//! and submitted along with the swap. The supply is split between the orders and the pool of both
+//! assets so that the trader gets the most of the target asset, and both legs are settled in the
+//! same transaction. Order fills are shared with the engine, so an order matched here cannot be
+//! settled again by a provider and the other way around.
+
+use codec::Encode;
+use frame_support::{decl_error, decl_event, decl_module, dispatch, ensure, transactional};
+use frame_support::dispatch::{DispatchError, DispatchResult};
+use frame_support::traits::Get;
+use frame_support::weights::Weight;
+use frame_system::ensure_signed;
+use orml_traits::MultiCurrency;
+use orderbook_engine::types::{Order, OrderType};
+use sp_core::{Hasher, U256};
+use sp_runtime::Permill;
+use sp_runtime::traits::{Saturating, UniqueSaturatedFrom, UniqueSaturatedInto, Verify, Zero};
+use sp_std::vec;
+use sp_std::vec::Vec;
+
+use types::{BookFill, HybridRoute, ProviderBook};
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+pub mod types;
+
+/// Number of iterations of the search for the best split between the orderbook and the pool.
+const SPLIT_SEARCH_ITERATIONS: u32 = 32;
+/// Fixed point accuracy of orderbook prices, the inner value of a `FixedU128`.
+const PRICE_ACCURACY: u128 = 1_000_000_000_000_000_000;
+
+pub type BalanceOf<T> = <T as polkapool::Config>::Balance;
+pub type OrderOf<T> = Order<BalanceOf<T>, <T as frame_system::Config>::AccountId, <T as polkapool::Config>::CurrencyId, <T as orderbook_engine::Config>::Signature>;
+pub type ProviderBookOf<T> = ProviderBook<<T as frame_system::Config>::AccountId, <T as frame_system::Config>::BlockNumber, OrderOf<T>, <T as orderbook_engine::Config>::Signature>;
+
+/// Configure the pallet by specifying the parameters and types on which it depends.
+pub trait Config: polkapool::Config + orderbook_engine::Config<Balance = <Self as polkapool::Config>::Balance, AssetId = <Self as polkapool::Config>::CurrencyId> {
+    /// Because this pallet emits events, it depends on the runtime's definition of an event.
+    type Event: From<Event<Self>> + Into<<Self as frame_system::Config>::Event>;
+    /// Asset in which orderbook prices are quoted
+    type QuoteAsset: Get<Self::CurrencyId>;
+    /// Maximum number of maker orders matched by a single swap
+    type MaxMakerOrders: Get<u32>;
+    /// Fee on the amount bought from orderbook orders, paid by the taker
+    type TradingFee: Get<Permill>;
+    /// Account receiving the orderbook trading fees
+    type TradingFeeAccount: Get<Self::AccountId>;
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as frame_system::Config>::AccountId,
+		AssetId = <T as polkapool::Config>::CurrencyId,
+		Balance = BalanceOf<T>
+	{
+		/// Swap routed across the orderbook and Polkapool. \[trader, supply_currency_id, supply_amount, target_currency_id, target_amount, pool_supply_amount, book_supply_amount\]
+		HybridSwap(AccountId, AssetId, Balance, AssetId, Balance, Balance, Balance),
+	}
+);
+
+decl_error! {
+	pub enum Error for Module<T: Config> {
+		/// Neither or both of the assets are the quote asset of the orderbook
+		NotAnOrderbookMarket,
+		/// Maker order is not a limit order on the side and market of the swap
+		InvalidMakerOrder,
+		/// Too many maker orders
+		TooManyMakerOrders,
+		/// Supply amount is zero
+		ZeroSupplyAmount,
+		/// Target amount is less than the minimum
+		InsufficientTargetAmount,
+		/// Overflow while pricing an order
+		PriceOverflow,
+		/// The maker orders are not signed by a registered provider
+		InvalidProviderSignature,
+		/// The maker orders are past their last valid block
+		MakerOrdersExpired,
+		/// A maker cannot pay for its fill
+		InsufficientMakerBalance,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Swaps up to `supply_amount` for the target asset, split between resting orders of the
+		/// orderbook and the Polkapool pool of both assets for the best output.
+        /// # Arguments
+        ///
+        /// * `origin` - This contains the detail of Origin from where Transaction originated.
+        ///
+        /// * `supply_currency_id` - Asset sold.
+        ///
+        /// * `target_currency_id` - Asset bought.
+        ///
+        /// * `supply_amount` - Maximum amount sold.
+        ///
+        /// * `min_target_amount` - Acceptable minimum amount bought.
+        ///
+        /// * `maker_orders` - Resting limit orders of the market, signed by a registered provider.
+		#[weight = 10000u64.saturating_add(1000u64.saturating_mul(maker_orders.orders.len() as Weight))]
+		pub fn swap_with_hybrid_route(origin, supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId, #[compact] supply_amount: BalanceOf<T>,
+		                              #[compact] min_target_amount: BalanceOf<T>, maker_orders: ProviderBookOf<T>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::verify_provider_book(&maker_orders)?;
+			Self::do_swap_with_hybrid_route(&who, supply_currency_id, target_currency_id, supply_amount, min_target_amount, maker_orders.orders)?;
+			Ok(())
+		}
+	}
+}
+
+/// Side of the orderbook taken by a swap.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    /// Supplies the quote asset for the base asset, matching asks.
+    Buy,
+    /// Supplies the base asset for the quote asset, matching bids.
+    Sell,
+}
+
+impl<T: Config> Module<T> {
+    /// Checks that the maker orders are signed by a registered provider and still valid.
+    pub fn verify_provider_book(book: &ProviderBookOf<T>) -> DispatchResult {
+        ensure!(orderbook_engine::Module::<T>::get_providers(&book.provider).is_some(), Error::<T>::InvalidProviderSignature);
+        ensure!(<frame_system::Module<T>>::block_number() <= book.valid_until, Error::<T>::MakerOrdersExpired);
+        let msg = (book.valid_until, &book.orders).using_encoded(<T as frame_system::Config>::Hashing::hash);
+        ensure!(book.signature.verify(&(msg.encode()[..]), &book.provider), Error::<T>::InvalidProviderSignature);
+        Ok(())
+    }
+
+    /// Swaps along the best split between `maker_orders` and Polkapool. The orders must come
+    /// from a registered provider, see `verify_provider_book`.
+    #[transactional]
+    pub fn do_swap_with_hybrid_route(who: &T::AccountId, supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId, supply_amount: BalanceOf<T>,
+                                     min_target_amount: BalanceOf<T>, maker_orders: Vec<OrderOf<T>>) -> DispatchResult {
+        let (route, fills) = Self::find_route(supply_currency_id, target_currency_id, supply_amount, &maker_orders)?;
+        let trading_fees: Vec<BalanceOf<T>> = fills.iter().map(|fill| T::TradingFee::get() * fill.target_amount).collect();
+        let trading_fee = trading_fees.iter().fold(Zero::zero(), |total: BalanceOf<T>, fee| total.saturating_add(*fee));
+        let target_amount = route.pool_target_amount.saturating_add(route.book_target_amount).saturating_sub(trading_fee);
+        ensure!(target_amount >= min_target_amount, Error::<T>::InsufficientTargetAmount);
+
+        for (fill, fee) in fills.iter().zip(trading_fees.into_iter()) {
+            let order = &maker_orders[fill.order_index as usize];
+            // Several orders of a maker may add up to more than it holds
+            ensure!(
+                <T as polkapool::Config>::Currency::free_balance(target_currency_id, &order.trader) >= fill.target_amount,
+                Error::<T>::InsufficientMakerBalance
+            );
+            <T as polkapool::Config>::Currency::transfer(supply_currency_id, who, &order.trader, fill.supply_amount)?;
+            <T as polkapool::Config>::Currency::transfer(target_currency_id, &order.trader, who, fill.target_amount)?;
+            <T as polkapool::Config>::Currency::transfer(target_currency_id, who, &T::TradingFeeAccount::get(), fee)?;
+            orderbook_engine::Module::<T>::fill_order(order, who, fill.quantity)?;
+        }
+
+        if !route.pool_supply_amount.is_zero() {
+            let path = vec![supply_currency_id, target_currency_id];
+            polkapool::Module::<T>::do_swap_with_exact_supply(who, &path, route.pool_supply_amount, route.pool_target_amount, None)?;
+        }
+
+        Self::deposit_event(RawEvent::HybridSwap(
+            who.clone(),
+            supply_currency_id,
+            route.pool_supply_amount.saturating_add(route.book_supply_amount),
+            target_currency_id,
+            target_amount,
+            route.pool_supply_amount,
+            route.book_supply_amount,
+        ));
+        Ok(())
+    }
+
+    /// Quotes the best split of a swap between `maker_orders` and Polkapool.
+    pub fn quote_hybrid_route(supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId, supply_amount: BalanceOf<T>,
+                              maker_orders: &[OrderOf<T>]) -> sp_std::result::Result<HybridRoute<BalanceOf<T>>, DispatchError> {
+        Self::find_route(supply_currency_id, target_currency_id, supply_amount, maker_orders).map(|(route, _)| route)
+    }
+
+    /// Searches the split of `supply_amount` giving the most output, along with the order fills
+    /// of its orderbook leg. The output is concave in the amount sent to the pool, the sum of a
+    /// pool curve and of orders matched from the best price down, so a ternary search finds it.
+    fn find_route(supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId, supply_amount: BalanceOf<T>,
+                  maker_orders: &[OrderOf<T>]) -> sp_std::result::Result<(HybridRoute<BalanceOf<T>>, Vec<BookFill<BalanceOf<T>>>), DispatchError> {
+        ensure!(!supply_amount.is_zero(), Error::<T>::ZeroSupplyAmount);
+        ensure!(maker_orders.len() <= T::MaxMakerOrders::get() as usize, Error::<T>::TooManyMakerOrders);
+        let side = Self::side(supply_currency_id, target_currency_id)?;
+        let book = Self::sorted_book(side, supply_currency_id, target_currency_id, maker_orders)?;
+
+        let evaluate = |pool_supply_amount: u128| -> sp_std::result::Result<(HybridRoute<BalanceOf<T>>, Vec<BookFill<BalanceOf<T>>>), DispatchError> {
+            let pool_supply_amount = BalanceOf::<T>::unique_saturated_from(pool_supply_amount);
+            let pool_target_amount = Self::pool_target_amount(supply_currency_id, target_currency_id, pool_supply_amount);
+            let (fills, book_supply_amount, book_target_amount) = Self::match_book(side, &book, supply_amount.saturating_sub(pool_supply_amount))?;
+            // A pool that cannot take its share leaves it with the trader
+            let pool_supply_amount = if pool_target_amount.is_zero() { Zero::zero() } else { pool_supply_amount };
+            Ok((HybridRoute { pool_supply_amount, pool_target_amount, book_supply_amount, book_target_amount }, fills))
+        };
+        let output = |route: &HybridRoute<BalanceOf<T>>| route.pool_target_amount.saturating_add(route.book_target_amount);
+
+        let supply: u128 = supply_amount.unique_saturated_into();
+        let (mut low, mut high) = (0u128, supply);
+        for _ in 0..SPLIT_SEARCH_ITERATIONS {
+            if high - low < 3 {
+                break;
+            }
+            let third = (high - low) / 3;
+            let (mid_low, mid_high) = (low + third, high - third);
+            if output(&evaluate(mid_low)?.0) < output(&evaluate(mid_high)?.0) {
+                low = mid_low;
+            } else {
+                high = mid_high;
+            }
+        }
+
+        let mut best = evaluate(0)?;
+        for candidate in [low, high, supply].iter() {
+            let route = evaluate(*candidate)?;
+            if output(&route.0) > output(&best.0) {
+                best = route;
+            }
+        }
+        Ok(best)
+    }
+
+    fn side(supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId) -> sp_std::result::Result<Side, Error<T>> {
+        let quote_asset = T::QuoteAsset::get();
+        if supply_currency_id == quote_asset && target_currency_id != quote_asset {
+            Ok(Side::Buy)
+        } else if target_currency_id == quote_asset && supply_currency_id != quote_asset {
+            Ok(Side::Sell)
+        } else {
+            Err(Error::<T>::NotAnOrderbookMarket)
+        }
+    }
+
+    /// Checks the maker orders and returns their indices, prices and the quantities their makers
+    /// can still fill, best price first.
+    fn sorted_book(side: Side, supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId,
+                   maker_orders: &[OrderOf<T>]) -> sp_std::result::Result<Vec<(u32, BalanceOf<T>, BalanceOf<T>)>, Error<T>> {
+        let (base_asset, order_type) = match side {
+            Side::Buy => (target_currency_id, OrderType::AskLimit),
+            Side::Sell => (supply_currency_id, OrderType::BidLimit),
+        };
+        let mut book = Vec::with_capacity(maker_orders.len());
+        for (index, order) in maker_orders.iter().enumerate() {
+            ensure!(order.asset_id == base_asset && order.token_id.is_none() && order.order_type == order_type, Error::<T>::InvalidMakerOrder);
+            ensure!(!order.price.is_zero() && orderbook_engine::Module::<T>::verify_signature(order), Error::<T>::InvalidMakerOrder);
+            // Makers pay the target asset: the base asset for asks, the quote asset for bids
+            let maker_balance = <T as polkapool::Config>::Currency::free_balance(target_currency_id, &order.trader);
+            let affordable = match side {
+                Side::Buy => maker_balance,
+                Side::Sell => Self::div_price(maker_balance, order.price).ok_or(Error::<T>::PriceOverflow)?,
+            };
+            let quantity = sp_std::cmp::min(orderbook_engine::Module::<T>::remaining_quantity(order), affordable);
+            book.push((index as u32, order.price, quantity));
+        }
+        match side {
+            Side::Buy => book.sort_by(|a, b| a.1.cmp(&b.1)),
+            Side::Sell => book.sort_by(|a, b| b.1.cmp(&a.1)),
+        }
+        Ok(book)
+    }
+
+    /// Matches `supply_amount` against the sorted book, returning the fills and the supply and
+    /// target amounts they add up to. Makers are paid rounded up and pay rounded down.
+    fn match_book(side: Side, book: &[(u32, BalanceOf<T>, BalanceOf<T>)], supply_amount: BalanceOf<T>)
+                  -> sp_std::result::Result<(Vec<BookFill<BalanceOf<T>>>, BalanceOf<T>, BalanceOf<T>), Error<T>> {
+        let mut fills = Vec::new();
+        let mut remaining_supply = supply_amount;
+        let mut book_supply_amount: BalanceOf<T> = Zero::zero();
+        let mut book_target_amount: BalanceOf<T> = Zero::zero();
+        for (order_index, price, remaining_quantity) in book.iter() {
+            if remaining_supply.is_zero() {
+                break;
+            }
+            let (quantity, fill_supply_amount, fill_target_amount) = match side {
+                Side::Buy => {
+                    let affordable = Self::div_price(remaining_supply, *price).ok_or(Error::<T>::PriceOverflow)?;
+                    let quantity = sp_std::cmp::min(*remaining_quantity, affordable);
+                    (quantity, Self::mul_price(quantity, *price, true).ok_or(Error::<T>::PriceOverflow)?, quantity)
+                }
+                Side::Sell => {
+                    let quantity = sp_std::cmp::min(*remaining_quantity, remaining_supply);
+                    (quantity, quantity, Self::mul_price(quantity, *price, false).ok_or(Error::<T>::PriceOverflow)?)
+                }
+            };
+            if quantity.is_zero() || fill_target_amount.is_zero() {
+                continue;
+            }
+            remaining_supply = remaining_supply.saturating_sub(fill_supply_amount);
+            book_supply_amount = book_supply_amount.saturating_add(fill_supply_amount);
+            book_target_amount = book_target_amount.saturating_add(fill_target_amount);
+            fills.push(BookFill { order_index: *order_index, quantity, supply_amount: fill_supply_amount, target_amount: fill_target_amount });
+        }
+        Ok((fills, book_supply_amount, book_target_amount))
+    }
+
+    /// Output of swapping `supply_amount` through the pool of both assets, zero when the pool
+    /// does not exist or rejects the swap.
+    fn pool_target_amount(supply_currency_id: T::CurrencyId, target_currency_id: T::CurrencyId, supply_amount: BalanceOf<T>) -> BalanceOf<T> {
+        if supply_amount.is_zero() {
+            return Zero::zero();
+        }
+        polkapool::Module::<T>::quote_swap_with_exact_supply(&[supply_currency_id, target_currency_id], supply_amount)
+            .ok()
+            .and_then(|quote| quote.amounts.last().copied())
+            .unwrap_or_else(Zero::zero)
+    }
+
+    /// `amount * price`, `price` being the inner value of a `FixedU128`.
+    fn mul_price(amount: BalanceOf<T>, price: BalanceOf<T>, round_up: bool) -> Option<BalanceOf<T>> {
+        let product = Self::to_u256(amount).checked_mul(Self::to_u256(price))?;
+        let accuracy = U256::from(PRICE_ACCURACY);
+        let mut result = product / accuracy;
+        if round_up && !(product % accuracy).is_zero() {
+            result = result + U256::one();
+        }
+        Self::from_u256(result)
+    }
+
+    /// `amount / price`, rounded down, `price` being the inner value of a `FixedU128`.
+    fn div_price(amount: BalanceOf<T>, price: BalanceOf<T>) -> Option<BalanceOf<T>> {
+        let numerator = Self::to_u256(amount).checked_mul(U256::from(PRICE_ACCURACY))?;
+        Self::from_u256(numerator / Self::to_u256(price))
+    }
+
+    fn to_u256(value: BalanceOf<T>) -> U256 {
+        U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(value))
+    }
+
+    fn from_u256(value: U256) -> Option<BalanceOf<T>> {
+        if value > U256::from(u128::max_value()) {
+            return None;
+        }
+        Some(BalanceOf::<T>::unique_saturated_from(value.low_u128()))
+    }
+}
@@ -0,0 +1,216 @@
+use crate as hybrid_router;
+use codec::Encode;
+use frame_support::parameter_types;
+use frame_system::EnsureRoot;
+use orderbook_engine::types::{Order, OrderType};
+use orml_traits::parameter_type_with_key;
+use polkadex_primitives::assets;
+use sp_core::{sr25519, Pair, H160, H256};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, Hash, IdentifyAccount, IdentityLookup, Verify},
+	MultiSignature, Permill,
+};
+
+use crate::types::ProviderBook;
+
+pub type Signature = MultiSignature;
+pub type Public = <Signature as Verify>::Signer;
+pub type AccountId = <Public as IdentifyAccount>::AccountId;
+pub type Balance = u128;
+pub type Amount = i128;
+pub type AssetId = assets::AssetId;
+pub type BlockNumber = u64;
+pub type TestOrder = Order<Balance, AccountId, AssetId, Signature>;
+pub type TestBook = ProviderBook<AccountId, BlockNumber, TestOrder, Signature>;
+
+/// Quote asset of the orderbook.
+pub const QUOTE: AssetId = AssetId::POLKADEX;
+pub const DOT: AssetId = AssetId::ChainsafeErc20(H160([2u8; 20]));
+
+/// Balance of each asset given to the test accounts at genesis.
+pub const INITIAL_BALANCE: Balance = 1_000_000_000_000_000_000;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		Tokens: orml_tokens::{Module, Call, Storage, Event<T>, Config<T>},
+		Nft: nft::{Module, Call, Storage, Event<T>},
+		OrderbookEngine: orderbook_engine::{Module, Call, Storage, Event<T>},
+		Polkapool: polkapool::{Module, Call, Storage, Event<T>},
+		HybridRouter: hybrid_router::{Module, Call, Event<T>},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const SS58Prefix: u8 = 42;
+}
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = SS58Prefix;
+}
+
+parameter_type_with_key! {
+	pub ExistentialDeposits: |_currency_id: AssetId| -> Balance {
+		0
+	};
+}
+
+impl orml_tokens::Config for Test {
+	type Event = Event;
+	type Balance = Balance;
+	type Amount = Amount;
+	type CurrencyId = AssetId;
+	type WeightInfo = ();
+	type ExistentialDeposits = ExistentialDeposits;
+	type OnDust = ();
+}
+
+parameter_types! {
+	pub const MaxMetadataLength: u32 = 256;
+}
+
+impl nft::Config for Test {
+	type Event = Event;
+	type MaxMetadataLength = MaxMetadataLength;
+}
+
+parameter_types! {
+	pub const FreeSettlementsPerBlock: u32 = 2;
+}
+
+impl orderbook_engine::Config for Test {
+	type Event = Event;
+	type Balance = Balance;
+	type AssetId = AssetId;
+	type Nft = Nft;
+	type Public = Public;
+	type Signature = Signature;
+	type FreeSettlementsPerBlock = FreeSettlementsPerBlock;
+	type AdminOrigin = EnsureRoot<AccountId>;
+}
+
+parameter_types! {
+	pub const TradingPathLimit: usize = 3;
+	pub const TwapObservationPeriod: u32 = 10;
+	pub const MaxAmplification: u32 = 1_000_000;
+	pub const MinAmplificationRampDuration: BlockNumber = 10;
+	pub const MaxWeightedPoolAssets: u32 = 4;
+	pub const MaxTicksPerPool: u32 = 4;
+	pub ProtocolFeeAccount: AccountId = account("ProtocolFee");
+}
+
+impl polkapool::Config for Test {
+	type Event = Event;
+	type TradingPathLimit = TradingPathLimit;
+	type Balance = Balance;
+	type TwapObservationPeriod = TwapObservationPeriod;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+	type MaxAmplification = MaxAmplification;
+	type MinAmplificationRampDuration = MinAmplificationRampDuration;
+	type MaxWeightedPoolAssets = MaxWeightedPoolAssets;
+	type MaxTicksPerPool = MaxTicksPerPool;
+	type CurrencyId = AssetId;
+	type Currency = Tokens;
+	type ProtocolFeeAccount = ProtocolFeeAccount;
+	type Call = Call;
+}
+
+parameter_types! {
+	pub const QuoteAsset: AssetId = QUOTE;
+	pub const MaxMakerOrders: u32 = 4;
+	pub const TradingFee: Permill = Permill::from_perthousand(2);
+	pub TradingFeeAccount: AccountId = account("TradingFee");
+}
+
+impl hybrid_router::Config for Test {
+	type Event = Event;
+	type QuoteAsset = QuoteAsset;
+	type MaxMakerOrders = MaxMakerOrders;
+	type TradingFee = TradingFee;
+	type TradingFeeAccount = TradingFeeAccount;
+}
+
+/// sr25519 test key derived from `//<name>`.
+pub fn key(name: &str) -> sr25519::Pair {
+	sr25519::Pair::from_string(&format!("//{}", name), None).expect("static values are valid; qed")
+}
+
+/// Account id of the key derived from `//<name>`.
+pub fn account(name: &str) -> AccountId {
+	Public::from(key(name).public()).into_account()
+}
+
+/// Builds a limit order of the trader of `pair` and signs it the way the engine expects.
+pub fn signed_order(pair: &sr25519::Pair, price: Balance, quantity: Balance, order_type: OrderType, nonce: u64) -> TestOrder {
+	let msg = BlakeTwo256::hash_of(&(price, quantity, order_type, nonce, DOT, None::<sp_core::U256>));
+	Order {
+		price,
+		quantity,
+		order_type,
+		trader: Public::from(pair.public()).into_account(),
+		nonce,
+		asset_id: DOT,
+		token_id: None,
+		signature: pair.sign(&msg.encode()).into(),
+	}
+}
+
+/// Signs `orders` as reported by the provider of `pair` until block `valid_until`.
+pub fn signed_book(pair: &sr25519::Pair, valid_until: BlockNumber, orders: Vec<TestOrder>) -> TestBook {
+	let msg = BlakeTwo256::hash_of(&(valid_until, &orders));
+	ProviderBook {
+		provider: Public::from(pair.public()).into_account(),
+		valid_until,
+		orders,
+		signature: pair.sign(&msg.encode()).into(),
+	}
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+	let mut endowed_accounts = Vec::new();
+	for name in ["Alice", "Bob", "Charlie", "Dave"].iter() {
+		for currency_id in [QUOTE, DOT].iter() {
+			endowed_accounts.push((account(name), *currency_id, INITIAL_BALANCE));
+		}
+	}
+	orml_tokens::GenesisConfig::<Test> { endowed_accounts }.assimilate_storage(&mut t).unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	// Events are not recorded in the genesis block
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
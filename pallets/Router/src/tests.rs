@@ -0,0 +1,187 @@
+use crate::{mock::*, Error, RawEvent};
+use frame_support::{assert_noop, assert_ok};
+use orderbook_engine::types::OrderType::{AskLimit, BidLimit};
+use orml_traits::MultiCurrency;
+use polkapool::types::PoolKind;
+use sp_runtime::Permill;
+
+/// 0.9 units of the quote asset per unit of `DOT`, as the inner value of a `FixedU128`.
+const PRICE: Balance = 900_000_000_000_000_000;
+const QUANTITY: Balance = 100_000_000_000;
+/// Cost of `QUANTITY` at `PRICE`.
+const COST: Balance = 90_000_000_000;
+const POOL: Balance = 1_000_000_000_000_000;
+const VALID_UNTIL: BlockNumber = 10;
+
+fn free_balance(currency_id: AssetId, who: &AccountId) -> Balance {
+	<Tokens as MultiCurrency<AccountId>>::free_balance(currency_id, who)
+}
+
+fn last_event() -> Event {
+	System::events().pop().expect("Event expected").event
+}
+
+fn trading_fee(amount: Balance) -> Balance {
+	Permill::from_perthousand(2) * amount
+}
+
+fn register_provider() {
+	assert_ok!(OrderbookEngine::register_provider(Origin::root(), account("Ferdie"), 1));
+}
+
+fn register_pool() {
+	assert_ok!(Polkapool::register_swap_pair(Origin::signed(account("Dave")), DOT, QUOTE, POOL, POOL, PoolKind::ConstantProduct));
+}
+
+fn book(orders: Vec<TestOrder>) -> TestBook {
+	signed_book(&key("Ferdie"), VALID_UNTIL, orders)
+}
+
+fn swap(who: &str, supply_currency_id: AssetId, target_currency_id: AssetId, supply_amount: Balance, min_target_amount: Balance,
+        maker_orders: TestBook) -> frame_support::dispatch::DispatchResult {
+	HybridRouter::swap_with_hybrid_route(Origin::signed(account(who)), supply_currency_id, target_currency_id, supply_amount, min_target_amount, maker_orders)
+}
+
+#[test]
+fn swap_with_hybrid_route_splits_between_book_and_pool() {
+	new_test_ext().execute_with(|| {
+		register_provider();
+		register_pool();
+		let ask = signed_order(&key("Alice"), PRICE, QUANTITY, AskLimit, 1);
+		let supply_amount = 10 * COST;
+		let route = HybridRouter::quote_hybrid_route(QUOTE, DOT, supply_amount, &[ask.clone()]).unwrap();
+		let pool_only = Polkapool::quote_swap_with_exact_supply(&[QUOTE, DOT], supply_amount).unwrap().amounts[1];
+
+		// The cheaper ask takes about its cost and the rest goes to the pool
+		assert!(route.book_supply_amount > 0 && route.book_supply_amount <= COST);
+		assert!(route.pool_supply_amount > 0 && route.pool_supply_amount + route.book_supply_amount <= supply_amount);
+		assert!(route.pool_target_amount + route.book_target_amount > pool_only);
+
+		assert_ok!(swap("Charlie", QUOTE, DOT, supply_amount, 0, book(vec![ask])));
+		let target_amount = route.pool_target_amount + route.book_target_amount - trading_fee(route.book_target_amount);
+		assert_eq!(free_balance(DOT, &account("Charlie")), INITIAL_BALANCE + target_amount);
+		assert_eq!(free_balance(QUOTE, &account("Charlie")), INITIAL_BALANCE - route.pool_supply_amount - route.book_supply_amount);
+		assert_eq!(free_balance(QUOTE, &account("Alice")), INITIAL_BALANCE + route.book_supply_amount);
+		assert_eq!(OrderbookEngine::order_fills(account("Alice"), 1), route.book_target_amount);
+		assert_eq!(
+			last_event(),
+			Event::hybrid_router(RawEvent::HybridSwap(
+				account("Charlie"),
+				QUOTE,
+				route.pool_supply_amount + route.book_supply_amount,
+				DOT,
+				target_amount,
+				route.pool_supply_amount,
+				route.book_supply_amount,
+			)),
+		);
+	});
+}
+
+#[test]
+fn swap_with_hybrid_route_pays_trading_fee() {
+	new_test_ext().execute_with(|| {
+		register_provider();
+		let ask = signed_order(&key("Alice"), PRICE, QUANTITY, AskLimit, 1);
+		let fee = trading_fee(QUANTITY);
+
+		// The fee counts against the minimum target amount
+		assert_noop!(
+			swap("Charlie", QUOTE, DOT, COST, QUANTITY - fee + 1, book(vec![ask.clone()])),
+			Error::<Test>::InsufficientTargetAmount
+		);
+		assert_ok!(swap("Charlie", QUOTE, DOT, COST, QUANTITY - fee, book(vec![ask])));
+		assert_eq!(free_balance(DOT, &account("Charlie")), INITIAL_BALANCE + QUANTITY - fee);
+		assert_eq!(free_balance(DOT, &account("TradingFee")), fee);
+		assert_eq!(free_balance(DOT, &account("Alice")), INITIAL_BALANCE - QUANTITY);
+		assert_eq!(last_event(), Event::hybrid_router(RawEvent::HybridSwap(account("Charlie"), QUOTE, COST, DOT, QUANTITY - fee, 0, COST)));
+	});
+}
+
+#[test]
+fn swap_with_hybrid_route_sells_to_bids() {
+	new_test_ext().execute_with(|| {
+		register_provider();
+		let bid = signed_order(&key("Bob"), PRICE, QUANTITY, BidLimit, 1);
+
+		assert_ok!(swap("Charlie", DOT, QUOTE, QUANTITY, 0, book(vec![bid])));
+		assert_eq!(free_balance(QUOTE, &account("Charlie")), INITIAL_BALANCE + COST - trading_fee(COST));
+		assert_eq!(free_balance(QUOTE, &account("TradingFee")), trading_fee(COST));
+		assert_eq!(free_balance(DOT, &account("Bob")), INITIAL_BALANCE + QUANTITY);
+	});
+}
+
+#[test]
+fn swap_with_hybrid_route_does_not_overfill_orders() {
+	new_test_ext().execute_with(|| {
+		register_provider();
+		let ask = signed_order(&key("Alice"), PRICE, QUANTITY, AskLimit, 1);
+		assert_ok!(OrderbookEngine::fill_order(&ask, &account("Bob"), 2 * QUANTITY / 5));
+
+		// Only the unfilled quantity is matched, the rest of the supply is kept
+		assert_ok!(swap("Charlie", QUOTE, DOT, COST, 0, book(vec![ask.clone()])));
+		let quantity = 3 * QUANTITY / 5;
+		assert_eq!(free_balance(DOT, &account("Charlie")), INITIAL_BALANCE + quantity - trading_fee(quantity));
+		assert_eq!(free_balance(QUOTE, &account("Charlie")), INITIAL_BALANCE - 3 * COST / 5);
+		assert_eq!(OrderbookEngine::order_fills(account("Alice"), 1), QUANTITY);
+
+		assert_noop!(swap("Charlie", QUOTE, DOT, COST, 1, book(vec![ask])), Error::<Test>::InsufficientTargetAmount);
+	});
+}
+
+#[test]
+fn swap_with_hybrid_route_rejects_orders_on_the_wrong_side() {
+	new_test_ext().execute_with(|| {
+		register_provider();
+		let bid = signed_order(&key("Bob"), PRICE, QUANTITY, BidLimit, 1);
+		let ask = signed_order(&key("Alice"), PRICE, QUANTITY, AskLimit, 1);
+
+		// Buying matches asks and selling matches bids
+		assert_noop!(swap("Charlie", QUOTE, DOT, COST, 0, book(vec![bid])), Error::<Test>::InvalidMakerOrder);
+		assert_noop!(swap("Charlie", DOT, QUOTE, QUANTITY, 0, book(vec![ask])), Error::<Test>::InvalidMakerOrder);
+	});
+}
+
+#[test]
+fn swap_with_hybrid_route_requires_orders_signed_by_a_registered_provider() {
+	new_test_ext().execute_with(|| {
+		let ask = signed_order(&key("Alice"), PRICE, QUANTITY, AskLimit, 1);
+		assert_noop!(swap("Charlie", QUOTE, DOT, COST, 0, book(vec![ask.clone()])), Error::<Test>::InvalidProviderSignature);
+
+		register_provider();
+		// Signed by another key
+		let mut forged = book(vec![ask.clone()]);
+		forged.signature = signed_book(&key("Eve"), VALID_UNTIL, vec![ask.clone()]).signature;
+		assert_noop!(swap("Charlie", QUOTE, DOT, COST, 0, forged), Error::<Test>::InvalidProviderSignature);
+		// Orders added after signing
+		let mut extended = book(vec![ask.clone()]);
+		extended.orders.push(signed_order(&key("Bob"), PRICE, QUANTITY, AskLimit, 1));
+		assert_noop!(swap("Charlie", QUOTE, DOT, COST, 0, extended), Error::<Test>::InvalidProviderSignature);
+
+		System::set_block_number(VALID_UNTIL + 1);
+		assert_noop!(swap("Charlie", QUOTE, DOT, COST, 0, book(vec![ask])), Error::<Test>::MakerOrdersExpired);
+	});
+}
+
+#[test]
+fn swap_with_hybrid_route_checks_maker_balances() {
+	new_test_ext().execute_with(|| {
+		register_provider();
+		let held = QUANTITY / 2;
+		assert_ok!(<Tokens as MultiCurrency<AccountId>>::transfer(DOT, &account("Alice"), &account("Dave"), INITIAL_BALANCE - held));
+
+		// Two orders each within the maker's balance but not together
+		let asks = vec![
+			signed_order(&key("Alice"), PRICE, 2 * QUANTITY / 5, AskLimit, 1),
+			signed_order(&key("Alice"), PRICE, 2 * QUANTITY / 5, AskLimit, 2),
+		];
+		assert_noop!(swap("Charlie", QUOTE, DOT, COST, 0, book(asks)), Error::<Test>::InsufficientMakerBalance);
+
+		// An order larger than the maker's balance is matched up to the balance
+		let ask = signed_order(&key("Alice"), PRICE, QUANTITY, AskLimit, 3);
+		assert_ok!(swap("Charlie", QUOTE, DOT, COST, 0, book(vec![ask])));
+		assert_eq!(free_balance(DOT, &account("Alice")), 0);
+		assert_eq!(free_balance(DOT, &account("Charlie")), INITIAL_BALANCE + held - trading_fee(held));
+		assert_eq!(OrderbookEngine::order_fills(account("Alice"), 3), held);
+	});
+}
@@ -0,0 +1,46 @@
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+/// Split of a swap between the resting orders of the orderbook and a Polkapool pool.
+///
+/// The supply amounts may add up to less than the requested supply when the orderbook cannot
+/// absorb its share, the remainder is then left with the trader.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct HybridRoute<Balance> {
+    pub pool_supply_amount: Balance,
+    pub pool_target_amount: Balance,
+    pub book_supply_amount: Balance,
+    pub book_target_amount: Balance,
+}
+
+/// Quantity of a resting order matched by a route, with the amounts exchanged with its maker.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct BookFill<Balance> {
+    /// Index of the order in the maker orders of the swap.
+    pub order_index: u32,
+    /// Quantity of the base asset.
+    pub quantity: Balance,
+    /// Amount paid by the taker to the maker, in the supply asset.
+    pub supply_amount: Balance,
+    /// Amount paid by the maker to the taker, in the target asset.
+    pub target_amount: Balance,
+}
+
+/// Resting orders of a market reported by a registered provider, which signs them along with the
+/// last block they can be matched in so that traders cannot submit orders cancelled since.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ProviderBook<AccountId, BlockNumber, Order, Signature> {
+    /// Registered provider of the orderbook engine reporting the orders.
+    pub provider: AccountId,
+    /// Last block in which the orders can be matched.
+    pub valid_until: BlockNumber,
+    /// Resting limit orders of the market.
+    pub orders: Vec<Order>,
+    /// Signature of the provider over `(valid_until, orders)`.
+    pub signature: Signature,
+}
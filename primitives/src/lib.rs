@@ -2,3 +2,4 @@
 
 pub mod engine;
 pub mod assets;
+pub mod nft;
@@ -0,0 +1,21 @@
+use sp_core::U256;
+use sp_runtime::{DispatchError, DispatchResult};
+use sp_std::vec::Vec;
+
+/// Ledger of non-fungible tokens, identified by the asset of their collection and a token id.
+pub trait NonFungibleLedger<AccountId, AssetId> {
+    /// Whether `asset_id` is a collection of non-fungible tokens.
+    fn is_collection(asset_id: &AssetId) -> bool;
+
+    /// Owner of a token, if it exists.
+    fn owner(asset_id: &AssetId, token_id: U256) -> Option<AccountId>;
+
+    /// Creates a token owned by `owner`.
+    fn mint(asset_id: &AssetId, token_id: U256, owner: &AccountId, metadata: Vec<u8>) -> DispatchResult;
+
+    /// Destroys a token owned by `owner`, returning its metadata.
+    fn burn(asset_id: &AssetId, token_id: U256, owner: &AccountId) -> Result<Vec<u8>, DispatchError>;
+
+    /// Moves a token from `from` to `to`.
+    fn transfer(asset_id: &AssetId, token_id: U256, from: &AccountId, to: &AccountId) -> DispatchResult;
+}
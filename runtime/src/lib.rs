@@ -7,18 +7,21 @@
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 
 use sp_std::prelude::*;
-use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
+use sp_core::{crypto::KeyTypeId, OpaqueMetadata, u32_trait::{_1, _2, _3, _4, _5}};
 use sp_runtime::{
-	ApplyExtrinsicResult, generic, create_runtime_str, impl_opaque_keys, MultiSignature,
-	transaction_validity::{TransactionValidity, TransactionSource},
+	ApplyExtrinsicResult, generic, create_runtime_str, impl_opaque_keys, MultiSignature, ModuleId,
+	curve::PiecewiseLinear,
+	transaction_validity::{TransactionValidity, TransactionSource, TransactionPriority},
 };
 use sp_runtime::traits::{
-	BlakeTwo256, Block as BlockT, AccountIdLookup, Verify, IdentifyAccount, NumberFor,
+	BlakeTwo256, Block as BlockT, AccountIdLookup, Verify, IdentifyAccount, NumberFor, AccountIdConversion,
+	OpaqueKeys,
 };
 use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use pallet_grandpa::{AuthorityId as GrandpaId, AuthorityList as GrandpaAuthorityList};
 use pallet_grandpa::fg_primitives;
+use pallet_session::historical as pallet_session_historical;
 use sp_version::RuntimeVersion;
 #[cfg(feature = "std")]
 use sp_version::NativeVersion;
@@ -29,18 +32,29 @@ pub use sp_runtime::BuildStorage;
 pub use pallet_timestamp::Call as TimestampCall;
 pub use pallet_balances::Call as BalancesCall;
 pub use sp_runtime::{Permill, Perbill};
+pub use pallet_staking::StakerStatus;
+pub use pallet_im_online::sr25519::AuthorityId as ImOnlineId;
+pub use call_filter::LaunchPhase;
+use call_filter::Feature;
 pub use frame_support::{
 	construct_runtime, parameter_types, StorageValue,
-	traits::{KeyOwnerProofSystem, Randomness},
+	traits::{Currency, Get, Imbalance, KeyOwnerProofSystem, OnUnbalanced, Randomness, U128CurrencyToVote},
 	weights::{
-		Weight, IdentityFee,
+		Weight, IdentityFee, DispatchClass,
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
 	},
 };
 use pallet_transaction_payment::CurrencyAdapter;
+use frame_system::{EnsureOneOf, EnsureRoot};
 
 use orderbook_engine;
 use orderbook_engine::Config;
+use polkapool;
+use orml_traits::parameter_type_with_key;
+use orml_currencies::BasicCurrencyAdapter;
+pub use polkadex_primitives::assets::AssetId;
+
+mod migrations;
 
 /// An index to a block.
 pub type BlockNumber = u32;
@@ -59,6 +73,9 @@ pub type AccountIndex = u32;
 /// Balance of an account.
 pub type Balance = u128;
 
+/// Signed version of Balance, used by the multi-currency ledger.
+pub type Amount = i128;
+
 /// Index of a transaction in the chain.
 pub type Index = u32;
 
@@ -88,6 +105,7 @@ pub mod opaque {
 		pub struct SessionKeys {
 			pub aura: Aura,
 			pub grandpa: Grandpa,
+			pub im_online: ImOnline,
 		}
 	}
 }
@@ -96,7 +114,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("node-polkadex"),
 	impl_name: create_runtime_str!("node-polkadex"),
 	authoring_version: 1,
-	spec_version: 1,
+	spec_version: 4,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 1,
@@ -143,7 +161,7 @@ parameter_types! {
 
 impl frame_system::Config for Runtime {
 	/// The basic call filter to use in dispatchable.
-	type BaseCallFilter = ();
+	type BaseCallFilter = call_filter::PhaseFilter<Runtime>;
 	/// Block & extrinsics weights: base values and limits.
 	type BlockWeights = BlockWeights;
 	/// The maximum length of a block (in bytes).
@@ -198,7 +216,7 @@ impl pallet_grandpa::Config for Runtime {
 	type Event = Event;
 	type Call = Call;
 
-	type KeyOwnerProofSystem = ();
+	type KeyOwnerProofSystem = Historical;
 
 	type KeyOwnerProof =
 		<Self::KeyOwnerProofSystem as KeyOwnerProofSystem<(KeyTypeId, GrandpaId)>>::Proof;
@@ -208,7 +226,8 @@ impl pallet_grandpa::Config for Runtime {
 		GrandpaId,
 	)>>::IdentificationTuple;
 
-	type HandleEquivocation = ();
+	type HandleEquivocation =
+		pallet_grandpa::EquivocationHandler<Self::KeyOwnerIdentification, Offences, ReportLongevity>;
 
 	type WeightInfo = ();
 }
@@ -246,8 +265,24 @@ parameter_types! {
 	pub const TransactionByteFee: Balance = 1;
 }
 
+type NegativeImbalance = <Balances as Currency<AccountId>>::NegativeImbalance;
+
+/// Sends 20% of the transaction fees and tips to the treasury and burns the rest.
+pub struct DealWithFees;
+impl OnUnbalanced<NegativeImbalance> for DealWithFees {
+	fn on_unbalanceds<B>(mut fees_then_tips: impl Iterator<Item=NegativeImbalance>) {
+		if let Some(mut fees) = fees_then_tips.next() {
+			if let Some(tips) = fees_then_tips.next() {
+				tips.merge_into(&mut fees);
+			}
+			let (to_treasury, _) = fees.ration(20, 80);
+			Treasury::on_unbalanced(to_treasury);
+		}
+	}
+}
+
 impl pallet_transaction_payment::Config for Runtime {
-	type OnChargeTransaction = CurrencyAdapter<Balances, ()>;
+	type OnChargeTransaction = CurrencyAdapter<Balances, DealWithFees>;
 	type TransactionByteFee = TransactionByteFee;
 	type WeightToFee = IdentityFee<Balance>;
 	type FeeMultiplierUpdate = ();
@@ -258,11 +293,440 @@ impl pallet_sudo::Config for Runtime {
 	type Call = Call;
 }
 
+parameter_types! {
+	pub MaximumSchedulerWeight: Weight = Perbill::from_percent(80) * BlockWeights::get().max_block;
+	pub const MaxScheduledPerBlock: u32 = 50;
+}
+
+impl pallet_scheduler::Config for Runtime {
+	type Event = Event;
+	type Origin = Origin;
+	type PalletsOrigin = OriginCaller;
+	type Call = Call;
+	type MaximumWeight = MaximumSchedulerWeight;
+	type ScheduleOrigin = EnsureRoot<AccountId>;
+	type MaxScheduledPerBlock = MaxScheduledPerBlock;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const CouncilMotionDuration: BlockNumber = 3 * DAYS;
+	pub const CouncilMaxProposals: u32 = 100;
+	pub const CouncilMaxMembers: u32 = 100;
+}
+
+type CouncilCollective = pallet_collective::Instance1;
+impl pallet_collective::Config<CouncilCollective> for Runtime {
+	type Origin = Origin;
+	type Proposal = Call;
+	type Event = Event;
+	type MotionDuration = CouncilMotionDuration;
+	type MaxProposals = CouncilMaxProposals;
+	type MaxMembers = CouncilMaxMembers;
+	type DefaultVote = pallet_collective::PrimeDefaultVote;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const TechnicalMotionDuration: BlockNumber = 3 * DAYS;
+	pub const TechnicalMaxProposals: u32 = 100;
+	pub const TechnicalMaxMembers: u32 = 100;
+}
+
+type TechnicalCollective = pallet_collective::Instance2;
+impl pallet_collective::Config<TechnicalCollective> for Runtime {
+	type Origin = Origin;
+	type Proposal = Call;
+	type Event = Event;
+	type MotionDuration = TechnicalMotionDuration;
+	type MaxProposals = TechnicalMaxProposals;
+	type MaxMembers = TechnicalMaxMembers;
+	type DefaultVote = pallet_collective::PrimeDefaultVote;
+	type WeightInfo = ();
+}
+
+/// Root, through a referendum, or a majority of the council.
+type EnsureRootOrHalfCouncil = EnsureOneOf<
+	AccountId,
+	EnsureRoot<AccountId>,
+	pallet_collective::EnsureProportionMoreThan<_1, _2, AccountId, CouncilCollective>,
+>;
+
+parameter_types! {
+	pub const ProposalBond: Permill = Permill::from_percent(5);
+	pub const ProposalBondMinimum: Balance = 100 * 500;
+	pub const SpendPeriod: BlockNumber = 6 * DAYS;
+	pub const Burn: Permill = Permill::from_percent(1);
+	pub const TreasuryModuleId: ModuleId = ModuleId(*b"py/trsry");
+	pub TreasuryAccount: AccountId = TreasuryModuleId::get().into_account();
+}
+
+impl pallet_treasury::Config for Runtime {
+	type ModuleId = TreasuryModuleId;
+	type Currency = Balances;
+	/// Three fifths of the council approve spend proposals.
+	type ApproveOrigin = EnsureOneOf<
+		AccountId,
+		EnsureRoot<AccountId>,
+		pallet_collective::EnsureProportionAtLeast<_3, _5, AccountId, CouncilCollective>,
+	>;
+	type RejectOrigin = EnsureRootOrHalfCouncil;
+	type Event = Event;
+	type OnSlash = Treasury;
+	type ProposalBond = ProposalBond;
+	type ProposalBondMinimum = ProposalBondMinimum;
+	type SpendPeriod = SpendPeriod;
+	type Burn = Burn;
+	type BurnDestination = ();
+	type SpendFunds = ();
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const LaunchPeriod: BlockNumber = 7 * DAYS;
+	pub const VotingPeriod: BlockNumber = 7 * DAYS;
+	pub const FastTrackVotingPeriod: BlockNumber = 3 * HOURS;
+	pub const InstantAllowed: bool = true;
+	pub const MinimumDeposit: Balance = 100 * 500;
+	pub const EnactmentPeriod: BlockNumber = 2 * DAYS;
+	pub const CooloffPeriod: BlockNumber = 7 * DAYS;
+	pub const PreimageByteDeposit: Balance = 1;
+	pub const MaxVotes: u32 = 100;
+	pub const MaxProposals: u32 = 100;
+}
+
+impl pallet_democracy::Config for Runtime {
+	type Proposal = Call;
+	type Event = Event;
+	type Currency = Balances;
+	type EnactmentPeriod = EnactmentPeriod;
+	type LaunchPeriod = LaunchPeriod;
+	type VotingPeriod = VotingPeriod;
+	type MinimumDeposit = MinimumDeposit;
+	/// A majority of the council can have the next scheduled referendum be a straight
+	/// majority-carries vote.
+	type ExternalOrigin = pallet_collective::EnsureProportionAtLeast<_1, _2, AccountId, CouncilCollective>;
+	/// Three quarters of the council can have the next scheduled referendum be a
+	/// majority-carries vote.
+	type ExternalMajorityOrigin = pallet_collective::EnsureProportionAtLeast<_3, _4, AccountId, CouncilCollective>;
+	/// The whole council can have the next scheduled referendum be a negative-turnout-bias vote.
+	type ExternalDefaultOrigin = pallet_collective::EnsureProportionAtLeast<_1, _1, AccountId, CouncilCollective>;
+	/// Two thirds of the technical committee can fast-track an external referendum.
+	type FastTrackOrigin = pallet_collective::EnsureProportionAtLeast<_2, _3, AccountId, TechnicalCollective>;
+	type InstantOrigin = pallet_collective::EnsureProportionAtLeast<_1, _1, AccountId, TechnicalCollective>;
+	type InstantAllowed = InstantAllowed;
+	type FastTrackVotingPeriod = FastTrackVotingPeriod;
+	/// Two thirds of the council can cancel a referendum.
+	type CancellationOrigin = pallet_collective::EnsureProportionAtLeast<_2, _3, AccountId, CouncilCollective>;
+	type CancelProposalOrigin = EnsureOneOf<
+		AccountId,
+		EnsureRoot<AccountId>,
+		pallet_collective::EnsureProportionAtLeast<_1, _1, AccountId, TechnicalCollective>,
+	>;
+	type BlacklistOrigin = EnsureRoot<AccountId>;
+	/// Any member of the technical committee can veto a coming council proposal, once.
+	type VetoOrigin = pallet_collective::EnsureMember<AccountId, TechnicalCollective>;
+	type CooloffPeriod = CooloffPeriod;
+	type PreimageByteDeposit = PreimageByteDeposit;
+	type OperationalPreimageOrigin = pallet_collective::EnsureMember<AccountId, CouncilCollective>;
+	type Slash = Treasury;
+	type Scheduler = Scheduler;
+	type PalletsOrigin = OriginCaller;
+	type MaxVotes = MaxVotes;
+	type WeightInfo = ();
+	type MaxProposals = MaxProposals;
+}
+
+parameter_types! {
+	/// Validator set rotation period.
+	pub const Period: BlockNumber = 6 * HOURS;
+	pub const Offset: BlockNumber = 0;
+	pub const DisabledValidatorsThreshold: Perbill = Perbill::from_percent(17);
+}
+
+impl pallet_session::Config for Runtime {
+	type Event = Event;
+	type ValidatorId = <Self as frame_system::Config>::AccountId;
+	type ValidatorIdOf = pallet_staking::StashOf<Self>;
+	type ShouldEndSession = pallet_session::PeriodicSessions<Period, Offset>;
+	type NextSessionRotation = pallet_session::PeriodicSessions<Period, Offset>;
+	type SessionManager = pallet_session::historical::NoteHistoricalRoot<Self, Staking>;
+	type SessionHandler = <opaque::SessionKeys as OpaqueKeys>::KeyTypeIdProviders;
+	type Keys = opaque::SessionKeys;
+	type DisabledValidatorsThreshold = DisabledValidatorsThreshold;
+	type WeightInfo = ();
+}
+
+impl pallet_session::historical::Config for Runtime {
+	type FullIdentification = pallet_staking::Exposure<AccountId, Balance>;
+	type FullIdentificationOf = pallet_staking::ExposureOf<Runtime>;
+}
+
+parameter_types! {
+	pub const UncleGenerations: BlockNumber = 0;
+}
+
+impl pallet_authorship::Config for Runtime {
+	type FindAuthor = pallet_session::FindAccountFromAuthorIndex<Self, Aura>;
+	type UncleGenerations = UncleGenerations;
+	type FilterUncle = ();
+	type EventHandler = (Staking, ImOnline);
+}
+
+pallet_staking_reward_curve::build! {
+	const REWARD_CURVE: PiecewiseLinear<'static> = curve!(
+		min_inflation: 0_025_000,
+		max_inflation: 0_100_000,
+		ideal_stake: 0_500_000,
+		falloff: 0_050_000,
+		max_piece_count: 40,
+		test_precision: 0_005_000,
+	);
+}
+
+parameter_types! {
+	pub const SessionsPerEra: sp_staking::SessionIndex = 4;
+	pub const BondingDuration: pallet_staking::EraIndex = 4 * 28;
+	/// A quarter of the bonding duration, for governance to cancel unjust slashes.
+	pub const SlashDeferDuration: pallet_staking::EraIndex = 28;
+	pub const RewardCurve: &'static PiecewiseLinear<'static> = &REWARD_CURVE;
+	pub const ElectionLookahead: BlockNumber = Period::get() / 4;
+	pub const MaxNominatorRewardedPerValidator: u32 = 256;
+	pub const MaxIterations: u32 = 10;
+	pub StakingUnsignedPriority: TransactionPriority = TransactionPriority::max_value() / 2;
+	// 0.05%. The higher the value, the more strict solution acceptance becomes.
+	pub MinSolutionScoreBump: Perbill = Perbill::from_rational_approximation(5u32, 10_000);
+	pub OffchainSolutionWeightLimit: Weight = BlockWeights::get()
+		.get(DispatchClass::Normal)
+		.max_extrinsic.expect("Normal extrinsics have a weight limit configured; qed")
+		.saturating_sub(BlockExecutionWeight::get());
+}
+
+impl pallet_staking::Config for Runtime {
+	type Currency = Balances;
+	type UnixTime = Timestamp;
+	type CurrencyToVote = U128CurrencyToVote;
+	type RewardRemainder = Treasury;
+	type Event = Event;
+	/// Slashed funds go to the treasury.
+	type Slash = Treasury;
+	/// Rewards are minted in POLKADEX.
+	type Reward = ();
+	type SessionsPerEra = SessionsPerEra;
+	type BondingDuration = BondingDuration;
+	type SlashDeferDuration = SlashDeferDuration;
+	/// Three quarters of the council can cancel a slash.
+	type SlashCancelOrigin = EnsureOneOf<
+		AccountId,
+		EnsureRoot<AccountId>,
+		pallet_collective::EnsureProportionAtLeast<_3, _4, AccountId, CouncilCollective>,
+	>;
+	type SessionInterface = Self;
+	type RewardCurve = RewardCurve;
+	type NextNewSession = Session;
+	type ElectionLookahead = ElectionLookahead;
+	type Call = Call;
+	type MaxIterations = MaxIterations;
+	type MinSolutionScoreBump = MinSolutionScoreBump;
+	type MaxNominatorRewardedPerValidator = MaxNominatorRewardedPerValidator;
+	type UnsignedPriority = StakingUnsignedPriority;
+	type OffchainSolutionWeightLimit = OffchainSolutionWeightLimit;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const SessionDuration: BlockNumber = Period::get();
+	pub ImOnlineUnsignedPriority: TransactionPriority = TransactionPriority::max_value();
+}
+
+impl pallet_im_online::Config for Runtime {
+	type AuthorityId = ImOnlineId;
+	type Event = Event;
+	type ValidatorSet = Historical;
+	type SessionDuration = SessionDuration;
+	type ReportUnresponsiveness = Offences;
+	type UnsignedPriority = ImOnlineUnsignedPriority;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub OffencesWeightSoftLimit: Weight = Perbill::from_percent(60) * BlockWeights::get().max_block;
+	/// Equivocation reports are kept for as long as offenders can be slashed.
+	pub const ReportLongevity: u64 =
+		BondingDuration::get() as u64 * SessionsPerEra::get() as u64 * Period::get() as u64;
+}
+
+impl pallet_offences::Config for Runtime {
+	type Event = Event;
+	type IdentificationTuple = pallet_session::historical::IdentificationTuple<Self>;
+	type OnOffenceHandler = Staking;
+	type WeightSoftLimit = OffencesWeightSoftLimit;
+}
+
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Runtime where
+	Call: From<C>,
+{
+	type Extrinsic = UncheckedExtrinsic;
+	type OverarchingCall = Call;
+}
+
+parameter_types! {
+	pub const MaxMetadataLength: u32 = 512;
+}
+
+impl nft::Config for Runtime {
+	type Event = Event;
+	type MaxMetadataLength = MaxMetadataLength;
+}
+
+parameter_types! {
+	pub const FreeSettlementsPerBlock: u32 = 100;
+}
+
 impl orderbook_engine::Config for Runtime{
 	type Event = Event;
 	type Balance = Balance;
+	type AssetId = AssetId;
+	type Nft = Nft;
 	type Public = <MultiSignature as Verify>::Signer;
 	type Signature = MultiSignature;
+	type FreeSettlementsPerBlock = FreeSettlementsPerBlock;
+	type AdminOrigin = EnsureRootOrHalfCouncil;
+}
+
+parameter_type_with_key! {
+	pub ExistentialDeposits: |_currency_id: AssetId| -> Balance {
+		0
+	};
+}
+
+impl orml_tokens::Config for Runtime {
+	type Event = Event;
+	type Balance = Balance;
+	type Amount = Amount;
+	type CurrencyId = AssetId;
+	type WeightInfo = ();
+	type ExistentialDeposits = ExistentialDeposits;
+	type OnDust = ();
+}
+
+parameter_types! {
+	pub const GetNativeCurrencyId: AssetId = AssetId::POLKADEX;
+}
+
+impl orml_currencies::Config for Runtime {
+	type Event = Event;
+	type MultiCurrency = Tokens;
+	type NativeCurrency = BasicCurrencyAdapter<Runtime, Balances, Amount, BlockNumber>;
+	type GetNativeCurrencyId = GetNativeCurrencyId;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const TradingPathLimit: usize = 3;
+	pub const TwapObservationPeriod: u32 = HOURS;
+	pub const MaxAmplification: u32 = 1_000_000;
+	pub const MinAmplificationRampDuration: BlockNumber = DAYS;
+	pub const MaxWeightedPoolAssets: u32 = 8;
+	pub const MaxTicksPerPool: u32 = 1_000;
+}
+
+impl polkapool::Config for Runtime {
+	type Event = Event;
+	type TradingPathLimit = TradingPathLimit;
+	type Balance = Balance;
+	type TwapObservationPeriod = TwapObservationPeriod;
+	type UpdateOrigin = EnsureRootOrHalfCouncil;
+	type MaxAmplification = MaxAmplification;
+	type MinAmplificationRampDuration = MinAmplificationRampDuration;
+	type MaxWeightedPoolAssets = MaxWeightedPoolAssets;
+	type MaxTicksPerPool = MaxTicksPerPool;
+	type CurrencyId = AssetId;
+	type Currency = Currencies;
+	type ProtocolFeeAccount = TreasuryAccount;
+	type Call = Call;
+}
+
+parameter_types! {
+	pub const OrderbookQuoteAsset: AssetId = AssetId::POLKADEX;
+	pub const MaxMakerOrders: u32 = 50;
+	pub const OrderbookTradingFee: Permill = Permill::from_parts(1_000);
+}
+
+impl hybrid_router::Config for Runtime {
+	type Event = Event;
+	type QuoteAsset = OrderbookQuoteAsset;
+	type MaxMakerOrders = MaxMakerOrders;
+	type TradingFee = OrderbookTradingFee;
+	type TradingFeeAccount = TreasuryAccount;
+}
+
+parameter_types! {
+	pub const BridgeChainId: u8 = 1;
+	pub const ProposalLifetime: BlockNumber = HOURS;
+}
+
+impl chainbridge::Config for Runtime {
+	type Event = Event;
+	type Currency = Currencies;
+	type Nft = Nft;
+	type AdminOrigin = EnsureRootOrHalfCouncil;
+	type ChainId = BridgeChainId;
+	type ProposalLifetime = ProposalLifetime;
+}
+
+parameter_types! {
+	/// Same split as `DealWithFees`.
+	pub const AssetTxFeeTreasuryShare: Permill = Permill::from_percent(20);
+	pub const AssetTxFeePriceWindow: BlockNumber = 10 * MINUTES;
+}
+
+impl asset_tx_payment::Config for Runtime {
+	type Event = Event;
+	type NativeAssetId = GetNativeCurrencyId;
+	type FeeAccount = TreasuryAccount;
+	type FeeAccountShare = AssetTxFeeTreasuryShare;
+	type PriceWindow = AssetTxFeePriceWindow;
+}
+
+/// Sorts the calls of the runtime into the features switched by the launch phases. Calls of
+/// governance, staking and the administration of the pallets are never filtered.
+pub struct RuntimeCallClassifier;
+
+impl call_filter::ClassifyCall<Call> for RuntimeCallClassifier {
+	fn feature(call: &Call) -> Option<Feature> {
+		match call {
+			Call::Balances(_) | Call::Currencies(_) | Call::Nft(_) => Some(Feature::Transfers),
+			Call::Engine(orderbook_engine::Call::settle_trade(..)) => Some(Feature::Orderbook),
+			Call::Polkapool(polkapool::Call::set_max_price_impact(..))
+			| Call::Polkapool(polkapool::Call::set_protocol_fee(..))
+			| Call::Polkapool(polkapool::Call::withdraw_protocol_fee(..))
+			| Call::Polkapool(polkapool::Call::set_pool_max_price_impact(..))
+			| Call::Polkapool(polkapool::Call::ramp_amplification(..))
+			| Call::Polkapool(polkapool::Call::stop_amplification_ramp(..)) => None,
+			Call::Polkapool(_) | Call::HybridRouter(_) => Some(Feature::Swaps),
+			Call::ChainBridge(chainbridge::Call::acknowledge_proposal(..))
+			| Call::ChainBridge(chainbridge::Call::reject_proposal(..))
+			| Call::ChainBridge(chainbridge::Call::eval_vote_state(..))
+			| Call::ChainBridge(chainbridge::Call::transfer_fungible(..))
+			| Call::ChainBridge(chainbridge::Call::transfer_nonfungible(..)) => Some(Feature::Bridge),
+			_ => None,
+		}
+	}
+}
+
+/// Root or half of the technical committee, to pause features quickly during incidents.
+type EnsureRootOrHalfTechnicalCommittee = EnsureOneOf<
+	AccountId,
+	EnsureRoot<AccountId>,
+	pallet_collective::EnsureProportionAtLeast<_1, _2, AccountId, TechnicalCollective>,
+>;
+
+impl call_filter::Config for Runtime {
+	type Event = Event;
+	type CallClassifier = RuntimeCallClassifier;
+	type UpdateOrigin = EnsureRootOrHalfCouncil;
+	type PauseOrigin = EnsureRootOrHalfTechnicalCommittee;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -276,11 +740,30 @@ construct_runtime!(
 		RandomnessCollectiveFlip: pallet_randomness_collective_flip::{Module, Call, Storage},
 		Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
 		Aura: pallet_aura::{Module, Config<T>},
-		Grandpa: pallet_grandpa::{Module, Call, Storage, Config, Event},
+		Authorship: pallet_authorship::{Module, Call, Storage, Inherent},
+		Staking: pallet_staking::{Module, Call, Config<T>, Storage, Event<T>, ValidateUnsigned},
+		Session: pallet_session::{Module, Call, Storage, Event, Config<T>},
+		Historical: pallet_session_historical::{Module},
+		Grandpa: pallet_grandpa::{Module, Call, Storage, Config, Event, ValidateUnsigned},
+		ImOnline: pallet_im_online::{Module, Call, Storage, Event<T>, ValidateUnsigned, Config<T>},
+		Offences: pallet_offences::{Module, Call, Storage, Event},
 		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
 		TransactionPayment: pallet_transaction_payment::{Module, Storage},
 		Sudo: pallet_sudo::{Module, Call, Config<T>, Storage, Event<T>},
-		Engine: orderbook_engine::{Module, Call, Storage, Event<T>}
+		Scheduler: pallet_scheduler::{Module, Call, Storage, Event<T>},
+		Treasury: pallet_treasury::{Module, Call, Storage, Config, Event<T>},
+		Democracy: pallet_democracy::{Module, Call, Storage, Config, Event<T>},
+		Council: pallet_collective::<Instance1>::{Module, Call, Storage, Origin<T>, Event<T>, Config<T>},
+		TechnicalCommittee: pallet_collective::<Instance2>::{Module, Call, Storage, Origin<T>, Event<T>, Config<T>},
+		Nft: nft::{Module, Call, Storage, Event<T>},
+		Engine: orderbook_engine::{Module, Call, Storage, Event<T>},
+		Tokens: orml_tokens::{Module, Storage, Event<T>},
+		Currencies: orml_currencies::{Module, Call, Event<T>},
+		Polkapool: polkapool::{Module, Call, Storage, Event<T>},
+		HybridRouter: hybrid_router::{Module, Call, Event<T>},
+		ChainBridge: chainbridge::{Module, Call, Storage, Event<T>},
+		AssetTxPayment: asset_tx_payment::{Module, Event<T>},
+		CallFilter: call_filter::{Module, Call, Storage, Event, Config},
 	}
 );
 
@@ -302,7 +785,7 @@ pub type SignedExtra = (
 	frame_system::CheckEra<Runtime>,
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
-	pallet_transaction_payment::ChargeTransactionPayment<Runtime>
+	asset_tx_payment::ChargeAssetTxPayment<Runtime>
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
@@ -315,6 +798,7 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllModules,
+	migrations::RemoveSudoKey,
 >;
 
 impl_runtime_apis! {
@@ -406,23 +890,29 @@ impl_runtime_apis! {
 		}
 
 		fn submit_report_equivocation_unsigned_extrinsic(
-			_equivocation_proof: fg_primitives::EquivocationProof<
+			equivocation_proof: fg_primitives::EquivocationProof<
 				<Block as BlockT>::Hash,
 				NumberFor<Block>,
 			>,
-			_key_owner_proof: fg_primitives::OpaqueKeyOwnershipProof,
+			key_owner_proof: fg_primitives::OpaqueKeyOwnershipProof,
 		) -> Option<()> {
-			None
+			let key_owner_proof = key_owner_proof.decode()?;
+
+			Grandpa::submit_unsigned_equivocation_report(
+				equivocation_proof,
+				key_owner_proof,
+			)
 		}
 
 		fn generate_key_ownership_proof(
 			_set_id: fg_primitives::SetId,
-			_authority_id: GrandpaId,
+			authority_id: GrandpaId,
 		) -> Option<fg_primitives::OpaqueKeyOwnershipProof> {
-			// NOTE: this is the only implementation possible since we've
-			// defined our key owner proof type as a bottom type (i.e. a type
-			// with no values).
-			None
+			use codec::Encode;
+
+			Historical::prove((fg_primitives::KEY_TYPE, authority_id))
+				.map(|p| p.encode())
+				.map(fg_primitives::OpaqueKeyOwnershipProof::new)
 		}
 	}
 
@@ -447,6 +937,50 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl polkapool_rpc_runtime_api::PolkapoolApi<Block, AccountId, AssetId, Balance> for Runtime {
+		fn quote_swap_with_exact_supply(
+			path: Vec<AssetId>,
+			supply_amount: Balance,
+		) -> Result<polkapool_rpc_runtime_api::SwapQuote<Balance>, sp_runtime::DispatchError> {
+			Polkapool::quote_swap_with_exact_supply(&path, supply_amount)
+		}
+
+		fn quote_swap_with_exact_target(
+			path: Vec<AssetId>,
+			target_amount: Balance,
+		) -> Result<polkapool_rpc_runtime_api::SwapQuote<Balance>, sp_runtime::DispatchError> {
+			Polkapool::quote_swap_with_exact_target(&path, target_amount)
+		}
+
+		fn get_pool_reserves(currency_id_a: AssetId, currency_id_b: AssetId) -> (Balance, Balance, Balance) {
+			Polkapool::get_pool_reserves(currency_id_a, currency_id_b)
+		}
+
+		fn get_lp_holdings(who: AccountId, currency_id_a: AssetId, currency_id_b: AssetId) -> Balance {
+			Polkapool::get_lp_holdings(&who, currency_id_a, currency_id_b)
+		}
+
+		fn get_accrued_protocol_fee(currency_id_a: AssetId, currency_id_b: AssetId) -> Balance {
+			Polkapool::get_accrued_protocol_fee(currency_id_a, currency_id_b)
+		}
+
+		fn find_best_route_with_exact_supply(
+			supply_currency_id: AssetId,
+			target_currency_id: AssetId,
+			supply_amount: Balance,
+		) -> Option<polkapool_rpc_runtime_api::SwapRoute<AssetId, Balance>> {
+			Polkapool::find_best_route_with_exact_supply(supply_currency_id, target_currency_id, supply_amount)
+		}
+
+		fn find_best_route_with_exact_target(
+			supply_currency_id: AssetId,
+			target_currency_id: AssetId,
+			target_amount: Balance,
+		) -> Option<polkapool_rpc_runtime_api::SwapRoute<AssetId, Balance>> {
+			Polkapool::find_best_route_with_exact_target(supply_currency_id, target_currency_id, target_amount)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn dispatch_benchmark(
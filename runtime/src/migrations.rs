@@ -0,0 +1,25 @@
+//! Storage migrations run by `Executive` on runtime upgrades.
+
+use frame_support::traits::{Get, OnRuntimeUpgrade};
+use frame_support::storage::migration::remove_storage_prefix;
+use frame_support::weights::Weight;
+
+use crate::{Council, Runtime, TechnicalCommittee};
+
+/// Clears the root key of `pallet_sudo` once the council and the technical committee have members.
+///
+/// The collectives start empty on a running chain, so the key is kept through the upgrade that
+/// adds them and is used to set their members. The first upgrade after that clears it, and `Sudo`
+/// can then be removed from the runtime.
+pub struct RemoveSudoKey;
+
+impl OnRuntimeUpgrade for RemoveSudoKey {
+	fn on_runtime_upgrade() -> Weight {
+		let db_weight = <Runtime as frame_system::Config>::DbWeight::get();
+		if Council::members().is_empty() || TechnicalCommittee::members().is_empty() {
+			return db_weight.reads(2);
+		}
+		remove_storage_prefix(b"Sudo", b"Key", &[]);
+		db_weight.reads_writes(2, 1)
+	}
+}